@@ -26,6 +26,70 @@ use async_io::Async;
 ))]
 use smol::Async;
 
+#[cfg(any(feature = "async-io", feature = "async-std"))]
+#[cfg(feature = "dump")]
+use async_io::Timer;
+
+#[cfg(all(
+    feature = "smol",
+    feature = "dump",
+    not(any(feature = "async-io", feature = "async-std"))
+))]
+use smol::Timer;
+
+#[cfg(any(feature = "async-io", feature = "async-std"))]
+#[cfg(feature = "dump")]
+use futures_lite::{
+    io::{AsyncBufRead, AsyncRead, BufReader},
+    stream::StreamExt,
+};
+
+#[cfg(all(
+    feature = "smol",
+    feature = "dump",
+    not(any(feature = "async-io", feature = "async-std"))
+))]
+use smol::{
+    io::{AsyncBufRead, AsyncRead, BufReader},
+    stream::StreamExt,
+};
+
+#[cfg(any(feature = "async-io", feature = "async-std"))]
+#[cfg(any(feature = "dump", feature = "netlink"))]
+use futures_lite::{ready, stream::Stream};
+
+#[cfg(all(
+    feature = "smol",
+    any(feature = "dump", feature = "netlink"),
+    not(any(feature = "async-io", feature = "async-std"))
+))]
+use smol::{ready, stream::Stream};
+
+#[cfg(feature = "dump")]
+use crate::{
+    dump::{AsyncLines, ParseError},
+    logfile::{Error as LogError, LogRecord},
+    replay::{AsyncFrameSink, AsyncPlayer, ReplayOptions},
+};
+#[cfg(any(feature = "dump", feature = "netlink"))]
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+#[cfg(feature = "netlink")]
+use crate::nl::{LinkEvent, NlInfoError};
+#[cfg(feature = "netlink")]
+use neli::{
+    consts::{nl::NlType, rtnl::Rtm},
+    err::NlError,
+    nl::Nlmsghdr,
+    rtnl::Ifinfomsg,
+    FromBytesWithInput,
+};
+#[cfg(feature = "netlink")]
+use std::fmt::Debug;
+
 /////////////////////////////////////////////////////////////////////////////
 
 /// An asynchronous CAN socket for use with `async-io`.
@@ -115,3 +179,381 @@ impl AsRawFd for CanFdSocket {
         self.0.as_raw_fd()
     }
 }
+
+/////////////////////////////////////////////////////////////////////////////
+
+/// An asynchronous candump log reader.
+///
+/// This is the async equivalent of [`dump::Reader`](crate::dump::Reader),
+/// and is a `Stream` of the records in the log. The stream ends at the
+/// end of the input, unless the reader is set to
+/// [follow](Self::set_follow) a log that is still being written.
+#[cfg(feature = "dump")]
+#[derive(Debug)]
+pub struct DumpReader<R>(AsyncLines<R, Timer>);
+
+#[cfg(feature = "dump")]
+impl<R: AsyncRead + Unpin> DumpReader<BufReader<R>> {
+    /// Creates an I/O buffered reader from an asynchronous reader.
+    pub fn from_reader(rdr: R) -> Self {
+        DumpReader::new(BufReader::new(rdr))
+    }
+}
+
+#[cfg(feature = "dump")]
+impl<R: AsyncBufRead + Unpin> DumpReader<R> {
+    /// Creates a reader from a buffered asynchronous reader.
+    pub fn new(rdr: R) -> Self {
+        Self(AsyncLines::new(rdr))
+    }
+
+    /// Sets whether the reader is lenient.
+    ///
+    /// See [`dump::Reader::set_lenient`](crate::dump::Reader::set_lenient).
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.0.lines.parser.lenient = lenient;
+    }
+
+    /// Determines if the reader is lenient.
+    pub fn is_lenient(&self) -> bool {
+        self.0.lines.parser.lenient
+    }
+
+    /// Sets whether the reader follows a log that is still being written,
+    /// like `tail -f`.
+    ///
+    /// When following, the reader waits for more data at the end of the
+    /// input, checking for it periodically, so the stream never ends. A
+    /// partial line at the end of the input is kept until its newline
+    /// arrives. Otherwise the stream ends at the end of the input, and a
+    /// last line without a newline is taken as complete.
+    pub fn set_follow(&mut self, follow: bool) {
+        self.0.lines.follow = follow;
+    }
+
+    /// Determines if the reader follows the log.
+    pub fn is_following(&self) -> bool {
+        self.0.lines.follow
+    }
+
+    /// Gets the number of malformed lines skipped by a lenient reader.
+    pub fn skipped(&self) -> u64 {
+        self.0.lines.parser.skipped
+    }
+
+    /// Gets the number of lines read so far.
+    pub fn line_number(&self) -> u64 {
+        self.0.lines.parser.line
+    }
+
+    /// Reads the next record from the log.
+    ///
+    /// Returns `None` at the end of the input.
+    pub async fn next_record(&mut self) -> Result<Option<LogRecord>, ParseError> {
+        self.next().await.transpose()
+    }
+}
+
+#[cfg(feature = "dump")]
+impl<R: AsyncBufRead + Unpin> Stream for DumpReader<R> {
+    type Item = Result<LogRecord, ParseError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .0
+            .poll_next(cx, R::poll_fill_buf, R::consume, Timer::after)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+/// An asynchronous player to replay CAN logs to sockets.
+///
+/// This is the async equivalent of [`replay::Player`](crate::replay::Player).
+/// The sink for each interface is opened the first time that it is needed,
+/// unless one was already added to the player.
+#[cfg(feature = "dump")]
+#[derive(Debug)]
+pub struct Player<S = CanFdSocket>(AsyncPlayer<S>);
+
+#[cfg(feature = "dump")]
+impl<S: AsyncFrameSink> Player<S> {
+    /// Creates a player with the specified options.
+    pub fn new(opts: ReplayOptions) -> Self {
+        Self(AsyncPlayer::new(opts))
+    }
+
+    /// Gets the options for the player.
+    pub fn options(&self) -> &ReplayOptions {
+        self.0.options()
+    }
+
+    /// Adds the sink to use for the named output interface.
+    pub fn add_sink<T: Into<String>>(&mut self, iface: T, sink: S) {
+        self.0.add_sink(iface.into(), sink);
+    }
+
+    /// Plays the records from a log.
+    ///
+    /// The records come from an async stream, like a [`DumpReader`], so
+    /// that reading the log doesn't block the executor.
+    ///
+    /// On success, returns the number of frames that were sent.
+    pub async fn play<R, E>(&mut self, records: R) -> Result<u64, LogError>
+    where
+        R: Stream<Item = Result<LogRecord, E>> + Unpin,
+        LogError: From<E>,
+    {
+        self.0.play(records, R::poll_next, Timer::at).await
+    }
+
+    /// Plays a log repeatedly.
+    ///
+    /// See [`replay::Player::play_loop`](crate::replay::Player::play_loop).
+    pub async fn play_loop<R, E, F>(&mut self, loops: Option<u32>, open: F) -> Result<u64, LogError>
+    where
+        R: Stream<Item = Result<LogRecord, E>> + Unpin,
+        LogError: From<E>,
+        F: FnMut() -> Result<R, LogError>,
+    {
+        self.0.play_loop(loops, open, R::poll_next, Timer::at).await
+    }
+}
+
+#[cfg(feature = "dump")]
+impl AsyncFrameSink for CanFdSocket {
+    fn open_sink(iface: &str) -> io::Result<Self> {
+        CanFdSocket::open(iface)
+    }
+
+    fn poll_send_frame(
+        &mut self,
+        cx: &mut Context<'_>,
+        frame: &CanAnyFrame,
+    ) -> Poll<io::Result<()>> {
+        loop {
+            ready!(self.0.poll_writable(cx))?;
+            match self.0.get_ref().write_frame(frame) {
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => continue,
+                res => return Poll::Ready(res),
+            }
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+/// An asynchronous monitor for changes to the CAN interfaces.
+///
+/// This is the async equivalent of [`nl::LinkMonitor`](crate::nl::LinkMonitor),
+/// and is a `Stream` of the events.
+#[cfg(feature = "netlink")]
+#[derive(Debug)]
+pub struct LinkMonitor(Async<crate::nl::LinkMonitor>);
+
+#[cfg(feature = "netlink")]
+impl LinkMonitor {
+    /// Creates a monitor for the CAN interfaces.
+    pub fn new() -> Result<Self, NlInfoError> {
+        Ok(Self(Async::new(crate::nl::LinkMonitor::new()?)?))
+    }
+
+    /// Gets a reference to the underlying blocking monitor.
+    pub fn get_ref(&self) -> &crate::nl::LinkMonitor {
+        self.0.get_ref()
+    }
+
+    /// Waits for the next event.
+    pub async fn next_event(&mut self) -> Result<LinkEvent, NlInfoError> {
+        std::future::poll_fn(|cx| self.poll_event(cx)).await
+    }
+
+    /// Polls for the next event.
+    fn poll_event(&mut self, cx: &mut Context) -> Poll<Result<LinkEvent, NlInfoError>> {
+        loop {
+            match self.0.get_mut().next_event() {
+                Ok(Some(event)) => return Poll::Ready(Ok(event)),
+                Ok(None) => ready!(self.0.poll_readable(cx))?,
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "netlink")]
+impl Stream for LinkMonitor {
+    type Item = Result<LinkEvent, NlInfoError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_event(cx).map(Some)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+/// Sends a request to the kernel on a new, non-blocking, netlink socket,
+/// and waits for the response.
+///
+/// This is all that the async [`CanInterface`] needs from the runtime.
+#[cfg(feature = "netlink")]
+async fn nl_request<T, P>(
+    hdr: Nlmsghdr<Rtm, Ifinfomsg>,
+) -> Result<Option<Nlmsghdr<T, P>>, NlError<T, P>>
+where
+    T: NlType + Debug,
+    P: for<'a> FromBytesWithInput<'a, Input = usize> + Debug,
+{
+    let mut sock = crate::nl::CanInterface::open_route_socket()?;
+    sock.send(hdr)?;
+
+    let mut sock = Async::new(sock)?;
+    loop {
+        match sock.get_mut().recv::<T, P>() {
+            Ok(None) => sock.readable().await?,
+            res => return res,
+        }
+    }
+}
+
+#[cfg(feature = "netlink")]
+crate::nl::async_can_interface!();
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "dump")]
+#[cfg(test)]
+mod dump_tests {
+    use super::*;
+    use crate::{CanDataFrame, EmbeddedFrame, Frame, StandardId};
+    use futures::executor::block_on;
+    use futures::stream;
+    use std::{
+        cell::RefCell,
+        rc::Rc,
+        time::{Duration, Instant, SystemTime},
+    };
+
+    const LOG: &[u8] = b"(1469439874.299591) can1 080#\n\
+                         (1469439874.299654) can1 701#7F\n\
+                         (1469439874.29x654) can1 123#\r\n\
+                         (1469439874.299700) vcan0 00000050#0102";
+
+    // Frames on "can1" at 0, 100, and 150 ms, and on "can2" at 50 ms
+    fn play_log() -> impl Stream<Item = io::Result<LogRecord>> + Unpin {
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_469_439_874);
+        let rec = |ms, iface, id, data: &[u8]| {
+            let frame = CanDataFrame::new(StandardId::new(id).unwrap(), data).unwrap();
+            Ok(LogRecord::new(
+                t0 + Duration::from_millis(ms),
+                iface,
+                CanAnyFrame::Normal(frame),
+            ))
+        };
+        stream::iter(vec![
+            rec(0, "can1", 0x080, &[]),
+            rec(50, "can2", 0x701, &[0x7F]),
+            rec(100, "can1", 0x123, &[0x01]),
+            rec(150, "can1", 0x124, &[0x02, 0x03]),
+        ])
+    }
+
+    /// A sink that keeps the time that each frame was sent
+    struct TestSink(Rc<RefCell<Vec<(Instant, CanAnyFrame)>>>);
+
+    impl AsyncFrameSink for TestSink {
+        fn open_sink(_iface: &str) -> io::Result<Self> {
+            Err(io::ErrorKind::NotFound.into())
+        }
+
+        fn poll_send_frame(
+            &mut self,
+            _cx: &mut Context<'_>,
+            frame: &CanAnyFrame,
+        ) -> Poll<io::Result<()>> {
+            self.0.borrow_mut().push((Instant::now(), *frame));
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[async_std::test]
+    async fn test_player() {
+        let mut opts = ReplayOptions::default();
+        opts.add_mapping("can1=vcan0").unwrap();
+
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let mut player = Player::new(opts);
+        player.add_sink("vcan0", TestSink(sent.clone()));
+
+        let start = Instant::now();
+        assert_eq!(3, player.play(play_log()).await.unwrap());
+
+        let sent = sent.borrow();
+        let ids: Vec<_> = sent
+            .iter()
+            .map(|(_, frame)| match frame {
+                CanAnyFrame::Normal(frame) => frame.raw_id(),
+                _ => panic!("Expected a data frame"),
+            })
+            .collect();
+        assert_eq!(vec![0x080, 0x123, 0x124], ids);
+
+        // The frames keep their times from the start of the replay
+        assert!(sent[0].0 - start < Duration::from_millis(100));
+        assert!(sent[1].0 - start >= Duration::from_millis(100));
+        assert!(sent[2].0 - start >= Duration::from_millis(150));
+    }
+
+    #[async_std::test]
+    async fn test_player_errors() {
+        let mut opts = ReplayOptions::default();
+        opts.map_interface("can9", "vcan9");
+        let mut player = Player::<TestSink>::new(opts);
+
+        // None of the records are for the mapped interface, so nothing is sent
+        assert_eq!(0, player.play(play_log()).await.unwrap());
+
+        // An error from the log stops the replay
+        let err = io::Error::from(io::ErrorKind::InvalidData);
+        let err = player.play(stream::iter(vec![Err(err)])).await.unwrap_err();
+        assert!(matches!(err, LogError::Io(err) if err.kind() == io::ErrorKind::InvalidData));
+
+        // As does a sink that can't be opened
+        let mut player = Player::<TestSink>::new(ReplayOptions::default());
+        let err = player.play(play_log()).await.unwrap_err();
+        assert!(matches!(err, LogError::Io(err) if err.kind() == io::ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn test_dump_reader() {
+        let recs = block_on(DumpReader::from_reader(LOG).take(2).collect::<Vec<_>>());
+        let rec = recs[1].as_ref().unwrap();
+        assert_eq!("can1", rec.interface);
+        let CanAnyFrame::Normal(frame) = rec.frame else {
+            panic!("Expected a data frame");
+        };
+        assert_eq!(0x701, frame.raw_id());
+        assert_eq!(&[0x7F], frame.data());
+
+        let mut rdr = DumpReader::from_reader(LOG);
+        rdr.set_lenient(true);
+        let recs = block_on((&mut rdr).collect::<Vec<_>>());
+        assert_eq!(3, recs.len());
+        assert_eq!(1, rdr.skipped());
+        assert_eq!(4, rdr.line_number());
+
+        let rec = recs[2].as_ref().unwrap();
+        assert_eq!("vcan0", rec.interface);
+        let CanAnyFrame::Normal(frame) = rec.frame else {
+            panic!("Expected a data frame");
+        };
+        assert!(frame.is_extended());
+        assert_eq!(&[1, 2], frame.data());
+
+        let mut rdr = DumpReader::from_reader(LOG);
+        let err = block_on((&mut rdr).collect::<Vec<_>>())
+            .into_iter()
+            .find_map(|res| res.err())
+            .unwrap();
+        assert_eq!(3, err.position().unwrap().line);
+    }
+}
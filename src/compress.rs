@@ -0,0 +1,393 @@
+// socketcan/src/compress.rs
+//
+// Implements transparent compression for CAN log files.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Transparent compression for CAN log files.
+//!
+//! Long captures are commonly stored compressed. The [`Decoder`] and
+//! [`Encoder`] here wrap a reader or writer of a log file, and handle the
+//! compression, if any, so that the logs can be processed as a stream
+//! without decompressing them to disk first.
+//!
+//! Each type of compression is supported behind an optional feature:
+//!
+//! * **gzip** - `.gz` files, using [flate2](https://crates.io/crates/flate2)
+//! * **xz** - `.xz` files, using [xz2](https://crates.io/crates/xz2)
+//! * **zstd** - `.zst` files, using [zstd](https://crates.io/crates/zstd)
+//!
+//! When reading, the compression is detected from the magic bytes at the
+//! start of the file. When writing, it's chosen from the file extension.
+
+use std::{
+    fmt,
+    io::{self, BufRead, Read, Write},
+    path::Path,
+};
+
+/// The magic bytes at the start of a gzip file
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+/// The magic bytes at the start of an xz file
+const XZ_MAGIC: &[u8] = &[0xFD, b'7', b'z', b'X', b'Z', 0x00];
+/// The magic bytes at the start of a zstd file
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+
+// ===== Compression =====
+
+/// The type of compression used for a log file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// An uncompressed file
+    #[default]
+    None,
+    /// gzip compression (.gz)
+    Gzip,
+    /// xz compression (.xz)
+    Xz,
+    /// Zstandard compression (.zst)
+    Zstd,
+}
+
+impl Compression {
+    /// Determines the compression from the extension of a file name.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("xz") => Compression::Xz,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    /// Determines the compression from the magic bytes at the start of
+    /// a file.
+    pub fn detect(buf: &[u8]) -> Self {
+        if buf.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if buf.starts_with(XZ_MAGIC) {
+            Compression::Xz
+        } else if buf.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// Determines if the compression is supported by the features
+    /// enabled in this build.
+    pub fn is_supported(&self) -> bool {
+        match *self {
+            Compression::None => true,
+            Compression::Gzip => cfg!(feature = "gzip"),
+            Compression::Xz => cfg!(feature = "xz"),
+            Compression::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    /// Gets the error for a compression that isn't supported.
+    fn unsupported(&self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} support is not enabled", self),
+        )
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Compression::None => write!(f, "none"),
+            Compression::Gzip => write!(f, "gzip"),
+            Compression::Xz => write!(f, "xz"),
+            Compression::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+// ===== Decoder =====
+
+/// A reader that decompresses a log file.
+pub enum Decoder<R: BufRead> {
+    /// An uncompressed file
+    Plain(R),
+    /// A gzip file
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::bufread::MultiGzDecoder<R>),
+    /// An xz file
+    #[cfg(feature = "xz")]
+    Xz(xz2::bufread::XzDecoder<R>),
+    /// A Zstandard file
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::read::Decoder<'static, R>),
+}
+
+impl<R: BufRead> Decoder<R> {
+    /// Creates a decoder, detecting the compression from the start of
+    /// the stream.
+    ///
+    /// This fails with an `Unsupported` error if the stream is compressed,
+    /// but support for that type of compression is not enabled.
+    pub fn new(mut rdr: R) -> io::Result<Self> {
+        let comp = Compression::detect(rdr.fill_buf()?);
+        Self::with_compression(rdr, comp)
+    }
+
+    /// Creates a decoder for the specified type of compression.
+    pub fn with_compression(rdr: R, comp: Compression) -> io::Result<Self> {
+        match comp {
+            Compression::None => Ok(Decoder::Plain(rdr)),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Ok(Decoder::Gzip(flate2::bufread::MultiGzDecoder::new(rdr))),
+            #[cfg(feature = "xz")]
+            Compression::Xz => Ok(Decoder::Xz(xz2::bufread::XzDecoder::new_multi_decoder(rdr))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(Decoder::Zstd(zstd::stream::read::Decoder::with_buffer(
+                rdr,
+            )?)),
+            #[allow(unreachable_patterns)]
+            _ => Err(comp.unsupported()),
+        }
+    }
+
+    /// Gets the type of compression being decoded.
+    pub fn compression(&self) -> Compression {
+        match *self {
+            Decoder::Plain(_) => Compression::None,
+            #[cfg(feature = "gzip")]
+            Decoder::Gzip(_) => Compression::Gzip,
+            #[cfg(feature = "xz")]
+            Decoder::Xz(_) => Compression::Xz,
+            #[cfg(feature = "zstd")]
+            Decoder::Zstd(_) => Compression::Zstd,
+        }
+    }
+}
+
+impl<R: BufRead> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Decoder::Plain(rdr) => rdr.read(buf),
+            #[cfg(feature = "gzip")]
+            Decoder::Gzip(rdr) => rdr.read(buf),
+            #[cfg(feature = "xz")]
+            Decoder::Xz(rdr) => rdr.read(buf),
+            #[cfg(feature = "zstd")]
+            Decoder::Zstd(rdr) => rdr.read(buf),
+        }
+    }
+}
+
+impl<R: BufRead> fmt::Debug for Decoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Decoder")
+            .field("compression", &self.compression())
+            .finish()
+    }
+}
+
+// ===== Encoder =====
+
+/// A writer that compresses a log file.
+///
+/// The compressed stream is finished when the encoder is dropped, but any
+/// errors are then lost. Call [`try_finish`](Encoder::try_finish) to get
+/// them.
+pub enum Encoder<W: Write> {
+    /// An uncompressed file
+    Plain(W),
+    /// A gzip file
+    #[cfg(feature = "gzip")]
+    Gzip(flate2::write::GzEncoder<W>),
+    /// An xz file
+    #[cfg(feature = "xz")]
+    Xz(xz2::write::XzEncoder<W>),
+    /// A Zstandard file
+    #[cfg(feature = "zstd")]
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Creates an encoder for the specified type of compression, using
+    /// the default compression level.
+    ///
+    /// This fails with an `Unsupported` error if support for the type of
+    /// compression is not enabled.
+    pub fn new(wtr: W, comp: Compression) -> io::Result<Self> {
+        match comp {
+            Compression::None => Ok(Encoder::Plain(wtr)),
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Ok(Encoder::Gzip(flate2::write::GzEncoder::new(
+                wtr,
+                flate2::Compression::default(),
+            ))),
+            #[cfg(feature = "xz")]
+            Compression::Xz => Ok(Encoder::Xz(xz2::write::XzEncoder::new(wtr, 6))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => Ok(Encoder::Zstd(zstd::stream::write::Encoder::new(wtr, 0)?)),
+            #[allow(unreachable_patterns)]
+            _ => Err(comp.unsupported()),
+        }
+    }
+
+    /// Gets the type of compression being encoded.
+    pub fn compression(&self) -> Compression {
+        match *self {
+            Encoder::Plain(_) => Compression::None,
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(_) => Compression::Gzip,
+            #[cfg(feature = "xz")]
+            Encoder::Xz(_) => Compression::Xz,
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(_) => Compression::Zstd,
+        }
+    }
+
+    /// Finishes the compressed stream and flushes the underlying writer.
+    ///
+    /// Nothing more should be written to the encoder after this.
+    pub fn try_finish(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(wtr) => wtr.flush(),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(wtr) => {
+                wtr.try_finish()?;
+                wtr.get_mut().flush()
+            }
+            #[cfg(feature = "xz")]
+            Encoder::Xz(wtr) => {
+                wtr.try_finish()?;
+                wtr.get_mut().flush()
+            }
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(wtr) => {
+                wtr.do_finish()?;
+                wtr.get_mut().flush()
+            }
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(wtr) => wtr.write(buf),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(wtr) => wtr.write(buf),
+            #[cfg(feature = "xz")]
+            Encoder::Xz(wtr) => wtr.write(buf),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(wtr) => wtr.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(wtr) => wtr.flush(),
+            #[cfg(feature = "gzip")]
+            Encoder::Gzip(wtr) => wtr.flush(),
+            #[cfg(feature = "xz")]
+            Encoder::Xz(wtr) => wtr.flush(),
+            #[cfg(feature = "zstd")]
+            Encoder::Zstd(wtr) => wtr.flush(),
+        }
+    }
+}
+
+impl<W: Write> Drop for Encoder<W> {
+    fn drop(&mut self) {
+        let _ = self.try_finish();
+    }
+}
+
+impl<W: Write> fmt::Debug for Encoder<W> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Encoder")
+            .field("compression", &self.compression())
+            .finish()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = b"(1469439874.299654) can1 701#7F\n(1469439874.299700) can1 123#\n";
+
+    // Compresses the text into a buffer
+    fn encode(comp: Compression) -> Vec<u8> {
+        let mut buf = Vec::new();
+        {
+            let mut wtr = Encoder::new(&mut buf, comp).unwrap();
+            wtr.write_all(TEXT).unwrap();
+            wtr.try_finish().unwrap();
+        }
+        buf
+    }
+
+    fn round_trip(comp: Compression) {
+        let buf = encode(comp);
+        assert_eq!(comp, Compression::detect(&buf));
+
+        let mut rdr = Decoder::new(buf.as_slice()).unwrap();
+        assert_eq!(comp, rdr.compression());
+
+        let mut text = Vec::new();
+        rdr.read_to_end(&mut text).unwrap();
+        assert_eq!(TEXT, text.as_slice());
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(Compression::Gzip, Compression::from_path("can.log.gz"));
+        assert_eq!(Compression::Xz, Compression::from_path("can.log.xz"));
+        assert_eq!(
+            Compression::Zstd,
+            Compression::from_path("/tmp/can.log.zst")
+        );
+        assert_eq!(Compression::None, Compression::from_path("can.log"));
+
+        assert_eq!(Compression::Gzip, Compression::detect(&[0x1F, 0x8B, 0x08]));
+        assert_eq!(Compression::Zstd, Compression::detect(ZSTD_MAGIC));
+        assert_eq!(Compression::None, Compression::detect(TEXT));
+        assert_eq!(Compression::None, Compression::detect(&[]));
+    }
+
+    #[test]
+    fn test_plain() {
+        round_trip(Compression::None);
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn test_unsupported() {
+        let err = Decoder::new(&[0x1F, 0x8B, 0x08, 0x00][..]).unwrap_err();
+        assert_eq!(io::ErrorKind::Unsupported, err.kind());
+        assert!(Encoder::new(Vec::new(), Compression::Gzip).is_err());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip() {
+        round_trip(Compression::Gzip);
+    }
+
+    #[cfg(feature = "xz")]
+    #[test]
+    fn test_xz() {
+        round_trip(Compression::Xz);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd() {
+        round_trip(Compression::Zstd);
+    }
+}
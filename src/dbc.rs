@@ -0,0 +1,1239 @@
+// socketcan/src/dbc.rs
+//
+// Implements DBC database parsing and signal decoding.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! DBC database support.
+//!
+//! A DBC file describes the messages on a CAN bus, and how the signals in
+//! each message are packed into the data bytes. This parses a DBC file into
+//! a [`Dbc`] database, which can then decode frames into the physical values
+//! of their signals, and encode signal values back into frames.
+//!
+//! Example:
+//!
+//! ```text
+//! BO_ 256 EngineData: 8 ECU
+//!  SG_ RPM : 0|16@1+ (0.25,0) [0|16383.75] "rpm" Dash
+//!  SG_ Temp : 16|8@1- (1,-40) [-40|215] "degC" Dash
+//!  SG_ Gear : 24|4@1+ (1,0) [0|15] "" Dash
+//!
+//! VAL_ 256 Gear 0 "Neutral" 15 "Reverse" ;
+//! ```
+//!
+//! The parts of the format that are supported are:
+//!
+//! - Messages (`BO_`) with standard or extended IDs, and their signals
+//!   (`SG_`).
+//! - Signals in little-endian (Intel, `@1`) or big-endian (Motorola, `@0`)
+//!   byte order, signed or unsigned, with a scale factor and offset.
+//! - IEEE float and double signals (`SIG_VALTYPE_`).
+//! - Simple multiplexing, with one multiplexor signal (`M`) selecting which
+//!   of the multiplexed signals (`m<n>`) are present in a frame.
+//! - Value descriptions for signals (`VAL_`) and named value tables
+//!   (`VAL_TABLE_`).
+//! - Comments on messages and signals (`CM_`).
+//!
+//! Other statements, like the attribute definitions, are skipped. Vector's
+//! pseudo message for unused signals, which does not have a valid CAN ID,
+//! is skipped as well.
+//!
+//! The top-level statements are expected to start at the beginning of a
+//! line, as in the files written by the common tools.
+//!
+//! ```
+//! use socketcan::{dbc::Dbc, CanDataFrame, EmbeddedFrame, StandardId};
+//!
+//! let dbc: Dbc = r#"
+//! BO_ 256 EngineData: 8 ECU
+//!  SG_ RPM : 0|16@1+ (0.25,0) [0|16383.75] "rpm" Dash
+//!  SG_ Temp : 16|8@1- (1,-40) [-40|215] "degC" Dash
+//! "#
+//! .parse()
+//! .unwrap();
+//!
+//! let id = StandardId::new(256).unwrap();
+//! let frame = CanDataFrame::new(id, &[0x40, 0x1F, 0x5A, 0, 0, 0, 0, 0]).unwrap();
+//!
+//! let decoded = dbc.decode(&frame).unwrap();
+//! assert_eq!("EngineData", decoded.message.name);
+//! assert_eq!(Some(2000.0), decoded.value("RPM"));
+//! assert_eq!(Some(50.0), decoded.value("Temp"));
+//! ```
+
+use crate::{
+    frame::{id_to_canid_t, CAN_EFF_MASK},
+    ByteOrder, CanAnyFrame, CanDataFrame, CanFdFrame, ConstructionError, EmbeddedFrame, ExtendedId,
+    Frame, Id, SignalLayout, SignalType, StandardId,
+};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    fs, io, path,
+    str::FromStr,
+};
+use thiserror::Error;
+
+/// The bit in a DBC message ID that marks it as extended.
+const DBC_EXT_FLAG: u32 = 0x8000_0000;
+
+/// Descriptions of the raw values of a signal, like "0 = Off, 1 = On"
+pub type ValueDescriptions = BTreeMap<i64, String>;
+
+// ===== Errors =====
+
+/// An error parsing a DBC file
+#[derive(Error, Debug)]
+pub enum ParseError {
+    /// I/O Error
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// Something other than what was expected was found in the file
+    #[error("line {line}: expected {expected}, found '{found}'")]
+    Syntax {
+        /// The line number, starting at 1
+        line: usize,
+        /// A description of what was expected
+        expected: &'static str,
+        /// The text that was found instead
+        found: String,
+    },
+    /// The file ended in the middle of a statement
+    #[error("unexpected end of file, expected {0}")]
+    UnexpectedEnd(&'static str),
+    /// More than one message has the same ID
+    #[error("line {line}: duplicate message ID {id}")]
+    DuplicateMessage {
+        /// The line number of the second message, starting at 1
+        line: usize,
+        /// The ID of the message, as it appears in the file
+        id: u32,
+    },
+}
+
+/// An error encoding signal values into a frame
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    /// The database does not have a message with the name
+    #[error("unknown message: {0}")]
+    UnknownMessage(String),
+    /// The message does not have a signal with the name
+    #[error("unknown signal: {0}")]
+    UnknownSignal(String),
+    /// The value does not fit in the bits of the signal
+    #[error("value out of range for signal: {0}")]
+    ValueOutOfRange(String),
+    /// The signal is multiplexed, and not selected by the multiplexor value
+    #[error("signal not selected by the multiplexor: {0}")]
+    InactiveSignal(String),
+    /// The bits of the signal do not fit in the message
+    #[error("signal does not fit in the message: {0}")]
+    SignalOutOfBounds(String),
+    /// The frame could not be created
+    #[error(transparent)]
+    Construction(#[from] ConstructionError),
+}
+
+// ===== Signal =====
+
+/// How a signal takes part in the multiplexing of a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplex {
+    /// The signal is always present
+    None,
+    /// The signal selects which of the multiplexed signals are present
+    Multiplexor,
+    /// The signal is only present when the multiplexor has the value
+    Multiplexed(u64),
+}
+
+/// A signal within a message
+#[derive(Debug, Clone, PartialEq)]
+pub struct Signal {
+    /// The name of the signal
+    pub name: String,
+    /// The position and encoding of the signal in the data
+    pub layout: SignalLayout,
+    /// The minimum physical value
+    pub min: f64,
+    /// The maximum physical value
+    pub max: f64,
+    /// The unit of the physical value
+    pub unit: String,
+    /// The nodes that receive the signal
+    pub receivers: Vec<String>,
+    /// How the signal is multiplexed
+    pub multiplex: Multiplex,
+    /// Descriptions of the raw values
+    pub values: ValueDescriptions,
+    /// The comment, if any
+    pub comment: Option<String>,
+}
+
+impl Signal {
+    /// Decodes the physical value of the signal from the data.
+    ///
+    /// Returns `None` if the signal does not fit in the data.
+    pub fn decode(&self, data: &[u8]) -> Option<f64> {
+        self.layout.decode(data)
+    }
+
+    /// Gets the description of a raw value, if there is one.
+    pub fn describe(&self, raw: u64) -> Option<&str> {
+        self.values
+            .get(&self.layout.raw_to_int(raw))
+            .map(String::as_str)
+    }
+}
+
+// ===== Message =====
+
+/// A message in the database
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    /// The CAN ID of the message
+    pub id: Id,
+    /// The name of the message
+    pub name: String,
+    /// The size of the message data, in bytes
+    pub size: usize,
+    /// The node that transmits the message
+    pub transmitter: String,
+    /// The signals in the message
+    pub signals: Vec<Signal>,
+    /// The comment, if any
+    pub comment: Option<String>,
+}
+
+impl Message {
+    /// Gets a signal by name.
+    pub fn signal(&self, name: &str) -> Option<&Signal> {
+        self.signals.iter().find(|sig| sig.name == name)
+    }
+
+    /// Gets the multiplexor signal, if the message is multiplexed.
+    pub fn multiplexor(&self) -> Option<&Signal> {
+        self.signals
+            .iter()
+            .find(|sig| sig.multiplex == Multiplex::Multiplexor)
+    }
+
+    /// Decodes the values of the signals from the data of a frame.
+    ///
+    /// Only the multiplexed signals selected by the multiplexor are
+    /// included, and any signals that do not fit in the data are skipped.
+    pub fn decode(&self, data: &[u8]) -> Vec<SignalValue<'_>> {
+        let mux = self.multiplexor().and_then(|sig| sig.layout.extract(data));
+
+        self.signals
+            .iter()
+            .filter(|sig| match sig.multiplex {
+                Multiplex::Multiplexed(n) => mux == Some(n),
+                _ => true,
+            })
+            .filter_map(|signal| {
+                let raw = signal.layout.extract(data)?;
+                Some(SignalValue {
+                    signal,
+                    raw,
+                    value: signal.layout.raw_to_physical(raw),
+                })
+            })
+            .collect()
+    }
+
+    /// Encodes signal values into the data bytes for the message.
+    ///
+    /// The values are given as pairs of the signal name and physical value.
+    /// Signals that are not given have a raw value of zero. A multiplexed
+    /// signal can only be given if the value of the multiplexor selects it.
+    pub fn encode_data<I, S>(&self, values: I) -> Result<Vec<u8>, EncodeError>
+    where
+        I: IntoIterator<Item = (S, f64)>,
+        S: AsRef<str>,
+    {
+        let mut data = vec![0u8; self.size];
+        let mut mux_raw = 0;
+        let mut muxed = Vec::new();
+
+        for (name, value) in values {
+            let name = name.as_ref();
+            let sig = self
+                .signal(name)
+                .ok_or_else(|| EncodeError::UnknownSignal(name.into()))?;
+            let raw = sig
+                .layout
+                .physical_to_raw(value)
+                .ok_or_else(|| EncodeError::ValueOutOfRange(name.into()))?;
+            sig.layout
+                .insert(&mut data, raw)
+                .map_err(|_| EncodeError::SignalOutOfBounds(name.into()))?;
+
+            match sig.multiplex {
+                Multiplex::Multiplexor => mux_raw = raw,
+                Multiplex::Multiplexed(n) => muxed.push((n, name.to_string())),
+                Multiplex::None => (),
+            }
+        }
+
+        if let Some((_, name)) = muxed.iter().find(|(n, _)| *n != mux_raw) {
+            return Err(EncodeError::InactiveSignal(name.clone()));
+        }
+        Ok(data)
+    }
+
+    /// Encodes signal values into a frame for the message.
+    ///
+    /// This is a classic data frame if the message has up to 8 bytes, and
+    /// an FD frame otherwise. See [`encode_data()`](Self::encode_data).
+    pub fn encode<I, S>(&self, values: I) -> Result<CanAnyFrame, EncodeError>
+    where
+        I: IntoIterator<Item = (S, f64)>,
+        S: AsRef<str>,
+    {
+        let data = self.encode_data(values)?;
+        let frame = if data.len() <= crate::frame::CAN_MAX_DLEN {
+            CanAnyFrame::Normal(
+                CanDataFrame::new(self.id, &data).ok_or(ConstructionError::TooMuchData)?,
+            )
+        } else {
+            CanAnyFrame::Fd(self.make_fd_frame(&data)?)
+        };
+        Ok(frame)
+    }
+
+    /// Encodes signal values into an FD frame for the message, whatever
+    /// its size. See [`encode_data()`](Self::encode_data).
+    pub fn encode_fd<I, S>(&self, values: I) -> Result<CanFdFrame, EncodeError>
+    where
+        I: IntoIterator<Item = (S, f64)>,
+        S: AsRef<str>,
+    {
+        let data = self.encode_data(values)?;
+        self.make_fd_frame(&data)
+    }
+
+    /// Makes an FD frame for the message with the data.
+    fn make_fd_frame(&self, data: &[u8]) -> Result<CanFdFrame, EncodeError> {
+        Ok(CanFdFrame::new(self.id, data).ok_or(ConstructionError::TooMuchData)?)
+    }
+}
+
+/// The value of a signal decoded from a frame
+#[derive(Debug, Clone, Copy)]
+pub struct SignalValue<'a> {
+    /// The signal
+    pub signal: &'a Signal,
+    /// The raw bits of the signal
+    pub raw: u64,
+    /// The physical value
+    pub value: f64,
+}
+
+impl<'a> SignalValue<'a> {
+    /// Gets the name of the signal.
+    pub fn name(&self) -> &'a str {
+        &self.signal.name
+    }
+
+    /// Gets the description of the raw value, if there is one.
+    pub fn description(&self) -> Option<&'a str> {
+        self.signal.describe(self.raw)
+    }
+}
+
+/// The signals decoded from a frame
+#[derive(Debug, Clone)]
+pub struct DecodedMessage<'a> {
+    /// The message that the frame matched
+    pub message: &'a Message,
+    /// The values of the signals present in the frame
+    pub signals: Vec<SignalValue<'a>>,
+}
+
+impl DecodedMessage<'_> {
+    /// Gets the physical value of a signal by name.
+    pub fn value(&self, name: &str) -> Option<f64> {
+        self.signals
+            .iter()
+            .find(|sig| sig.name() == name)
+            .map(|sig| sig.value)
+    }
+}
+
+// ===== Dbc =====
+
+/// A DBC database of messages and their signals
+#[derive(Debug, Clone, Default)]
+pub struct Dbc {
+    /// The version string, which is often empty
+    pub version: String,
+    /// The nodes on the bus
+    pub nodes: Vec<String>,
+    /// The messages, in the order of the file
+    messages: Vec<Message>,
+    /// The named value tables
+    pub value_tables: BTreeMap<String, ValueDescriptions>,
+    /// Index of the messages by their ID word
+    index: HashMap<u32, usize>,
+}
+
+impl Dbc {
+    /// Parses a database from the text of a DBC file.
+    pub fn parse(text: &str) -> Result<Self, ParseError> {
+        Parser::new(text).parse()
+    }
+
+    /// Reads and parses a DBC file.
+    ///
+    /// DBC files are often in a Windows code page, rather than UTF-8, so a
+    /// file that is not valid UTF-8 is read as Latin-1.
+    pub fn from_file<P: AsRef<path::Path>>(path: P) -> Result<Self, ParseError> {
+        let buf = fs::read(path)?;
+        let text = match std::str::from_utf8(&buf) {
+            Ok(s) => Cow::Borrowed(s),
+            Err(_) => Cow::Owned(buf.iter().map(|&b| b as char).collect()),
+        };
+        Self::parse(&text)
+    }
+
+    /// Gets all of the messages, in the order of the file.
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// Gets a message by ID.
+    pub fn message(&self, id: impl Into<Id>) -> Option<&Message> {
+        self.index
+            .get(&id_to_canid_t(id))
+            .map(|&i| &self.messages[i])
+    }
+
+    /// Gets a message by name.
+    pub fn message_by_name(&self, name: &str) -> Option<&Message> {
+        self.messages.iter().find(|msg| msg.name == name)
+    }
+
+    /// Decodes the signals from a data frame.
+    ///
+    /// Returns `None` if the database does not have a message with the ID
+    /// of the frame, or it is a remote or error frame.
+    pub fn decode<F: Frame>(&self, frame: &F) -> Option<DecodedMessage<'_>> {
+        if frame.is_remote_frame() || frame.is_error_frame() {
+            return None;
+        }
+        let message = self.message(frame.id())?;
+        Some(DecodedMessage {
+            message,
+            signals: message.decode(frame.data()),
+        })
+    }
+
+    /// Encodes signal values into a frame for the named message.
+    ///
+    /// See [`Message::encode()`].
+    pub fn encode<I, S>(&self, message: &str, values: I) -> Result<CanAnyFrame, EncodeError>
+    where
+        I: IntoIterator<Item = (S, f64)>,
+        S: AsRef<str>,
+    {
+        self.message_by_name(message)
+            .ok_or_else(|| EncodeError::UnknownMessage(message.into()))?
+            .encode(values)
+    }
+
+    /// Gets a mutable message by its ID in the file.
+    fn message_mut(&mut self, dbc_id: u32) -> Option<&mut Message> {
+        let id = dbc_id_to_id(dbc_id)?;
+        let i = *self.index.get(&id_to_canid_t(id))?;
+        Some(&mut self.messages[i])
+    }
+
+    /// Gets a mutable signal by the ID of its message in the file, and name.
+    fn signal_mut(&mut self, dbc_id: u32, name: &str) -> Option<&mut Signal> {
+        self.message_mut(dbc_id)?
+            .signals
+            .iter_mut()
+            .find(|sig| sig.name == name)
+    }
+}
+
+impl FromStr for Dbc {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Converts a message ID from a DBC file, where the high bit marks an
+/// extended ID, to a CAN ID.
+fn dbc_id_to_id(dbc_id: u32) -> Option<Id> {
+    if dbc_id & DBC_EXT_FLAG != 0 {
+        let id = dbc_id & !DBC_EXT_FLAG;
+        if id > CAN_EFF_MASK {
+            return None;
+        }
+        ExtendedId::new(id).map(Id::Extended)
+    } else {
+        u16::try_from(dbc_id)
+            .ok()
+            .and_then(StandardId::new)
+            .map(Id::Standard)
+    }
+}
+
+// ===== Tokenizer =====
+
+/// A token in a DBC file
+#[derive(Debug, Clone, PartialEq)]
+enum Tok<'a> {
+    /// A name, keyword, or number
+    Word(&'a str),
+    /// A quoted string, without the quotes
+    Str(String),
+    /// A punctuation character
+    Punct(char),
+}
+
+/// A token with its location
+#[derive(Debug, Clone)]
+struct Token<'a> {
+    tok: Tok<'a>,
+    /// The line number, starting at 1
+    line: usize,
+    /// Whether the token is at the very start of a line
+    line_start: bool,
+}
+
+impl Token<'_> {
+    /// Gets the text of the token for an error message.
+    fn text(&self) -> String {
+        match &self.tok {
+            Tok::Word(s) => s.to_string(),
+            Tok::Str(s) => format!("\"{}\"", s),
+            Tok::Punct(c) => c.to_string(),
+        }
+    }
+}
+
+/// Whether the character is punctuation that ends a word.
+fn is_punct(c: char) -> bool {
+    matches!(c, ':' | '|' | '@' | '(' | ')' | '[' | ']' | ',' | ';')
+}
+
+/// Splits the text of a DBC file into tokens.
+#[derive(Debug)]
+struct Lexer<'a> {
+    text: &'a str,
+    pos: usize,
+    line: usize,
+    line_pos: usize,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            pos: 0,
+            line: 1,
+            line_pos: 0,
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Token<'a>;
+
+    fn next(&mut self) -> Option<Token<'a>> {
+        let bytes = self.text.as_bytes();
+
+        // skip whitespace, counting lines
+        while let Some(&c) = bytes.get(self.pos) {
+            if c == b'\n' {
+                self.line += 1;
+                self.line_pos = self.pos + 1;
+            } else if !c.is_ascii_whitespace() {
+                break;
+            }
+            self.pos += 1;
+        }
+
+        let start = self.pos;
+        let line = self.line;
+        let line_start = start == self.line_pos;
+        let c = self.text[start..].chars().next()?;
+
+        let tok = if c == '"' {
+            let mut s = String::new();
+            let mut chars = self.text[start + 1..].char_indices();
+            self.pos = self.text.len();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    '"' => {
+                        self.pos = start + 1 + i + 1;
+                        break;
+                    }
+                    '\\' => {
+                        if let Some((_, c)) = chars.next() {
+                            s.push(c);
+                        }
+                    }
+                    '\n' => {
+                        self.line += 1;
+                        self.line_pos = start + 1 + i + 1;
+                        s.push(c);
+                    }
+                    _ => s.push(c),
+                }
+            }
+            Tok::Str(s)
+        } else if is_punct(c) {
+            self.pos += 1;
+            Tok::Punct(c)
+        } else {
+            let len = self.text[start..]
+                .find(|c: char| c.is_whitespace() || c == '"' || is_punct(c))
+                .unwrap_or(self.text.len() - start);
+            self.pos += len;
+            Tok::Word(&self.text[start..self.pos])
+        };
+
+        Some(Token {
+            tok,
+            line,
+            line_start,
+        })
+    }
+}
+
+// ===== Parser =====
+
+/// Parses the tokens of a DBC file into a database.
+#[derive(Debug)]
+struct Parser<'a> {
+    lex: std::iter::Peekable<Lexer<'a>>,
+    dbc: Dbc,
+}
+
+impl<'a> Parser<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            lex: Lexer::new(text).peekable(),
+            dbc: Dbc::default(),
+        }
+    }
+
+    /// Creates an error for an unexpected token.
+    fn error(tok: Option<Token>, expected: &'static str) -> ParseError {
+        match tok {
+            Some(tok) => ParseError::Syntax {
+                line: tok.line,
+                expected,
+                found: tok.text(),
+            },
+            None => ParseError::UnexpectedEnd(expected),
+        }
+    }
+
+    /// Gets the next token, which must be a word.
+    fn word(&mut self, expected: &'static str) -> Result<&'a str, ParseError> {
+        match self.lex.next() {
+            Some(Token {
+                tok: Tok::Word(s), ..
+            }) => Ok(s),
+            tok => Err(Self::error(tok, expected)),
+        }
+    }
+
+    /// Gets the next token, which must be a number.
+    fn number<T: FromStr>(&mut self, expected: &'static str) -> Result<T, ParseError> {
+        let tok = self.lex.next();
+        let val = match tok.as_ref().map(|tok| &tok.tok) {
+            Some(Tok::Word(s)) => s.parse().ok(),
+            _ => None,
+        };
+        val.ok_or_else(|| Self::error(tok, expected))
+    }
+
+    /// Gets the next token, which must be a quoted string.
+    fn string(&mut self, expected: &'static str) -> Result<String, ParseError> {
+        match self.lex.next() {
+            Some(Token {
+                tok: Tok::Str(s), ..
+            }) => Ok(s),
+            tok => Err(Self::error(tok, expected)),
+        }
+    }
+
+    /// Gets the next token, which must be the punctuation character.
+    fn punct(&mut self, c: char, expected: &'static str) -> Result<(), ParseError> {
+        match self.lex.next() {
+            Some(Token {
+                tok: Tok::Punct(p), ..
+            }) if p == c => Ok(()),
+            tok => Err(Self::error(tok, expected)),
+        }
+    }
+
+    /// Whether the next token is the word.
+    fn peek_word(&mut self, word: &str) -> bool {
+        matches!(self.lex.peek(), Some(Token { tok: Tok::Word(s), .. }) if *s == word)
+    }
+
+    /// Whether the next token is a string.
+    fn peek_string(&mut self) -> bool {
+        matches!(
+            self.lex.peek(),
+            Some(Token {
+                tok: Tok::Str(_),
+                ..
+            })
+        )
+    }
+
+    /// Skips to the next token at the start of a line, which begins the
+    /// next statement.
+    fn skip_statement(&mut self) {
+        while self.lex.next_if(|tok| !tok.line_start).is_some() {}
+    }
+
+    /// Parses the whole file.
+    fn parse(mut self) -> Result<Dbc, ParseError> {
+        while let Some(tok) = self.lex.next() {
+            if let Tok::Word(kw) = tok.tok {
+                match kw {
+                    "VERSION" => self.dbc.version = self.string("version string")?,
+                    "BU_" => self.parse_nodes(tok.line)?,
+                    "BO_" => self.parse_message(tok.line)?,
+                    "CM_" => self.parse_comment()?,
+                    "VAL_" => self.parse_values()?,
+                    "VAL_TABLE_" => self.parse_value_table()?,
+                    "SIG_VALTYPE_" => self.parse_value_type()?,
+                    _ => (),
+                }
+            }
+            self.skip_statement();
+        }
+        Ok(self.dbc)
+    }
+
+    /// Parses the list of nodes: `BU_: <node>...`
+    fn parse_nodes(&mut self, line: usize) -> Result<(), ParseError> {
+        self.punct(':', "':' after BU_")?;
+        while let Some(tok) = self.lex.next_if(|tok| tok.line == line) {
+            if let Tok::Word(s) = tok.tok {
+                self.dbc.nodes.push(s.into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a message and its signals:
+    /// `BO_ <id> <name>: <size> <transmitter>`
+    fn parse_message(&mut self, line: usize) -> Result<(), ParseError> {
+        let dbc_id = self.number::<u32>("message ID")?;
+        let name = self.word("message name")?;
+        self.punct(':', "':' after message name")?;
+        let size = self.number::<usize>("message size")?;
+        let transmitter = self.word("transmitter")?;
+
+        let mut signals = Vec::new();
+        while self.peek_word("SG_") {
+            self.lex.next();
+            signals.push(self.parse_signal()?);
+        }
+
+        // The pseudo message for unused signals doesn't have a valid ID
+        if let Some(id) = dbc_id_to_id(dbc_id) {
+            let n = self.dbc.messages.len();
+            if self.dbc.index.insert(id_to_canid_t(id), n).is_some() {
+                return Err(ParseError::DuplicateMessage { line, id: dbc_id });
+            }
+            self.dbc.messages.push(Message {
+                id,
+                name: name.into(),
+                size,
+                transmitter: transmitter.into(),
+                signals,
+                comment: None,
+            });
+        }
+        Ok(())
+    }
+
+    /// Parses a signal:
+    /// `SG_ <name> [M|m<n>] : <start>|<size>@<order><sign> (<factor>,<offset>)
+    /// [<min>|<max>] "<unit>" <receivers>`
+    fn parse_signal(&mut self) -> Result<Signal, ParseError> {
+        let name = self.word("signal name")?;
+
+        let mut multiplex = Multiplex::None;
+        if let Some(tok) = self.lex.next_if(|tok| matches!(tok.tok, Tok::Word(_))) {
+            let Tok::Word(s) = tok.tok else {
+                unreachable!()
+            };
+            multiplex = match s.strip_prefix('m') {
+                _ if s == "M" => Multiplex::Multiplexor,
+                // An extended multiplexor, like "m3M", is treated as
+                // multiplexed, since only simple multiplexing is supported
+                Some(n) => n
+                    .trim_end_matches('M')
+                    .parse()
+                    .map(Multiplex::Multiplexed)
+                    .map_err(|_| Self::error(Some(tok.clone()), "multiplex indicator"))?,
+                None => return Err(Self::error(Some(tok), "multiplex indicator")),
+            };
+        }
+
+        self.punct(':', "':' after signal name")?;
+        let start_bit = self.number::<usize>("start bit")?;
+        self.punct('|', "'|' after start bit")?;
+        let tok = self.lex.next();
+        let size = match tok.as_ref().map(|tok| &tok.tok) {
+            Some(Tok::Word(s)) => s.parse().ok().filter(|n| (1..=64).contains(n)),
+            _ => None,
+        }
+        .ok_or_else(|| Self::error(tok, "signal size from 1 to 64"))?;
+        self.punct('@', "'@' after signal size")?;
+
+        let tok = self.lex.next();
+        let (byte_order, signal_type) = match tok.as_ref().map(|tok| &tok.tok) {
+            Some(Tok::Word(s)) if s.len() == 2 => {
+                let order = match s.as_bytes()[0] {
+                    b'0' => ByteOrder::Motorola,
+                    b'1' => ByteOrder::Intel,
+                    _ => return Err(Self::error(tok, "byte order and sign")),
+                };
+                let vtype = match s.as_bytes()[1] {
+                    b'+' => SignalType::Unsigned,
+                    b'-' => SignalType::Signed,
+                    _ => return Err(Self::error(tok, "byte order and sign")),
+                };
+                (order, vtype)
+            }
+            _ => return Err(Self::error(tok, "byte order and sign")),
+        };
+
+        self.punct('(', "'(' before factor")?;
+        let factor = self.number::<f64>("factor")?;
+        self.punct(',', "',' after factor")?;
+        let offset = self.number::<f64>("offset")?;
+        self.punct(')', "')' after offset")?;
+
+        self.punct('[', "'[' before minimum")?;
+        let min = self.number::<f64>("minimum")?;
+        self.punct('|', "'|' after minimum")?;
+        let max = self.number::<f64>("maximum")?;
+        self.punct(']', "']' after maximum")?;
+
+        let unit = self.string("unit")?;
+
+        // The receivers are the rest of the line, separated by commas
+        let mut receivers = Vec::new();
+        let line = match self.lex.peek() {
+            Some(tok) if !tok.line_start => tok.line,
+            _ => 0,
+        };
+        while let Some(tok) = self.lex.next_if(|tok| {
+            tok.line == line
+                && !tok.line_start
+                && matches!(tok.tok, Tok::Word(_) | Tok::Punct(','))
+                && tok.tok != Tok::Word("SG_")
+        }) {
+            if let Tok::Word(s) = tok.tok {
+                receivers.push(s.into());
+            }
+        }
+
+        Ok(Signal {
+            name: name.into(),
+            layout: SignalLayout {
+                start_bit,
+                size,
+                byte_order,
+                signal_type,
+                factor,
+                offset,
+            },
+            min,
+            max,
+            unit,
+            receivers,
+            multiplex,
+            values: ValueDescriptions::new(),
+            comment: None,
+        })
+    }
+
+    /// Parses a comment: `CM_ [BO_ <id> | SG_ <id> <name> | BU_ <name>] "<text>";`
+    ///
+    /// Only the comments for messages and signals are kept.
+    fn parse_comment(&mut self) -> Result<(), ParseError> {
+        if self.peek_string() {
+            return Ok(());
+        }
+        match self.word("comment type")? {
+            "BO_" => {
+                let dbc_id = self.number::<u32>("message ID")?;
+                let text = self.string("comment")?;
+                if let Some(msg) = self.dbc.message_mut(dbc_id) {
+                    msg.comment = Some(text);
+                }
+            }
+            "SG_" => {
+                let dbc_id = self.number::<u32>("message ID")?;
+                let name = self.word("signal name")?;
+                let text = self.string("comment")?;
+                if let Some(sig) = self.dbc.signal_mut(dbc_id, name) {
+                    sig.comment = Some(text);
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Parses a list of value descriptions, up to the terminating ';'.
+    fn parse_descriptions(&mut self) -> Result<ValueDescriptions, ParseError> {
+        let mut values = ValueDescriptions::new();
+        while self.lex.next_if(|tok| tok.tok == Tok::Punct(';')).is_none() {
+            let val = self.number::<f64>("value")? as i64;
+            let desc = self.string("value description")?;
+            values.insert(val, desc);
+        }
+        Ok(values)
+    }
+
+    /// Parses the value descriptions for a signal:
+    /// `VAL_ <id> <signal> <value> "<description>"... ;`
+    ///
+    /// Descriptions for environment variables are skipped.
+    fn parse_values(&mut self) -> Result<(), ParseError> {
+        let Some(Token {
+            tok: Tok::Word(first),
+            ..
+        }) = self.lex.peek().cloned()
+        else {
+            return Ok(());
+        };
+        let Ok(dbc_id) = first.parse::<u32>() else {
+            return Ok(());
+        };
+        self.lex.next();
+        let name = self.word("signal name")?;
+        let values = self.parse_descriptions()?;
+        if let Some(sig) = self.dbc.signal_mut(dbc_id, name) {
+            sig.values = values;
+        }
+        Ok(())
+    }
+
+    /// Parses a named value table:
+    /// `VAL_TABLE_ <name> <value> "<description>"... ;`
+    fn parse_value_table(&mut self) -> Result<(), ParseError> {
+        let name = self.word("value table name")?;
+        let values = self.parse_descriptions()?;
+        self.dbc.value_tables.insert(name.into(), values);
+        Ok(())
+    }
+
+    /// Parses the value type of a float signal:
+    /// `SIG_VALTYPE_ <id> <signal> : <type>;`
+    fn parse_value_type(&mut self) -> Result<(), ParseError> {
+        let dbc_id = self.number::<u32>("message ID")?;
+        let name = self.word("signal name")?;
+        self.punct(':', "':' after signal name")?;
+        let tok = self.lex.peek().cloned();
+        let (signal_type, size) = match self.number::<u32>("signal value type")? {
+            1 => (SignalType::Float, 32),
+            2 => (SignalType::Double, 64),
+            _ => return Ok(()),
+        };
+        if let Some(sig) = self.dbc.signal_mut(dbc_id, name) {
+            // the raw bits are the IEEE value, so must be the same size
+            if sig.layout.size != size {
+                return Err(Self::error(tok, "value type matching the signal size"));
+            }
+            sig.layout.signal_type = signal_type;
+        }
+        Ok(())
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{frame::FdFlags, CanFrame};
+
+    const DBC: &str = r#"VERSION "1.0"
+
+NS_ :
+	NS_DESC_
+	CM_
+	BA_DEF_
+	VAL_
+
+BS_:
+
+BU_: ECU Dash Gateway
+
+VAL_TABLE_ OnOff 1 "On" 0 "Off" ;
+
+BO_ 256 EngineData: 8 ECU
+ SG_ RPM : 0|16@1+ (0.25,0) [0|16383.75] "rpm" Dash,Gateway
+ SG_ Temp : 16|8@1- (1,-40) [-40|215] "degC" Dash
+ SG_ Gear : 24|4@1+ (1,0) [0|15] "" Dash
+
+BO_ 2566848512 BigEndian: 8 Gateway
+ SG_ Speed : 7|16@0+ (0.01,0) [0|655.35] "km/h" Dash
+ SG_ Accel : 23|12@0- (0.1,0) [-204.8|204.7] "m/s2" Dash
+ SG_ Ratio : 32|32@1- (1,0) [0|0] "" Dash
+
+BO_ 512 Muxed: 8 ECU
+ SG_ Page M : 0|8@1+ (1,0) [0|255] "" Dash
+ SG_ Volts m0 : 8|16@1+ (0.001,0) [0|65.535] "V" Dash
+ SG_ Amps m1 : 8|16@1- (0.01,0) [-327.68|327.67] "A" Dash
+ SG_ Status : 56|8@1+ (1,0) [0|255] "" Dash
+
+BO_ 1024 FdData: 24 ECU
+ SG_ Last : 184|8@1+ (1,0) [0|255] "" Dash
+
+BO_ 3221225472 VECTOR__INDEPENDENT_SIG_MSG: 0 Vector__XXX
+ SG_ Unused : 0|8@1+ (1,0) [0|0] "" Vector__XXX
+
+CM_ "The network";
+CM_ BU_ ECU "The engine controller";
+CM_ BO_ 256 "Engine status";
+CM_ SG_ 256 RPM "Engine speed,
+over two lines";
+BA_DEF_ BO_ "GenMsgCycleTime" INT 0 65535;
+BA_ "GenMsgCycleTime" BO_ 256 100;
+VAL_ 256 Gear 0 "Neutral" 15 "Reverse" ;
+VAL_ 512 Amps -1 "Fault" ;
+SIG_VALTYPE_ 2566848512 Ratio : 1;
+"#;
+
+    fn dbc() -> Dbc {
+        DBC.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse() {
+        let dbc = dbc();
+        assert_eq!("1.0", dbc.version);
+        assert_eq!(vec!["ECU", "Dash", "Gateway"], dbc.nodes);
+        assert_eq!(4, dbc.messages().len());
+        assert_eq!(
+            Some("On"),
+            dbc.value_tables["OnOff"].get(&1).map(|s| s.as_str())
+        );
+
+        let msg = dbc.message(StandardId::new(256).unwrap()).unwrap();
+        assert_eq!("EngineData", msg.name);
+        assert_eq!(8, msg.size);
+        assert_eq!("ECU", msg.transmitter);
+        assert_eq!(Some("Engine status"), msg.comment.as_deref());
+
+        let sig = msg.signal("RPM").unwrap();
+        assert_eq!(
+            SignalLayout::new(0, 16, ByteOrder::Intel).scale(0.25, 0.0),
+            sig.layout
+        );
+        assert_eq!(16383.75, sig.max);
+        assert_eq!("rpm", sig.unit);
+        assert_eq!(vec!["Dash", "Gateway"], sig.receivers);
+        assert_eq!(
+            Some("Engine speed,\nover two lines"),
+            sig.comment.as_deref()
+        );
+
+        let sig = msg.signal("Gear").unwrap();
+        assert_eq!(Some("Reverse"), sig.describe(15));
+
+        let msg = dbc.message(ExtendedId::new(0x18FF_0000).unwrap()).unwrap();
+        assert_eq!("BigEndian", msg.name);
+        assert_eq!(ByteOrder::Motorola, msg.signals[0].layout.byte_order);
+        assert_eq!(
+            SignalType::Float,
+            msg.signal("Ratio").unwrap().layout.signal_type
+        );
+        assert!(dbc.message(StandardId::new(0x7FF).unwrap()).is_none());
+
+        let msg = dbc.message_by_name("Muxed").unwrap();
+        assert_eq!(Multiplex::Multiplexor, msg.signals[0].multiplex);
+        assert_eq!(Multiplex::Multiplexed(1), msg.signals[2].multiplex);
+        assert_eq!(Multiplex::None, msg.signals[3].multiplex);
+        assert_eq!("Page", msg.multiplexor().unwrap().name);
+
+        assert!(dbc.message_by_name("VECTOR__INDEPENDENT_SIG_MSG").is_none());
+    }
+
+    #[test]
+    fn test_decode() {
+        let dbc = dbc();
+
+        let frame = CanDataFrame::new(
+            StandardId::new(256).unwrap(),
+            &[0x40, 0x1F, 0xCE, 0x0F, 0, 0, 0, 0],
+        )
+        .unwrap();
+        let decoded = dbc.decode(&frame).unwrap();
+        assert_eq!("EngineData", decoded.message.name);
+        assert_eq!(3, decoded.signals.len());
+        assert_eq!(Some(2000.0), decoded.value("RPM"));
+        assert_eq!(Some(-90.0), decoded.value("Temp"));
+        assert_eq!(Some(15.0), decoded.value("Gear"));
+        assert_eq!(Some("Reverse"), decoded.signals[2].description());
+
+        // big endian, with the MSB first
+        let mut data = [0u8; 8];
+        data[..4].copy_from_slice(&[0x27, 0x10, 0xFF, 0x60]);
+        data[4..].copy_from_slice(&1.5f32.to_bits().to_le_bytes());
+        let frame = CanDataFrame::new(ExtendedId::new(0x18FF_0000).unwrap(), &data).unwrap();
+        let decoded = dbc.decode(&frame).unwrap();
+        assert_eq!(Some(100.0), decoded.value("Speed"));
+        assert_eq!(
+            Some(-1.0),
+            decoded.value("Accel").map(|v| (v * 10.0).round() / 10.0)
+        );
+        assert_eq!(Some(1.5), decoded.value("Ratio"));
+
+        // multiplexed
+        let id = StandardId::new(512).unwrap();
+        let frame = CanDataFrame::new(id, &[0, 0xB8, 0x0B, 0, 0, 0, 0, 7]).unwrap();
+        let decoded = dbc.decode(&frame).unwrap();
+        assert_eq!(Some(3.0), decoded.value("Volts"));
+        assert_eq!(None, decoded.value("Amps"));
+        assert_eq!(Some(7.0), decoded.value("Status"));
+
+        let frame = CanDataFrame::new(id, &[1, 0xFF, 0xFF, 0, 0, 0, 0, 0]).unwrap();
+        let decoded = dbc.decode(&frame).unwrap();
+        assert_eq!(None, decoded.value("Volts"));
+        assert_eq!(Some(-0.01), decoded.value("Amps"));
+        assert_eq!(Some("Fault"), decoded.signals[1].description());
+
+        // short data skips the signals that don't fit
+        let frame = CanDataFrame::new(StandardId::new(256).unwrap(), &[0x40, 0x1F]).unwrap();
+        let decoded = dbc.decode(&frame).unwrap();
+        assert_eq!(1, decoded.signals.len());
+
+        // other frames
+        let frame = CanFrame::remote_from_raw_id(256, 8).unwrap();
+        assert!(dbc.decode(&frame).is_none());
+        let frame = CanDataFrame::new(StandardId::new(0x123).unwrap(), &[]).unwrap();
+        assert!(dbc.decode(&frame).is_none());
+
+        let frame = CanFdFrame::new(StandardId::new(1024).unwrap(), &[0x2A; 24]).unwrap();
+        assert_eq!(Some(42.0), dbc.decode(&frame).unwrap().value("Last"));
+    }
+
+    #[test]
+    fn test_encode() {
+        let dbc = dbc();
+
+        let msg = dbc.message_by_name("EngineData").unwrap();
+        let frame = msg
+            .encode([("RPM", 2000.0), ("Temp", -90.0), ("Gear", 15.0)])
+            .unwrap();
+        let CanAnyFrame::Normal(frame) = frame else {
+            panic!("Expected a data frame");
+        };
+        assert_eq!(Id::Standard(StandardId::new(256).unwrap()), frame.id());
+        assert_eq!(&[0x40, 0x1F, 0xCE, 0x0F, 0, 0, 0, 0], frame.data());
+
+        let values = [("Speed", 100.0), ("Accel", -1.0), ("Ratio", 1.5)];
+        let frame = dbc.encode("BigEndian", values).unwrap();
+        let CanAnyFrame::Normal(frame) = frame else {
+            panic!("Expected a data frame");
+        };
+        assert_eq!(&[0x27, 0x10, 0xFF, 0x60], &frame.data()[..4]);
+        let decoded = dbc.decode(&frame).unwrap();
+        assert_eq!(Some(1.5), decoded.value("Ratio"));
+
+        let msg = dbc.message_by_name("Muxed").unwrap();
+        let data = msg.encode_data([("Page", 1.0), ("Amps", -0.01)]).unwrap();
+        assert_eq!(vec![1, 0xFF, 0xFF, 0, 0, 0, 0, 0], data);
+        assert_eq!(
+            Err(EncodeError::InactiveSignal("Amps".into())),
+            msg.encode_data([("Page", 0.0), ("Amps", 1.0)])
+        );
+        assert_eq!(
+            Err(EncodeError::UnknownSignal("Nope".into())),
+            msg.encode_data([("Nope", 1.0)])
+        );
+        assert_eq!(
+            Err(EncodeError::ValueOutOfRange("Page".into())),
+            msg.encode_data([("Page", 256.0)])
+        );
+        assert_eq!(
+            Err(EncodeError::ValueOutOfRange("Amps".into())),
+            msg.encode_data([("Page", 1.0), ("Amps", 400.0)])
+        );
+
+        assert!(matches!(
+            dbc.encode("Nope", [("Last", 42.0)]),
+            Err(EncodeError::UnknownMessage(name)) if name == "Nope"
+        ));
+
+        let frame = dbc.encode("FdData", [("Last", 42.0)]).unwrap();
+        let CanAnyFrame::Fd(frame) = frame else {
+            panic!("Expected an FD frame");
+        };
+        assert_eq!(24, frame.len());
+        assert_eq!(42, frame.data()[23]);
+
+        let msg = dbc.message_by_name("EngineData").unwrap();
+        let frame = msg.encode_fd([("Gear", 1.0)]).unwrap();
+        assert_eq!(FdFlags::empty(), frame.flags());
+        assert_eq!(8, frame.len());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err =
+            Dbc::parse("BO_ 100 Msg: 8 ECU\n SG_ Sig : 0|8@2+ (1,0) [0|0] \"\" X\n").unwrap_err();
+        assert!(
+            matches!(err, ParseError::Syntax { line: 2, ref found, .. } if found == "2+"),
+            "{}",
+            err
+        );
+
+        let err =
+            Dbc::parse("BO_ 100 Msg: 8 ECU\n SG_ Sig : 0|65@1+ (1,0) [0|0] \"\" X\n").unwrap_err();
+        assert!(matches!(err, ParseError::Syntax { line: 2, .. }));
+
+        // a float signal must be 32 bits
+        let err = Dbc::parse(
+            "BO_ 100 Msg: 8 ECU\n SG_ Sig : 0|16@1+ (1,0) [0|0] \"\" X\n\
+             SIG_VALTYPE_ 100 Sig : 1;\n",
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, ParseError::Syntax { line: 3, ref found, .. } if found == "1"),
+            "{}",
+            err
+        );
+
+        let err = Dbc::parse("BO_ 100 Msg:").unwrap_err();
+        assert!(matches!(err, ParseError::UnexpectedEnd(_)));
+
+        // unknown statements are skipped
+        let dbc = Dbc::parse("FOO_ 1 2 3;\nBO_ 100 Msg: 8 ECU\n").unwrap();
+        assert_eq!(1, dbc.messages().len());
+
+        let err = Dbc::parse("BO_ 100 Msg: 8 ECU\nBO_ 100 Other: 8 ECU\n").unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::DuplicateMessage { line: 2, id: 100 }
+        ));
+    }
+}
@@ -22,47 +22,125 @@
 //!
 //! Can be parsed by a `Reader` object. The API is inspired by the
 //! [csv](https://crates.io/crates/csv) crate.
+//!
+//! Logs in the same format can be written with a `Writer` object, for
+//! playback with the `canplayer` utility.
+//!
+//! Log files may be compressed with gzip, xz, or zstd when the matching
+//! feature is enabled. See the [`compress`](crate::compress) module.
 
 use crate::{
-    frame::{FdFlags, IdFlags},
-    CanDataFrame, CanFdFrame,
+    compress::{Compression, Decoder, Encoder},
+    frame::parse_frame,
+    logfile::{self, LogReader, LogRecord, LogWriter},
+    CanAnyFrame, ParseFrameError,
+};
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path,
+    time::{Duration, SystemTime},
+};
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+use std::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
 };
-use embedded_can::StandardId;
-use hex::FromHex;
-use libc::canid_t;
-use std::{fs, io, path};
-
-// cannot be generic, because from_str_radix is not part of any Trait
-fn parse_raw(bytes: &[u8], radix: u32) -> Option<u64> {
-    ::std::str::from_utf8(bytes)
-        .ok()
-        .and_then(|s| u64::from_str_radix(s, radix).ok())
+use thiserror::Error;
+
+// ===== Low-level parsing =====
+//
+// These work directly on the bytes of the line, without allocating or
+// checking for UTF-8, since they're in the path of every record.
+
+/// Parses an unsigned decimal number.
+///
+/// Fails if the field is empty, has anything other than digits, or
+/// overflows.
+#[inline]
+fn parse_dec(bytes: &[u8]) -> Option<u64> {
+    // Up to 19 digits always fit, so only longer numbers need checking
+    const MAX_UNCHECKED: usize = 19;
+
+    if bytes.is_empty() {
+        return None;
+    }
+    let mut n = 0u64;
+    for (i, &c) in bytes.iter().enumerate() {
+        let d = c.wrapping_sub(b'0');
+        if d > 9 {
+            return None;
+        }
+        n = if i < MAX_UNCHECKED {
+            n * 10 + d as u64
+        } else {
+            n.checked_mul(10)?.checked_add(d as u64)?
+        };
+    }
+    Some(n)
+}
+
+/// Parses a timestamp like "1469439874.299654" into microseconds.
+///
+/// The fractional part is normally six digits, but fewer are scaled, and
+/// any past microseconds are ignored.
+#[inline]
+fn parse_timestamp(bytes: &[u8]) -> Option<u64> {
+    let dot = bytes.iter().position(|&c| c == b'.')?;
+    let (secs, frac) = (&bytes[..dot], &bytes[dot + 1..]);
+
+    let us = match frac.len() {
+        n @ 0..=6 => parse_dec(frac)? * 10u64.pow(6 - n as u32),
+        _ if frac[6..].iter().all(u8::is_ascii_digit) => parse_dec(&frac[..6])?,
+        _ => return None,
+    };
+    Some(
+        parse_dec(secs)?
+            .saturating_mul(1_000_000)
+            .saturating_add(us),
+    )
 }
 
+/// The decoder for a candump file opened by name.
+pub type FileDecoder = Decoder<io::BufReader<fs::File>>;
+
+/// The encoder for a candump file created by name.
+pub type FileEncoder = Encoder<io::BufWriter<fs::File>>;
+
 #[derive(Debug)]
 /// A CAN log reader.
 pub struct Reader<R> {
     rdr: R,
     line_buf: Vec<u8>,
+    device: String,
+    parser: LineParser,
 }
 
 impl<R: io::Read> Reader<R> {
     /// Creates an I/O buffered reader from a CAN log reader.
     pub fn from_reader(rdr: R) -> Reader<io::BufReader<R>> {
-        Reader {
-            rdr: io::BufReader::new(rdr),
-            line_buf: Vec::new(),
-        }
+        Reader::new(io::BufReader::new(rdr))
     }
 }
 
 impl Reader<fs::File> {
     /// Creates an I/O buffered reader from a file.
-    pub fn from_file<P>(path: P) -> io::Result<Reader<io::BufReader<fs::File>>>
+    ///
+    /// If the file is compressed, it is decompressed as it's read. The
+    /// compression is detected from the start of the file, and must be
+    /// enabled with the matching feature, like "gzip", "xz", or "zstd".
+    pub fn from_file<P>(path: P) -> io::Result<Reader<io::BufReader<FileDecoder>>>
     where
         P: AsRef<path::Path>,
     {
-        Ok(Reader::from_reader(fs::File::open(path)?))
+        let file = io::BufReader::new(fs::File::open(path)?);
+        Ok(Reader::from_reader(Decoder::new(file)?))
     }
 }
 
@@ -80,150 +158,512 @@ pub struct CanDumpRecord<'a> {
     /// The name of the device
     pub device: &'a str,
     /// The parsed frame
-    pub frame: super::CanAnyFrame,
+    pub frame: CanAnyFrame,
+}
+
+impl From<CanDumpRecord<'_>> for LogRecord {
+    fn from(rec: CanDumpRecord<'_>) -> Self {
+        LogRecord::new(
+            SystemTime::UNIX_EPOCH + Duration::from_micros(rec.t_us),
+            rec.device,
+            rec.frame,
+        )
+    }
+}
+
+/// The location of a parse error in a candump log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Position {
+    /// The line number, starting at one
+    pub line: u64,
+    /// The byte offset of the bad field within the line, starting at zero
+    pub offset: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, offset {}", self.line, self.offset)
+    }
 }
 
-#[derive(Debug)]
 /// candump line parse error
+#[derive(Error, Debug)]
 pub enum ParseError {
     /// I/O Error
-    Io(io::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
     /// Unexpected end of line
-    UnexpectedEndOfLine,
+    #[error("{pos}: unexpected end of line")]
+    UnexpectedEndOfLine {
+        /// Where the line ended
+        pos: Position,
+    },
     /// Invalid time stamp
-    InvalidTimestamp,
+    #[error("{pos}: invalid timestamp: '{field}'")]
+    InvalidTimestamp {
+        /// The location of the field
+        pos: Position,
+        /// The text of the field
+        field: String,
+    },
     /// Invalid device name
-    InvalidDeviceName,
+    #[error("{pos}: invalid device name: '{field}'")]
+    InvalidDeviceName {
+        /// The location of the field
+        pos: Position,
+        /// The text of the field
+        field: String,
+    },
     /// Invalid CAN frame
-    InvalidCanFrame,
+    #[error("{pos}: invalid CAN frame: '{field}'")]
+    InvalidCanFrame {
+        /// The location of the field
+        pos: Position,
+        /// The text of the field
+        field: String,
+    },
     /// Error creating the frame
-    ConstructionError(super::ConstructionError),
+    #[error("{pos}: {source}: '{field}'")]
+    ConstructionError {
+        /// The location of the field
+        pos: Position,
+        /// The text of the field
+        field: String,
+        /// The error creating the frame
+        source: super::ConstructionError,
+    },
 }
 
-impl From<io::Error> for ParseError {
-    fn from(e: io::Error) -> ParseError {
-        ParseError::Io(e)
+impl ParseError {
+    /// Gets the location in the log where the error occurred.
+    ///
+    /// This is `None` for I/O errors.
+    pub fn position(&self) -> Option<Position> {
+        use ParseError::*;
+        match self {
+            Io(_) => None,
+            UnexpectedEndOfLine { pos }
+            | InvalidTimestamp { pos, .. }
+            | InvalidDeviceName { pos, .. }
+            | InvalidCanFrame { pos, .. }
+            | ConstructionError { pos, .. } => Some(*pos),
+        }
     }
+
+    /// Gets the text of the field that could not be parsed, if any.
+    pub fn field(&self) -> Option<&str> {
+        use ParseError::*;
+        match self {
+            Io(_) | UnexpectedEndOfLine { .. } => None,
+            InvalidTimestamp { field, .. }
+            | InvalidDeviceName { field, .. }
+            | InvalidCanFrame { field, .. }
+            | ConstructionError { field, .. } => Some(field),
+        }
+    }
+}
+
+/// Helper to create parse errors for the fields of a single line.
+struct LineContext<'a> {
+    line: &'a [u8],
+    line_num: u64,
 }
 
-impl From<super::ConstructionError> for ParseError {
-    fn from(e: super::ConstructionError) -> ParseError {
-        ParseError::ConstructionError(e)
+impl LineContext<'_> {
+    /// Gets the position of a field, which must be a slice of the line.
+    fn pos(&self, field: &[u8]) -> Position {
+        Position {
+            line: self.line_num,
+            offset: field.as_ptr() as usize - self.line.as_ptr() as usize,
+        }
+    }
+
+    fn end_of_line(&self) -> ParseError {
+        ParseError::UnexpectedEndOfLine {
+            pos: self.pos(&self.line[self.line.len()..]),
+        }
+    }
+
+    fn timestamp(&self, field: &[u8]) -> ParseError {
+        ParseError::InvalidTimestamp {
+            pos: self.pos(field),
+            field: String::from_utf8_lossy(field).into_owned(),
+        }
+    }
+
+    fn device(&self, field: &[u8]) -> ParseError {
+        ParseError::InvalidDeviceName {
+            pos: self.pos(field),
+            field: String::from_utf8_lossy(field).into_owned(),
+        }
+    }
+
+    fn frame(&self, field: &[u8]) -> ParseError {
+        ParseError::InvalidCanFrame {
+            pos: self.pos(field),
+            field: String::from_utf8_lossy(field).into_owned(),
+        }
+    }
+
+    fn construction(&self, field: &[u8], err: super::ConstructionError) -> ParseError {
+        ParseError::ConstructionError {
+            pos: self.pos(field),
+            field: String::from_utf8_lossy(field).into_owned(),
+            source: err,
+        }
     }
 }
 
-impl<R: io::BufRead> Reader<R> {
-    /// Returns an iterator over all records
-    pub fn records(&mut self) -> CanDumpRecords<R> {
-        CanDumpRecords { src: self }
+/// The state of parsing a candump log, line by line.
+///
+/// This is shared by the blocking and async readers, which only differ
+/// in how they get the lines.
+#[derive(Debug, Default)]
+pub(crate) struct LineParser {
+    // The number of lines read so far
+    pub line: u64,
+    // Whether to skip lines that can't be parsed
+    pub lenient: bool,
+    // The number of lines skipped in lenient mode
+    pub skipped: u64,
+}
+
+impl LineParser {
+    /// Parses the next line of the log, including any line ending.
+    ///
+    /// The record borrows the device name from the line. In lenient mode,
+    /// lines that can not be parsed are counted and return `None`.
+    pub fn parse<'a>(&mut self, line: &'a [u8]) -> Result<Option<CanDumpRecord<'a>>, ParseError> {
+        self.line += 1;
+
+        // cut off the line ending
+        let mut line = line;
+        if let Some(&b'\n') = line.last() {
+            line = &line[..line.len() - 1];
+        }
+        if let Some(&b'\r') = line.last() {
+            line = &line[..line.len() - 1];
+        }
+
+        match parse_line(line, self.line) {
+            Ok(rec) => Ok(Some(rec)),
+            Err(_) if self.lenient => {
+                self.skipped += 1;
+                Ok(None)
+            }
+            Err(err) => Err(err),
+        }
     }
 
-    /// Advance state, returning next record.
-    pub fn next_record(&mut self) -> Result<Option<CanDumpRecord>, ParseError> {
+    /// Parses the next line of the log into an owned record.
+    #[cfg(any(
+        feature = "tokio",
+        feature = "async-io",
+        feature = "async-std",
+        feature = "smol"
+    ))]
+    pub fn parse_record(&mut self, line: &[u8]) -> Result<Option<LogRecord>, ParseError> {
+        Ok(self.parse(line)?.map(LogRecord::from))
+    }
+}
+
+/// How long the async readers wait, when following a log, before they
+/// look for more data at the end of the input.
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The next step for an async reader, from the data in its reader's buffer.
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+#[derive(Debug)]
+enum LineStep {
+    /// Consume the number of bytes from the reader's buffer, then return the
+    /// parsed line, if there is one.
+    Consume(usize, Option<Result<Option<LogRecord>, ParseError>>),
+    /// Wait, then look for more data at the end of the input
+    Wait,
+    /// The log is finished
+    End,
+}
+
+/// Splits the data from the buffer of an async reader into lines, and
+/// parses them.
+///
+/// A line is only parsed once its newline arrives, or at the end of the
+/// input when not following the log.
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+#[derive(Debug, Default)]
+pub(crate) struct LineSplitter {
+    // The parser for the lines
+    pub parser: LineParser,
+    // Whether to wait for more data at the end of the input
+    pub follow: bool,
+    // The part of the line read so far
+    line_buf: Vec<u8>,
+}
+
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+impl LineSplitter {
+    /// Takes the data in the reader's buffer, which is empty at the end
+    /// of the input, and determines what to do next.
+    fn step(&mut self, buf: &[u8]) -> LineStep {
+        if buf.is_empty() {
+            return if self.follow {
+                LineStep::Wait
+            } else if self.line_buf.is_empty() {
+                LineStep::End
+            } else {
+                // The last line of a finished log doesn't need a newline
+                LineStep::Consume(0, Some(self.parse_line()))
+            };
+        }
+
+        match buf.iter().position(|&c| c == b'\n') {
+            Some(i) => {
+                self.line_buf.extend_from_slice(&buf[..=i]);
+                LineStep::Consume(i + 1, Some(self.parse_line()))
+            }
+            None => {
+                self.line_buf.extend_from_slice(buf);
+                LineStep::Consume(buf.len(), None)
+            }
+        }
+    }
+
+    /// Parses the line that was collected, and clears it for the next one.
+    fn parse_line(&mut self) -> Result<Option<LogRecord>, ParseError> {
+        let res = self.parser.parse_record(&self.line_buf);
         self.line_buf.clear();
-        let bytes_read = self.rdr.read_until(b'\n', &mut self.line_buf)?;
+        res
+    }
+}
 
-        // reached EOF
-        if bytes_read == 0 {
-            return Ok(None);
+/// The `poll_fill_buf()` function of an async buffered reader.
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+type PollFillBuf<R> = for<'a> fn(Pin<&'a mut R>, &mut Context<'_>) -> Poll<io::Result<&'a [u8]>>;
+
+/// The state of an async log reader, with the poll loop that reads and
+/// parses the lines.
+///
+/// This is shared by the async readers. Each runtime gives the functions
+/// to fill and consume the buffer of its reader, and to create a timer
+/// to wait on when following the log.
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+#[derive(Debug)]
+pub(crate) struct AsyncLines<R, W> {
+    // The buffered async reader
+    pub rdr: R,
+    // The lines split from the reader's data
+    pub lines: LineSplitter,
+    // The wait for more data, when following the log
+    wait: Option<W>,
+}
+
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+impl<R: Unpin, W: Future + Unpin> AsyncLines<R, W> {
+    /// Creates the reader state for the buffered async reader.
+    pub fn new(rdr: R) -> Self {
+        Self {
+            rdr,
+            lines: LineSplitter::default(),
+            wait: None,
         }
+    }
 
-        let mut field_iter = self.line_buf.split(|&c| c == b' ');
+    /// Polls for the next record in the log.
+    pub fn poll_next(
+        &mut self,
+        cx: &mut Context<'_>,
+        fill_buf: PollFillBuf<R>,
+        consume: fn(Pin<&mut R>, usize),
+        timer: fn(Duration) -> W,
+    ) -> Poll<Option<Result<LogRecord, ParseError>>> {
+        loop {
+            if let Some(wait) = self.wait.as_mut() {
+                match Pin::new(wait).poll(cx) {
+                    Poll::Ready(_) => self.wait = None,
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
 
-        // parse time field
-        let f = field_iter.next().ok_or(ParseError::UnexpectedEndOfLine)?;
+            let buf = match fill_buf(Pin::new(&mut self.rdr), cx) {
+                Poll::Ready(Ok(buf)) => buf,
+                Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
+                Poll::Pending => return Poll::Pending,
+            };
 
-        if f.len() < 3 || f[0] != b'(' || f[f.len() - 1] != b')' {
-            return Err(ParseError::InvalidTimestamp);
+            match self.lines.step(buf) {
+                LineStep::Consume(n, res) => {
+                    consume(Pin::new(&mut self.rdr), n);
+                    match res {
+                        Some(Ok(Some(rec))) => return Poll::Ready(Some(Ok(rec))),
+                        Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                        Some(Ok(None)) | None => (),
+                    }
+                }
+                LineStep::Wait => self.wait = Some(timer(FOLLOW_INTERVAL)),
+                LineStep::End => return Poll::Ready(None),
+            }
         }
+    }
+}
 
-        let inner = &f[1..f.len() - 1];
+/// Parses a single line of a candump log, without the line ending.
+///
+/// The record borrows the device name from the line.
+fn parse_line(line: &[u8], line_num: u64) -> Result<CanDumpRecord<'_>, ParseError> {
+    let ctx = LineContext { line, line_num };
+    let mut field_iter = line.split(|&c| c == b' ');
 
-        // split at dot, read both parts
-        let dot = inner
-            .iter()
-            .position(|&c| c == b'.')
-            .ok_or(ParseError::InvalidTimestamp)?;
+    // parse time field
+    let f = field_iter.next().ok_or_else(|| ctx.end_of_line())?;
 
-        let (num, mant) = inner.split_at(dot);
+    if f.len() < 3 || f[0] != b'(' || f[f.len() - 1] != b')' {
+        return Err(ctx.timestamp(f));
+    }
 
-        // parse number and multiply
-        let n_num: u64 = parse_raw(num, 10).ok_or(ParseError::InvalidTimestamp)?;
-        let n_mant: u64 = parse_raw(&mant[1..], 10).ok_or(ParseError::InvalidTimestamp)?;
-        let t_us = n_num.saturating_mul(1_000_000).saturating_add(n_mant);
+    let t_us = parse_timestamp(&f[1..f.len() - 1]).ok_or_else(|| ctx.timestamp(f))?;
 
-        let f = field_iter.next().ok_or(ParseError::UnexpectedEndOfLine)?;
+    let f = field_iter.next().ok_or_else(|| ctx.end_of_line())?;
 
-        // device name
-        let device = ::std::str::from_utf8(f).map_err(|_| ParseError::InvalidDeviceName)?;
+    // device name
+    let device = match std::str::from_utf8(f) {
+        Ok(device) if !device.is_empty() => device,
+        _ => return Err(ctx.device(f)),
+    };
 
-        // parse packet
-        let can_raw = field_iter.next().ok_or(ParseError::UnexpectedEndOfLine)?;
+    // parse packet
+    let can_raw = field_iter.next().ok_or_else(|| ctx.end_of_line())?;
 
-        let sep_idx = can_raw
-            .iter()
-            .position(|&c| c == b'#')
-            .ok_or(ParseError::InvalidCanFrame)?;
-        let (can_id, mut can_data) = can_raw.split_at(sep_idx);
+    let frame = parse_frame(can_raw).map_err(|err| match err {
+        ParseFrameError::InvalidFormat => ctx.frame(can_raw),
+        ParseFrameError::Construction(err) => ctx.construction(can_raw, err),
+    })?;
+    Ok(CanDumpRecord {
+        t_us,
+        device,
+        frame,
+    })
+}
 
-        // determine frame type (FD or classical) and skip separator(s)
-        let mut fd_flags = FdFlags::empty();
-        let is_fd_frame = if let Some(&b'#') = can_data.get(1) {
-            fd_flags = FdFlags::from_bits_truncate(can_data[2]);
-            can_data = &can_data[3..];
-            true
-        } else {
-            can_data = &can_data[1..];
-            false
-        };
+impl<R: io::BufRead> Reader<R> {
+    /// Creates a reader from a buffered reader.
+    pub fn new(rdr: R) -> Self {
+        Self {
+            rdr,
+            line_buf: Vec::new(),
+            device: String::new(),
+            parser: LineParser::default(),
+        }
+    }
 
-        // cut of linefeed
-        if let Some(&b'\n') = can_data.last() {
-            can_data = &can_data[..can_data.len() - 1];
-        };
-        // cut off \r
-        if let Some(&b'\r') = can_data.last() {
-            can_data = &can_data[..can_data.len() - 1];
-        };
+    /// Sets whether the reader is lenient.
+    ///
+    /// A lenient reader skips over lines that it can not parse, rather
+    /// than returning an error. This keeps a corrupt line from stopping
+    /// the import of a large log. The number of lines that were skipped
+    /// is available from [`skipped()`](Reader::skipped).
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.parser.lenient = lenient;
+    }
 
-        let mut flags = IdFlags::empty();
-        flags.set(IdFlags::RTR, b"R" == can_data);
-        // TODO: How are error frames saved?
+    /// Determines if the reader is lenient.
+    pub fn is_lenient(&self) -> bool {
+        self.parser.lenient
+    }
 
-        let data = if flags.contains(IdFlags::RTR) {
-            Vec::new()
-        } else {
-            Vec::from_hex(can_data).map_err(|_| ParseError::InvalidCanFrame)?
-        };
-        let frame: super::CanAnyFrame = if is_fd_frame {
-            CanFdFrame::init(
-                parse_raw(can_id, 16).ok_or(ParseError::InvalidCanFrame)? as canid_t | flags.bits(),
-                &data,
-                fd_flags,
-            )
-            .map(super::CanAnyFrame::Fd)
-        } else {
-            // TODO: Check for other frame types?
-            // is extended?
-            let can_id = parse_raw(can_id, 16).ok_or(ParseError::InvalidCanFrame)?;
-            if can_id >= StandardId::MAX.as_raw() as u64 {
-                flags.set(IdFlags::EFF, true);
+    /// Gets the number of malformed lines skipped by a lenient reader.
+    pub fn skipped(&self) -> u64 {
+        self.parser.skipped
+    }
+
+    /// Gets the number of lines read so far.
+    pub fn line_number(&self) -> u64 {
+        self.parser.line
+    }
+
+    /// Returns an iterator over all records
+    pub fn records(&mut self) -> CanDumpRecords<'_, R> {
+        CanDumpRecords { src: self }
+    }
+
+    /// Advance state, returning next record.
+    ///
+    /// Once the buffers have grown to fit the longest line, parsing a
+    /// record doesn't allocate. The device name in the record borrows a
+    /// buffer in the reader.
+    ///
+    /// In lenient mode, lines that can not be parsed are skipped and
+    /// counted, and only I/O errors are returned.
+    pub fn next_record(&mut self) -> Result<Option<CanDumpRecord<'_>>, ParseError> {
+        let (t_us, frame) = loop {
+            self.line_buf.clear();
+            let bytes_read = self.rdr.read_until(b'\n', &mut self.line_buf)?;
+
+            // reached EOF
+            if bytes_read == 0 {
+                return Ok(None);
             }
-            CanDataFrame::init(can_id as canid_t | flags.bits(), &data)
-                .map(super::CanFrame::Data)
-                .map(|f| f.into())
-        }?;
+
+            // The device name is copied out, so that the line buffer isn't
+            // borrowed when the loop goes on to the next line.
+            if let Some(rec) = self.parser.parse(&self.line_buf)? {
+                self.device.clear();
+                self.device.push_str(rec.device);
+                break (rec.t_us, rec.frame);
+            }
+        };
 
         Ok(Some(CanDumpRecord {
             t_us,
-            device,
+            device: &self.device,
             frame,
         }))
     }
 }
 
-impl<'a, R: io::Read> Iterator for CanDumpRecords<'a, io::BufReader<R>> {
-    type Item = Result<(u64, super::CanAnyFrame), ParseError>;
+impl<R: io::BufRead> LogReader for Reader<R> {
+    fn read_record(&mut self) -> Result<Option<LogRecord>, logfile::Error> {
+        Ok(self.next_record()?.map(LogRecord::from))
+    }
+}
+
+impl<R: io::BufRead> Iterator for CanDumpRecords<'_, R> {
+    type Item = Result<(u64, CanAnyFrame), ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         // lift Option:
@@ -235,10 +675,86 @@ impl<'a, R: io::Read> Iterator for CanDumpRecords<'a, io::BufReader<R>> {
     }
 }
 
+// ===== Writer =====
+
+/// A CAN log writer.
+///
+/// This writes frames in the same format as `candump -l`.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    wtr: W,
+}
+
+impl Writer<FileEncoder> {
+    /// Creates a buffered writer to a new file.
+    ///
+    /// The file is compressed if it has an extension like ".gz", ".xz",
+    /// or ".zst", and the matching feature is enabled. The compressed
+    /// stream is finished when the writer is dropped.
+    pub fn create<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<path::Path>,
+    {
+        let comp = Compression::from_path(&path);
+        let file = io::BufWriter::new(fs::File::create(path)?);
+        Ok(Self::new(Encoder::new(file, comp)?))
+    }
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a CAN log writer.
+    pub fn new(wtr: W) -> Self {
+        Self { wtr }
+    }
+
+    /// Writes a frame to the log.
+    ///
+    /// The timestamp, `t_us`, is in microseconds since the Unix epoch.
+    pub fn write_frame(&mut self, t_us: u64, device: &str, frame: &CanAnyFrame) -> io::Result<()> {
+        writeln!(
+            self.wtr,
+            "({:010}.{:06}) {} {}",
+            t_us / 1_000_000,
+            t_us % 1_000_000,
+            device,
+            frame
+        )
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.wtr.flush()
+    }
+
+    /// Consumes the writer, returning the underlying I/O writer.
+    pub fn into_inner(self) -> W {
+        self.wtr
+    }
+}
+
+impl<W: Write> LogWriter for Writer<W> {
+    /// Writes the record to the log.
+    ///
+    /// The candump format does not record the direction of the frame,
+    /// so it is dropped.
+    fn write_record(&mut self, rec: &LogRecord) -> io::Result<()> {
+        let t_us = rec
+            .timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        self.write_frame(t_us, &rec.interface, &rec.frame)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.wtr.flush()
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::{CanAnyFrame, Frame};
+    use crate::{frame::FdFlags, CanAnyFrame, CanDataFrame, CanFdFrame, Frame, StandardId};
     use embedded_can::Frame as EmbeddedFrame;
 
     #[test]
@@ -259,7 +775,7 @@ mod test {
                 assert!(!frame.is_remote_frame());
                 assert!(!frame.is_error_frame());
                 assert!(!frame.is_extended());
-                assert_eq!(frame.data(), &[]);
+                assert_eq!(frame.data(), &[] as &[u8]);
             } else {
                 panic!("Expected Normal frame, got FD");
             }
@@ -299,10 +815,10 @@ mod test {
 
             if let CanAnyFrame::Normal(frame) = rec1.frame {
                 assert_eq!(frame.raw_id(), 0x080080);
-                assert_eq!(frame.is_remote_frame(), false);
-                assert_eq!(frame.is_error_frame(), false);
-                assert_eq!(frame.is_extended(), true);
-                assert_eq!(frame.data(), &[]);
+                assert!(!frame.is_remote_frame());
+                assert!(!frame.is_error_frame());
+                assert!(frame.is_extended());
+                assert_eq!(frame.data(), &[] as &[u8]);
             } else {
                 panic!("Expected Normal frame, got FD");
             }
@@ -315,9 +831,9 @@ mod test {
 
             if let CanAnyFrame::Normal(frame) = rec2.frame {
                 assert_eq!(frame.raw_id(), 0x053701);
-                assert_eq!(frame.is_remote_frame(), false);
-                assert_eq!(frame.is_error_frame(), false);
-                assert_eq!(frame.is_extended(), true);
+                assert!(!frame.is_remote_frame());
+                assert!(!frame.is_error_frame());
+                assert!(frame.is_extended());
                 assert_eq!(frame.data(), &[0x7F]);
             } else {
                 panic!("Expected Normal frame, got FD");
@@ -346,7 +862,7 @@ mod test {
                 assert!(!frame.is_extended());
                 assert!(!frame.is_brs());
                 assert!(!frame.is_esi());
-                assert_eq!(frame.data(), &[]);
+                assert_eq!(frame.data(), &[] as &[u8]);
             } else {
                 panic!("Expected FD frame, got Normal");
             }
@@ -371,4 +887,266 @@ mod test {
 
         assert!(reader.next_record().unwrap().is_none());
     }
+
+    #[test]
+    fn test_write_read() {
+        use crate::{CanErrorFrame, CanRemoteFrame, ExtendedId};
+
+        let frames = [
+            CanAnyFrame::Normal(
+                CanDataFrame::new(StandardId::new(0x7FF).unwrap(), &[0x12, 0xAB]).unwrap(),
+            ),
+            CanAnyFrame::Normal(CanDataFrame::new(ExtendedId::new(0x50).unwrap(), &[]).unwrap()),
+            CanAnyFrame::Remote(
+                CanRemoteFrame::new_remote(StandardId::new(0x123).unwrap(), 3).unwrap(),
+            ),
+            CanAnyFrame::Error(CanErrorFrame::new_error(0x0004, &[0, 0x10]).unwrap()),
+            CanAnyFrame::Fd(
+                CanFdFrame::with_flags(StandardId::new(0x1).unwrap(), &[0x55; 12], FdFlags::BRS)
+                    .unwrap(),
+            ),
+        ];
+
+        let mut wtr = Writer::new(Vec::new());
+        for (i, frame) in frames.iter().enumerate() {
+            wtr.write_frame(1469439874299591 + i as u64, "can1", frame)
+                .unwrap();
+        }
+        let buf = wtr.into_inner();
+
+        let text = std::str::from_utf8(&buf).unwrap();
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                "(1469439874.299591) can1 7FF#12AB",
+                "(1469439874.299592) can1 00000050#",
+                "(1469439874.299593) can1 123#R3",
+                "(1469439874.299594) can1 20000004#0010000000000000",
+                "(1469439874.299595) can1 001##1555555555555555555555555",
+            ]
+        );
+
+        let mut reader = Reader::from_reader(buf.as_slice());
+        for (i, frame) in frames.iter().enumerate() {
+            let rec = reader.next_record().unwrap().unwrap();
+            assert_eq!(rec.t_us, 1469439874299591 + i as u64);
+            match (frame, rec.frame) {
+                (CanAnyFrame::Normal(a), CanAnyFrame::Normal(b)) => {
+                    assert_eq!(a.id(), b.id());
+                    assert_eq!(a.data(), b.data());
+                }
+                (CanAnyFrame::Remote(a), CanAnyFrame::Remote(b)) => {
+                    assert_eq!(a.id(), b.id());
+                    assert_eq!(a.dlc(), b.dlc());
+                }
+                (CanAnyFrame::Error(a), CanAnyFrame::Error(b)) => {
+                    assert_eq!(a.id_word(), b.id_word());
+                    assert_eq!(a.data(), b.data());
+                }
+                (CanAnyFrame::Fd(a), CanAnyFrame::Fd(b)) => {
+                    assert_eq!(a.id(), b.id());
+                    assert_eq!(a.flags(), b.flags());
+                    assert_eq!(a.data(), b.data());
+                }
+                _ => panic!("Frame type mismatch"),
+            }
+        }
+        assert!(reader.next_record().unwrap().is_none());
+    }
+
+    // Writes and reads back a log file, compressed by the extension.
+    fn file_round_trip(ext: &str) {
+        let path =
+            std::env::temp_dir().join(format!("socketcan-dump-{}-{}", std::process::id(), ext));
+        let frame = CanAnyFrame::Normal(
+            CanDataFrame::new(StandardId::new(0x123).unwrap(), &[1, 2, 3]).unwrap(),
+        );
+
+        {
+            let mut wtr = Writer::create(&path).unwrap();
+            for i in 0..100 {
+                wtr.write_frame(1469439874299591 + i, "can1", &frame)
+                    .unwrap();
+            }
+            wtr.into_inner().try_finish().unwrap();
+        }
+
+        let mut reader = Reader::from_file(&path).unwrap();
+        let n = reader
+            .records()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+            .len();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(100, n);
+    }
+
+    #[test]
+    fn test_file() {
+        file_round_trip("can.log");
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_file_gzip() {
+        file_round_trip("can.log.gz");
+    }
+
+    #[cfg(feature = "xz")]
+    #[test]
+    fn test_file_xz() {
+        file_round_trip("can.log.xz");
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_file_zstd() {
+        file_round_trip("can.log.zst");
+    }
+
+    #[test]
+    fn test_low_level() {
+        assert_eq!(Some(1234), parse_dec(b"1234"));
+        assert_eq!(Some(u64::MAX), parse_dec(b"18446744073709551615"));
+        assert_eq!(None, parse_dec(b"18446744073709551616"));
+        assert_eq!(None, parse_dec(b""));
+        assert_eq!(None, parse_dec(b"12a"));
+
+        assert_eq!(
+            Some(1469439874299654),
+            parse_timestamp(b"1469439874.299654")
+        );
+        assert_eq!(Some(1469439874290000), parse_timestamp(b"1469439874.29"));
+        assert_eq!(
+            Some(1469439874299654),
+            parse_timestamp(b"1469439874.299654321")
+        );
+        assert_eq!(None, parse_timestamp(b"1469439874"));
+        assert_eq!(None, parse_timestamp(b"1469439874."));
+        assert_eq!(None, parse_timestamp(b"1469439874.2996543x1"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let input: &[u8] = b"(1469439874.299591) can1 080#\n\
+                             (1469439874.29x654) can1 701#7F\n\
+                             (1469439874.299654) can1\n\
+                             (1469439874.299654) can1 701#7G\n\
+                             (1469439874.299654) can1 701#112233445566778899\n";
+
+        let mut reader = Reader::from_reader(input);
+        assert!(reader.next_record().unwrap().is_some());
+
+        let err = reader.next_record().unwrap_err();
+        assert!(matches!(err, ParseError::InvalidTimestamp { .. }));
+        assert_eq!(Some(Position { line: 2, offset: 0 }), err.position());
+        assert_eq!(Some("(1469439874.29x654)"), err.field());
+        assert_eq!(
+            "line 2, offset 0: invalid timestamp: '(1469439874.29x654)'",
+            err.to_string()
+        );
+
+        let err = reader.next_record().unwrap_err();
+        assert!(matches!(err, ParseError::UnexpectedEndOfLine { .. }));
+        assert_eq!(
+            Some(Position {
+                line: 3,
+                offset: 24
+            }),
+            err.position()
+        );
+
+        let err = reader.next_record().unwrap_err();
+        assert!(matches!(err, ParseError::InvalidCanFrame { .. }));
+        assert_eq!(
+            Some(Position {
+                line: 4,
+                offset: 25
+            }),
+            err.position()
+        );
+        assert_eq!(Some("701#7G"), err.field());
+
+        let err = reader.next_record().unwrap_err();
+        assert!(matches!(err, ParseError::ConstructionError { .. }));
+        assert_eq!(5, err.position().unwrap().line);
+
+        assert!(reader.next_record().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_lenient() {
+        let input: &[u8] = b"(1469439874.299591) can1 080#\n\
+                             garbage\n\
+                             \n\
+                             (1469439874.299654) can1 701#7G\n\
+                             (1469439874.299654) can1 701#7F\n";
+
+        let mut reader = Reader::from_reader(input);
+        reader.set_lenient(true);
+
+        let frames = reader.records().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(2, frames.len());
+        assert_eq!(3, reader.skipped());
+        assert_eq!(5, reader.line_number());
+    }
+
+    #[test]
+    fn test_device_name() {
+        let input: &[u8] = b"(1469439874.299591) c\xFFn1 080#\n\
+                             (1469439874.299654) vcan0 701#7F\n";
+
+        let mut reader = Reader::from_reader(input);
+        let err = reader.next_record().unwrap_err();
+        assert!(matches!(err, ParseError::InvalidDeviceName { .. }));
+
+        // A lenient reader returns the record after the skipped line
+        let mut reader = Reader::from_reader(input);
+        reader.set_lenient(true);
+        let rec = reader.next_record().unwrap().unwrap();
+        assert_eq!("vcan0", rec.device);
+        assert_eq!(1, reader.skipped());
+    }
+
+    #[cfg(any(
+        feature = "tokio",
+        feature = "async-io",
+        feature = "async-std",
+        feature = "smol"
+    ))]
+    #[test]
+    fn test_line_splitter() {
+        let line = b"(1469439874.299591) can1 080#\n";
+        let mut lines = LineSplitter::default();
+
+        // A partial line is kept until its newline arrives
+        assert!(matches!(
+            lines.step(&line[..10]),
+            LineStep::Consume(10, None)
+        ));
+        let LineStep::Consume(n, Some(Ok(Some(rec)))) = lines.step(&line[10..]) else {
+            panic!("Expected a record");
+        };
+        assert_eq!(line.len() - 10, n);
+        assert_eq!("can1", rec.interface);
+        assert!(matches!(lines.step(&[]), LineStep::End));
+
+        // The last line of a finished log doesn't need a newline
+        lines.step(&line[..line.len() - 1]);
+        assert!(matches!(
+            lines.step(&[]),
+            LineStep::Consume(0, Some(Ok(Some(_))))
+        ));
+
+        // When following, a partial line waits for more data
+        lines.follow = true;
+        lines.step(&line[..10]);
+        assert!(matches!(lines.step(&[]), LineStep::Wait));
+        assert!(matches!(
+            lines.step(&line[10..]),
+            LineStep::Consume(_, Some(Ok(Some(_))))
+        ));
+        assert_eq!(3, lines.parser.line);
+    }
 }
@@ -22,6 +22,10 @@ use libudev::{Context, Enumerator};
 
 /// Scans the system for available SocketCAN network interfaces and returns a
 /// list of them.
+///
+/// This requires udev. With the `netlink` feature,
+/// [`CanInterface::list()`](crate::nl::CanInterface::list) gets the
+/// interfaces, with their details, straight from the kernel instead.
 pub fn available_interfaces() -> Result<Vec<String>> {
     let mut interfaces = Vec::new();
     if let Ok(context) = Context::new() {
@@ -121,7 +121,22 @@ pub type IoResult<T> = io::Result<T>;
 /// word of an error frame - a frame in which the CAN error flag
 /// (`CAN_ERR_FLAG`) is set. But there are additional types to handle any
 /// problems decoding the error frame.
+///
+/// With the `serde` feature, the error is serialized in the default,
+/// externally tagged, form, keyed by the variant name. Variants without
+/// data are just the name, the others are a map from the name to the data,
+/// and the nested problem and location types are serialized by their names.
+/// In JSON:
+///
+/// ```text
+/// "BusOff"
+/// {"LostArbitration": 5}
+/// {"ControllerProblem": "ReceiveBufferOverflow"}
+/// {"ProtocolViolation": {"vtype": "BitStuffingError", "location": "StartOfFrame"}}
+/// {"DecodingFailure": {"UnknownErrorType": 4096}}
+/// ```
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CanError {
     /// TX timeout (by netdevice driver)
     TransmitTimeout,
@@ -232,6 +247,7 @@ impl From<CanErrorFrame> for CanError {
 /// This is derived from `data[1]` of an error frame
 #[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
 #[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ControllerProblem {
     /// unspecified
     Unspecified = 0x00,
@@ -296,6 +312,7 @@ impl TryFrom<u8> for ControllerProblem {
 /// This is derived from `data[2]` of an error frame.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ViolationType {
     /// Unspecified Violation
     Unspecified = 0x00,
@@ -365,6 +382,7 @@ impl TryFrom<u8> for ViolationType {
 /// This is derived from `data[3]` of an error frame.
 #[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
 #[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Location {
     /// Unspecified
     Unspecified = 0x00,
@@ -474,6 +492,7 @@ impl TryFrom<u8> for Location {
 /// This is derived from `data[4]` of an error frame.
 #[derive(Debug, Clone, Copy, PartialOrd, Ord, PartialEq, Eq)]
 #[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TransceiverError {
     /// Unsecified
     Unspecified = 0x00,
@@ -541,6 +560,7 @@ impl<T: Frame> ControllerSpecificErrorInformation for T {
 
 /// Error decoding a CanError from a CanErrorFrame.
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CanErrorDecodingFailure {
     /// The supplied CANFrame did not have the error bit set.
     NotAnError,
@@ -590,6 +610,12 @@ pub enum ConstructionError {
     IDTooLarge,
     /// Larger payload reported than can be held in the frame.
     TooMuchData,
+    /// The data length code is not valid for the frame
+    InvalidDlc,
+    /// A signal does not fit within the data of the frame
+    SignalOutOfBounds,
+    /// A value does not fit within the bits of a signal
+    ValueOutOfRange,
 }
 
 impl error::Error for ConstructionError {}
@@ -601,11 +627,50 @@ impl fmt::Display for ConstructionError {
             WrongFrameType => "Incompatible frame type",
             IDTooLarge => "CAN ID too large",
             TooMuchData => "Payload is too large",
+            InvalidDlc => "Invalid data length code",
+            SignalOutOfBounds => "Signal does not fit in the frame data",
+            ValueOutOfRange => "Value out of range for the signal",
         };
         write!(f, "{}", msg)
     }
 }
 
+// ===== ParseFrameError =====
+
+#[derive(Debug, Copy, Clone, PartialOrd, Ord, PartialEq, Eq)]
+/// Error that occurs when parsing a frame from text in the `cansend`
+/// format, like "123#DEADBEEF".
+pub enum ParseFrameError {
+    /// The text is not a frame in the `cansend` format
+    InvalidFormat,
+    /// The text was well formed, but a frame could not be made from it
+    Construction(ConstructionError),
+}
+
+impl error::Error for ParseFrameError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            ParseFrameError::InvalidFormat => None,
+            ParseFrameError::Construction(err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for ParseFrameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseFrameError::InvalidFormat => write!(f, "Invalid frame format"),
+            ParseFrameError::Construction(err) => write!(f, "Invalid frame: {}", err),
+        }
+    }
+}
+
+impl From<ConstructionError> for ParseFrameError {
+    fn from(err: ConstructionError) -> Self {
+        ParseFrameError::Construction(err)
+    }
+}
+
 /////////////////////////////////////////////////////////////////////////////
 
 #[cfg(test)]
@@ -633,4 +698,58 @@ mod tests {
             panic!("Wrong error conversion");
         }
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use super::{
+            CanError, CanErrorDecodingFailure, ControllerProblem, Location, ViolationType,
+        };
+        use crate::{CanErrorFrame, CanFilter};
+
+        let err = CanError::from(CanErrorFrame::new_error(0x0008, &[0, 0, 0x04, 0x03]).unwrap());
+        let s = serde_json::to_string(&err).unwrap();
+        let err: CanError = serde_json::from_str(&s).unwrap();
+        assert!(matches!(
+            err,
+            CanError::ProtocolViolation {
+                vtype: ViolationType::BitStuffingError,
+                location: Location::StartOfFrame,
+            }
+        ));
+
+        // the representation is part of the API, so is pinned down here
+        let json = |err: CanError| serde_json::to_value(err).unwrap();
+        assert_eq!(json(CanError::BusOff), serde_json::json!("BusOff"));
+        assert_eq!(
+            json(CanError::LostArbitration(5)),
+            serde_json::json!({"LostArbitration": 5})
+        );
+        assert_eq!(
+            json(CanError::ControllerProblem(
+                ControllerProblem::ReceiveBufferOverflow
+            )),
+            serde_json::json!({"ControllerProblem": "ReceiveBufferOverflow"})
+        );
+        assert_eq!(
+            json(err),
+            serde_json::json!({
+                "ProtocolViolation": {"vtype": "BitStuffingError", "location": "StartOfFrame"}
+            })
+        );
+        assert_eq!(
+            json(CanError::DecodingFailure(
+                CanErrorDecodingFailure::UnknownErrorType(0x1000)
+            )),
+            serde_json::json!({"DecodingFailure": {"UnknownErrorType": 4096}})
+        );
+
+        let filt = CanFilter::new_inverted(0x123, 0x7FF);
+        let val = serde_json::to_value(filt).unwrap();
+        assert_eq!(
+            val,
+            serde_json::json!({"id": 0x123, "mask": 0x7FF, "inverted": true})
+        );
+        assert_eq!(filt, serde_json::from_value(val).unwrap());
+    }
 }
@@ -29,15 +29,85 @@
 //!   can not be sent to the bus, but can be converted to standard Rust
 //!   [Error](https://doc.rust-lang.org/std/error/trait.Error.html) types.
 //!
+//! # Text Format
+//!
+//! The frames implement `Display` and `FromStr` using the syntax of the
+//! `cansend` and `candump` tools from can-utils:
+//!
+//! - `123#DEADBEEF` - A data frame with a standard ID (3 hex digits)
+//! - `1F334455#11.22.33` - A data frame with an extended ID (8 hex
+//!   digits), with optional '.' separators between the data bytes
+//! - `123#0011223344556677_F` - A data frame with 8 bytes and a raw
+//!   DLC of 9-15 (see [`CanDataFrame::len8_dlc()`])
+//! - `123#R` or `1F334455#R3` - A remote frame, with an optional DLC
+//! - `123##1AABB` - An FD frame, with the FD flags as a single hex digit
+//!   after the "##"
+//!
+//! An ID with the error flag set, like `20000004#0004000000000000`,
+//! is an error frame.
+//!
+//! ```
+//! use socketcan::{CanFrame, EmbeddedFrame};
+//!
+//! let frame: CanFrame = "123#DE.AD.BE.EF".parse().unwrap();
+//! assert_eq!(frame.data(), &[0xDE, 0xAD, 0xBE, 0xEF]);
+//! assert_eq!(frame.to_string(), "123#DEADBEEF");
+//! ```
+//!
+//! # Signals
+//!
+//! The [`Frame`] trait can read and write signals, which are bit fields
+//! of any size and position within the data, in Intel or Motorola byte
+//! order, using the same bit numbering as DBC files. A [`SignalLayout`]
+//! adds the signal type, scale, and offset to convert between the raw and
+//! physical values.
+//!
+//! ```
+//! use socketcan::{ByteOrder, CanDataFrame, EmbeddedFrame, Frame, StandardId};
+//!
+//! let id = StandardId::new(0x100).unwrap();
+//! let mut frame = CanDataFrame::new(id, &[0x0A, 0xBC, 0, 0]).unwrap();
+//! assert_eq!(Some(0xABC), frame.get_signal(3, 12, ByteOrder::Motorola));
+//!
+//! frame.set_signal(16, 12, ByteOrder::Intel, 0xABC).unwrap();
+//! assert_eq!(frame.data(), &[0x0A, 0xBC, 0xBC, 0x0A]);
+//! ```
+//!
+//! # Serialization
+//!
+//! With the `serde` feature, the frames can be serialized and deserialized.
+//!
+//! The frames are serialized as structs with the ID, flags, and data as
+//! separate fields, rather than the raw C structs. The individual frame
+//! types have these fields, where `id` is the raw ID without any flags,
+//! and `data` is a sequence of bytes:
+//!
+//! - `CanDataFrame`: `{ "id", "extended", "data" }`, plus `"len8_dlc"`
+//!   if the frame has a raw DLC above 8
+//! - `CanRemoteFrame`: `{ "id", "extended", "dlc" }`
+//! - `CanErrorFrame`: `{ "id", "data" }` where `id` has the error class bits
+//! - `CanFdFrame`: `{ "id", "extended", "brs", "esi", "data" }`
+//!
+//! The enums, `CanFrame` and `CanAnyFrame`, add a `type` field to tell
+//! them apart, which is one of "data", "remote", "error", or "fd". So,
+//! in JSON, a data frame in a `CanFrame` looks like:
+//!
+//! ```text
+//! {"type":"data","id":291,"extended":false,"data":[1,2,3]}
+//! ```
+//!
+//! Since the `type` tag is read before the rest of the frame, the enums
+//! need a self-describing format, like JSON or MessagePack.
 
-use crate::{CanError, ConstructionError};
+use crate::{CanError, ConstructionError, ParseFrameError};
 use bitflags::bitflags;
 use embedded_can::{ExtendedId, Frame as EmbeddedFrame, Id, StandardId};
 use itertools::Itertools;
-use libc::{can_frame, canfd_frame, canid_t};
+use libc::{can_frame, canfd_frame, canid_t, CANFD_MTU, CAN_MTU};
 use std::{
     ffi::c_void,
     mem::size_of,
+    str::FromStr,
     {convert::TryFrom, fmt, matches, mem},
 };
 
@@ -46,6 +116,12 @@ pub use libc::{
     CAN_MAX_DLEN, CAN_RTR_FLAG, CAN_SFF_MASK,
 };
 
+/// The largest raw data length code (DLC) for a frame.
+///
+/// Classic frames can carry a DLC of 9-15 with 8 data bytes, and FD frames
+/// use these to encode lengths from 12 to 64 bytes.
+pub const CAN_MAX_RAW_DLC: usize = 15;
+
 /// An error mask that will cause SocketCAN to report all errors
 pub const ERR_MASK_ALL: u32 = CAN_ERR_MASK;
 
@@ -91,7 +167,7 @@ pub fn id_is_extended(id: &Id) -> bool {
 ///
 /// If the `id` is <= 0x7FF, it's assumed to be a standard ID, otherwise
 /// it is created as an Extened ID. If you require an Extended ID <= 0x7FF,
-/// create it explicitly.
+/// create it explicitly, or use a [`FrameBuilder`].
 pub fn id_from_raw(id: u32) -> Option<Id> {
     let id = match id {
         n if n <= CAN_SFF_MASK => StandardId::new(n as u16)?.into(),
@@ -100,6 +176,35 @@ pub fn id_from_raw(id: u32) -> Option<Id> {
     Some(id)
 }
 
+/// Gets the payload length of an FD frame from its data length code.
+///
+/// DLC's 0-8 are the length, and 9-15 are 12, 16, 20, 24, 32, 48, and 64
+/// bytes. Returns `None` if the DLC is larger than 15.
+pub fn dlc_to_len(dlc: usize) -> Option<usize> {
+    const LENS: [usize; CAN_MAX_RAW_DLC + 1] =
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 12, 16, 20, 24, 32, 48, 64];
+    LENS.get(dlc).copied()
+}
+
+/// Gets the smallest FD data length code that can hold `len` bytes.
+///
+/// Returns `None` if the length is larger than an FD frame can hold.
+pub fn len_to_dlc(len: usize) -> Option<usize> {
+    match len {
+        0..=CAN_MAX_DLEN => Some(len),
+        9..=CANFD_MAX_DLEN => (9..=CAN_MAX_RAW_DLC).find(|&dlc| dlc_to_len(dlc) >= Some(len)),
+        _ => None,
+    }
+}
+
+/// Gets the length that an FD payload of `len` bytes is padded out to,
+/// which is the next valid FD frame size.
+///
+/// Returns `None` if the length is larger than an FD frame can hold.
+pub fn fd_padded_len(len: usize) -> Option<usize> {
+    len_to_dlc(len).and_then(dlc_to_len)
+}
+
 // ===== can_frame =====
 
 /// Creates a default C `can_frame`.
@@ -164,7 +269,8 @@ pub trait Frame: EmbeddedFrame {
     ///
     /// If the `id` is <= 0x7FF, it's assumed to be a standard ID, otherwise
     /// it is created as an Extened ID. If you require an Etended ID <= 0x7FF,
-    /// use `new()`.
+    /// use `new()`, or a [`FrameBuilder`], which also reports why a frame
+    /// could not be created.
     fn from_raw_id(id: u32, data: &[u8]) -> Option<Self> {
         Self::new(id_from_raw(id)?, data)
     }
@@ -224,6 +330,291 @@ pub trait Frame: EmbeddedFrame {
 
     /// Sets the data payload of the frame.
     fn set_data(&mut self, data: &[u8]) -> Result<(), ConstructionError>;
+
+    /// Reads the raw bits of a signal from the frame data.
+    ///
+    /// The signal is `size` bits, from 1 to 64, starting at `start_bit`,
+    /// in the byte order. See [`ByteOrder`] for how the bits are numbered.
+    /// Returns `None` if the signal does not fit in the data.
+    fn get_signal(&self, start_bit: usize, size: usize, order: ByteOrder) -> Option<u64> {
+        SignalLayout::new(start_bit, size, order).extract(self.data())
+    }
+
+    /// Writes the raw bits of a signal into the frame data.
+    ///
+    /// The data keeps its length, and the bits outside the signal are
+    /// unchanged. This fails if the signal does not fit in the data, or
+    /// `raw` does not fit in `size` bits, or for a frame without data, like
+    /// a remote frame.
+    fn set_signal(
+        &mut self,
+        start_bit: usize,
+        size: usize,
+        order: ByteOrder,
+        raw: u64,
+    ) -> Result<(), ConstructionError> {
+        let layout = SignalLayout::new(start_bit, size, order);
+        modify_data(self, |data| layout.insert(data, raw))
+    }
+
+    /// Reads the physical value of a signal from the frame data.
+    ///
+    /// Returns `None` if the signal does not fit in the data.
+    fn get_signal_value(&self, layout: &SignalLayout) -> Option<f64> {
+        layout.decode(self.data())
+    }
+
+    /// Writes the physical value of a signal into the frame data.
+    ///
+    /// This fails if the signal does not fit in the data, or the value
+    /// does not fit in the signal.
+    fn set_signal_value(
+        &mut self,
+        layout: &SignalLayout,
+        value: f64,
+    ) -> Result<(), ConstructionError> {
+        modify_data(self, |data| layout.encode(data, value))
+    }
+}
+
+/// Modifies a copy of the frame data in place, then sets it back into the
+/// frame. Remote frames do not have data to modify.
+fn modify_data<F, U>(frame: &mut F, update: U) -> Result<(), ConstructionError>
+where
+    F: Frame,
+    U: FnOnce(&mut [u8]) -> Result<(), ConstructionError>,
+{
+    if frame.is_remote_frame() {
+        return Err(ConstructionError::WrongFrameType);
+    }
+    let mut buf = [0u8; CANFD_MAX_DLEN];
+    let n = frame.data().len();
+    buf[..n].copy_from_slice(frame.data());
+    update(&mut buf[..n])?;
+    frame.set_data(&buf[..n])
+}
+
+// ===== Signals =====
+
+/// The byte order of a signal within the data of a frame.
+///
+/// The bits of the data are numbered as in a DBC file: bit 0 is the least
+/// significant bit of the first byte, bit 7 is its most significant bit,
+/// bit 8 is the least significant bit of the second byte, and so on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteOrder {
+    /// Little endian. The start bit is the least significant bit of the
+    /// signal, and the signal continues up into the following bytes.
+    Intel,
+    /// Big endian. The start bit is the most significant bit of the
+    /// signal, which continues down through the byte, then into the
+    /// following bytes, from their most significant bit.
+    ///
+    /// So a 12-bit signal starting at bit 3 has its top four bits in the
+    /// low nibble of the first byte, and the rest in the second byte.
+    Motorola,
+}
+
+/// The type of the raw value of a signal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalType {
+    /// An unsigned integer
+    Unsigned,
+    /// A two's complement signed integer
+    Signed,
+    /// A 32-bit IEEE float
+    Float,
+    /// A 64-bit IEEE double
+    Double,
+}
+
+/// The position and encoding of a signal within the data of a frame.
+///
+/// The physical value of the signal is the raw value, multiplied by the
+/// `factor`, plus the `offset`.
+///
+/// ```
+/// use socketcan::{ByteOrder, CanDataFrame, EmbeddedFrame, Frame, SignalLayout, StandardId};
+///
+/// let temp = SignalLayout::new(8, 8, ByteOrder::Intel)
+///     .signed()
+///     .scale(0.5, -10.0);
+///
+/// let mut frame = CanDataFrame::new(StandardId::new(0x100).unwrap(), &[0; 4]).unwrap();
+/// frame.set_signal_value(&temp, 21.5).unwrap();
+/// assert_eq!(frame.data(), &[0, 63, 0, 0]);
+/// assert_eq!(Some(21.5), frame.get_signal_value(&temp));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignalLayout {
+    /// The start bit. For an Intel signal this is the least significant
+    /// bit, and for Motorola, the most significant.
+    pub start_bit: usize,
+    /// The size of the signal, in bits, from 1 to 64
+    pub size: usize,
+    /// The byte order
+    pub byte_order: ByteOrder,
+    /// The type of the raw value
+    pub signal_type: SignalType,
+    /// The scale factor from the raw to the physical value
+    pub factor: f64,
+    /// The offset added to the scaled raw value
+    pub offset: f64,
+}
+
+impl SignalLayout {
+    /// Creates the layout of an unsigned signal, with a factor of one and
+    /// no offset.
+    pub fn new(start_bit: usize, size: usize, byte_order: ByteOrder) -> Self {
+        Self {
+            start_bit,
+            size,
+            byte_order,
+            signal_type: SignalType::Unsigned,
+            factor: 1.0,
+            offset: 0.0,
+        }
+    }
+
+    /// Makes the signal a two's complement signed integer.
+    pub fn signed(self) -> Self {
+        self.signal_type(SignalType::Signed)
+    }
+
+    /// Sets the type of the raw value.
+    pub fn signal_type(mut self, signal_type: SignalType) -> Self {
+        self.signal_type = signal_type;
+        self
+    }
+
+    /// Sets the scale factor and offset for the physical value.
+    pub fn scale(mut self, factor: f64, offset: f64) -> Self {
+        self.factor = factor;
+        self.offset = offset;
+        self
+    }
+
+    /// Gets the positions of the bits of the signal in the data, with the
+    /// index of each bit in the raw value.
+    fn bits(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (order, size) = (self.byte_order, self.size);
+        std::iter::successors(Some(self.start_bit), move |&pos| match order {
+            ByteOrder::Intel => Some(pos + 1),
+            ByteOrder::Motorola if pos % 8 == 0 => Some(pos + 15),
+            ByteOrder::Motorola => Some(pos - 1),
+        })
+        .take(size)
+        .enumerate()
+        .map(move |(i, pos)| match order {
+            ByteOrder::Intel => (pos, i),
+            ByteOrder::Motorola => (pos, size - 1 - i),
+        })
+    }
+
+    /// Whether the signal has a valid size, and fits in the data.
+    fn fits(&self, data: &[u8]) -> bool {
+        (1..=64).contains(&self.size) && self.bits().all(|(pos, _)| pos / 8 < data.len())
+    }
+
+    /// Extracts the raw bits of the signal from the data.
+    ///
+    /// Returns `None` if the signal does not fit in the data.
+    pub fn extract(&self, data: &[u8]) -> Option<u64> {
+        if !self.fits(data) {
+            return None;
+        }
+        Some(self.bits().fold(0, |raw, (pos, i)| {
+            raw | (((data[pos / 8] >> (pos % 8)) & 1) as u64) << i
+        }))
+    }
+
+    /// Packs the raw bits of the signal into the data.
+    ///
+    /// On error, the data is left untouched.
+    pub fn insert(&self, data: &mut [u8], raw: u64) -> Result<(), ConstructionError> {
+        if !self.fits(data) {
+            return Err(ConstructionError::SignalOutOfBounds);
+        }
+        if self.size < 64 && raw >> self.size != 0 {
+            return Err(ConstructionError::ValueOutOfRange);
+        }
+        for (pos, i) in self.bits() {
+            let mask = 1u8 << (pos % 8);
+            if (raw >> i) & 1 != 0 {
+                data[pos / 8] |= mask;
+            } else {
+                data[pos / 8] &= !mask;
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the raw bits as an integer, sign extending them for a signed
+    /// signal.
+    pub fn raw_to_int(&self, raw: u64) -> i64 {
+        match self.signal_type {
+            SignalType::Signed if (1..64).contains(&self.size) => {
+                let shift = 64 - self.size;
+                ((raw << shift) as i64) >> shift
+            }
+            _ => raw as i64,
+        }
+    }
+
+    /// Converts the raw bits of the signal to the physical value.
+    pub fn raw_to_physical(&self, raw: u64) -> f64 {
+        let x = match self.signal_type {
+            SignalType::Unsigned => raw as f64,
+            SignalType::Signed => self.raw_to_int(raw) as f64,
+            SignalType::Float => f32::from_bits(raw as u32) as f64,
+            SignalType::Double => f64::from_bits(raw),
+        };
+        x * self.factor + self.offset
+    }
+
+    /// Converts a physical value to the raw bits of the signal.
+    ///
+    /// Integer values are rounded to the nearest raw value. Returns `None`
+    /// if the value does not fit in the signal, or if a float or double
+    /// signal isn't 32 or 64 bits.
+    pub fn physical_to_raw(&self, value: f64) -> Option<u64> {
+        if !(1..=64).contains(&self.size) {
+            return None;
+        }
+        let x = (value - self.offset) / self.factor;
+        let size = self.size as i32;
+        match self.signal_type {
+            SignalType::Float => (size == 32).then_some((x as f32).to_bits() as u64),
+            SignalType::Double => (size == 64).then_some(x.to_bits()),
+            SignalType::Unsigned => {
+                let x = x.round();
+                (x >= 0.0 && x < 2f64.powi(size)).then_some(x as u64)
+            }
+            SignalType::Signed => {
+                let x = x.round();
+                let lim = 2f64.powi(size - 1);
+                let mask = u64::MAX >> (64 - self.size);
+                (x >= -lim && x < lim).then_some(x as i64 as u64 & mask)
+            }
+        }
+    }
+
+    /// Decodes the physical value of the signal from the data.
+    ///
+    /// Returns `None` if the signal does not fit in the data.
+    pub fn decode(&self, data: &[u8]) -> Option<f64> {
+        self.extract(data).map(|raw| self.raw_to_physical(raw))
+    }
+
+    /// Encodes the physical value of the signal into the data.
+    ///
+    /// On error, the data is left untouched.
+    pub fn encode(&self, data: &mut [u8], value: f64) -> Result<(), ConstructionError> {
+        let raw = self
+            .physical_to_raw(value)
+            .ok_or(ConstructionError::ValueOutOfRange)?;
+        self.insert(data, raw)
+    }
 }
 
 // ===== CanAnyFrame =====
@@ -472,6 +863,36 @@ impl Frame for CanFrame {
             Error(frame) => frame.set_data(data),
         }
     }
+
+    /// Writes the raw bits of a signal into the frame data.
+    fn set_signal(
+        &mut self,
+        start_bit: usize,
+        size: usize,
+        order: ByteOrder,
+        raw: u64,
+    ) -> Result<(), ConstructionError> {
+        use CanFrame::*;
+        match self {
+            Data(frame) => frame.set_signal(start_bit, size, order, raw),
+            Remote(frame) => frame.set_signal(start_bit, size, order, raw),
+            Error(frame) => frame.set_signal(start_bit, size, order, raw),
+        }
+    }
+
+    /// Writes the physical value of a signal into the frame data.
+    fn set_signal_value(
+        &mut self,
+        layout: &SignalLayout,
+        value: f64,
+    ) -> Result<(), ConstructionError> {
+        use CanFrame::*;
+        match self {
+            Data(frame) => frame.set_signal_value(layout, value),
+            Remote(frame) => frame.set_signal_value(layout, value),
+            Error(frame) => frame.set_signal_value(layout, value),
+        }
+    }
 }
 
 impl Default for CanFrame {
@@ -572,6 +993,44 @@ impl CanDataFrame {
             _ => Err(ConstructionError::TooMuchData),
         }
     }
+
+    /// Gets the raw data length code (DLC) of 9-15 that a frame with 8
+    /// data bytes was sent or received with.
+    ///
+    /// This is only used by interfaces with the `CAN_CTRLMODE_CC_LEN8_DLC`
+    /// mode enabled. It returns `None` if the frame does not have 8 data
+    /// bytes, or the DLC was not set above 8.
+    pub fn len8_dlc(&self) -> Option<usize> {
+        match self.0.len8_dlc as usize {
+            dlc @ 9..=CAN_MAX_RAW_DLC if self.0.can_dlc as usize == CAN_MAX_DLEN => Some(dlc),
+            _ => None,
+        }
+    }
+
+    /// Sets a raw data length code (DLC) of 9-15 for a frame with 8 data
+    /// bytes, or clears it with `None`.
+    ///
+    /// The DLC is only sent on interfaces with the `CAN_CTRLMODE_CC_LEN8_DLC`
+    /// mode enabled. It is an error to set it for a frame without 8 data
+    /// bytes, or to a value outside of 9-15.
+    pub fn set_len8_dlc(&mut self, dlc: Option<usize>) -> Result<(), ConstructionError> {
+        match dlc {
+            None => self.0.len8_dlc = 0,
+            Some(dlc @ 9..=CAN_MAX_RAW_DLC) if self.0.can_dlc as usize == CAN_MAX_DLEN => {
+                self.0.len8_dlc = dlc as u8
+            }
+            Some(_) => return Err(ConstructionError::InvalidDlc),
+        }
+        Ok(())
+    }
+
+    /// Gets the raw data length code (DLC) of the frame.
+    ///
+    /// This is the number of data bytes, unless a DLC of 9-15 was set for
+    /// a frame with 8 bytes. See [`len8_dlc()`](Self::len8_dlc).
+    pub fn raw_dlc(&self) -> usize {
+        self.len8_dlc().unwrap_or(self.0.can_dlc as usize)
+    }
 }
 
 impl AsPtr for CanDataFrame {
@@ -644,12 +1103,37 @@ impl Frame for CanDataFrame {
         match data.len() {
             n if n <= CAN_MAX_DLEN => {
                 self.0.can_dlc = n as u8;
+                self.0.len8_dlc = 0;
                 self.0.data[..n].copy_from_slice(data);
                 Ok(())
             }
             _ => Err(ConstructionError::TooMuchData),
         }
     }
+
+    /// Writes the raw bits of a signal into the frame data, in place, so
+    /// that the raw DLC is kept.
+    fn set_signal(
+        &mut self,
+        start_bit: usize,
+        size: usize,
+        order: ByteOrder,
+        raw: u64,
+    ) -> Result<(), ConstructionError> {
+        let n = self.len();
+        SignalLayout::new(start_bit, size, order).insert(&mut self.0.data[..n], raw)
+    }
+
+    /// Writes the physical value of a signal into the frame data, in
+    /// place, so that the raw DLC is kept.
+    fn set_signal_value(
+        &mut self,
+        layout: &SignalLayout,
+        value: f64,
+    ) -> Result<(), ConstructionError> {
+        let n = self.len();
+        layout.encode(&mut self.0.data[..n], value)
+    }
 }
 
 impl Default for CanDataFrame {
@@ -1080,28 +1564,34 @@ pub struct CanFdFrame(canfd_frame);
 
 impl CanFdFrame {
     /// Create a new FD frame with FD flags
+    ///
+    /// The data is padded with zeros out to the next valid FD frame size.
     pub fn with_flags(id: impl Into<Id>, data: &[u8], flags: FdFlags) -> Option<Self> {
         let can_id = id_to_canid_t(id);
         Self::init(can_id, data, flags).ok()
     }
 
     /// Initialize a FD frame from the raw components.
+    ///
+    /// The data is padded with zeros out to the next valid FD frame size.
     pub(crate) fn init(
         can_id: u32,
         data: &[u8],
         fd_flags: FdFlags,
     ) -> Result<Self, ConstructionError> {
-        match data.len() {
-            n if n <= CANFD_MAX_DLEN => {
-                let mut frame = canfd_frame_default();
-                frame.can_id = can_id;
-                frame.len = n as u8;
-                frame.flags = fd_flags.bits();
-                frame.data[..n].copy_from_slice(data);
-                Ok(Self(frame))
-            }
-            _ => Err(ConstructionError::TooMuchData),
-        }
+        let mut frame = Self(canfd_frame_default());
+        frame.0.can_id = can_id;
+        frame.0.flags = fd_flags.bits();
+        frame.set_data(data)?;
+        Ok(frame)
+    }
+
+    /// Gets the data length code (DLC) for the length of the frame.
+    ///
+    /// Unlike [`dlc()`](EmbeddedFrame::dlc), which is the number of data
+    /// bytes, this is the 4-bit code sent on the bus.
+    pub fn raw_dlc(&self) -> usize {
+        len_to_dlc(self.0.len as usize).unwrap_or(CAN_MAX_RAW_DLC)
     }
 
     /// Gets the flags for the FD frame.
@@ -1210,15 +1700,15 @@ impl Frame for CanFdFrame {
     }
 
     /// Sets the data payload of the frame.
+    ///
+    /// The data is padded with zeros out to the next valid FD frame size.
     fn set_data(&mut self, data: &[u8]) -> Result<(), ConstructionError> {
-        match data.len() {
-            n if n <= CANFD_MAX_DLEN => {
-                self.0.len = n as u8;
-                self.0.data[..n].copy_from_slice(data);
-                Ok(())
-            }
-            _ => Err(ConstructionError::TooMuchData),
-        }
+        let n = data.len();
+        let len = fd_padded_len(n).ok_or(ConstructionError::TooMuchData)?;
+        self.0.len = len as u8;
+        self.0.data[..n].copy_from_slice(data);
+        self.0.data[n..len].fill(0);
+        Ok(())
     }
 }
 
@@ -1270,97 +1760,1059 @@ impl AsRef<canfd_frame> for CanFdFrame {
     }
 }
 
-/////////////////////////////////////////////////////////////////////////////
+// ===== Frame views =====
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::errors;
+/// The offset of the data in the kernel frame structs, after the ID word,
+/// length, and flag bytes.
+const FRAME_DATA_OFFSET: usize = 8;
 
-    const STD_ID: Id = Id::Standard(StandardId::MAX);
-    const EXT_ID: Id = Id::Extended(ExtendedId::MAX);
+/// Reads the composite ID word from the start of a kernel frame struct.
+#[inline]
+fn bytes_id_word(buf: &[u8]) -> canid_t {
+    canid_t::from_ne_bytes([buf[0], buf[1], buf[2], buf[3]])
+}
 
-    const EXT_LOW_ID: Id = Id::Extended(unsafe { ExtendedId::new_unchecked(0x7FF) });
+/// A borrowed, read-only view of a classic CAN 2.0 frame in a byte buffer.
+///
+/// The bytes have the layout of a kernel `can_frame`, in native byte order,
+/// as read from a raw CAN socket. This allows frames to be used in place,
+/// like from a `recvmmsg` buffer, an mmap'd file, or a network packet,
+/// without copying each of them into an owned frame. The buffer does not
+/// need to be aligned.
+///
+/// ```
+/// use socketcan::{CanFrameRef, EmbeddedFrame, Frame};
+///
+/// // Two frames, back to back, as read from a socket
+/// let mut buf = [0u8; 2 * libc::CAN_MTU];
+/// buf[0..2].copy_from_slice(&0x123u16.to_ne_bytes());
+/// buf[4] = 2;
+/// buf[8..10].copy_from_slice(&[0xAA, 0xBB]);
+///
+/// let frames: Vec<_> = buf
+///     .chunks_exact(libc::CAN_MTU)
+///     .filter_map(CanFrameRef::from_bytes)
+///     .collect();
+///
+/// assert_eq!(0x123, frames[0].raw_id());
+/// assert_eq!(&[0xAA, 0xBB], frames[0].data());
+/// assert_eq!(0, frames[1].dlc());
+/// ```
+///
+/// Since the view is read-only, the constructors from the frame traits
+/// return `None`, [`set_id()`](Frame::set_id) has no effect, and
+/// [`set_data()`](Frame::set_data) returns an error. Use
+/// [`to_frame()`](Self::to_frame) to get an owned copy to modify.
+#[derive(Clone, Copy)]
+pub struct CanFrameRef<'a>(&'a [u8; CAN_MTU]);
 
-    const DATA: &[u8] = &[0, 1, 2, 3];
-    const DATA_LEN: usize = DATA.len();
+impl<'a> CanFrameRef<'a> {
+    /// Creates a view of a frame from a buffer of exactly `CAN_MTU` bytes.
+    ///
+    /// Returns `None` if the buffer is the wrong size, or the length of the
+    /// data in the frame is more than 8 bytes.
+    pub fn from_bytes(buf: &'a [u8]) -> Option<Self> {
+        let buf = <&[u8; CAN_MTU]>::try_from(buf).ok()?;
+        (buf[4] as usize <= CAN_MAX_DLEN).then_some(Self(buf))
+    }
 
-    const EMPTY_DATA: &[u8] = &[];
-    const ZERO_DATA: &[u8] = &[0u8; DATA_LEN];
+    /// Gets the underlying bytes of the frame.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
 
-    fn id_to_raw(id: Id) -> u32 {
-        match id {
-            Id::Standard(id) => id.as_raw() as u32,
-            Id::Extended(id) => id.as_raw(),
-        }
+    /// Copies the frame into an owned frame.
+    pub fn to_frame(&self) -> CanFrame {
+        // SAFETY: The buffer is the size of a `can_frame`, which is plain
+        // old data, and the read does not need to be aligned.
+        let frame: can_frame = unsafe { std::ptr::read_unaligned(self.0.as_ptr().cast()) };
+        frame.into()
     }
+}
 
-    #[test]
-    fn test_bit_flags() {
-        let mut flags = IdFlags::RTR;
-        assert_eq!(CAN_RTR_FLAG, flags.bits());
+impl EmbeddedFrame for CanFrameRef<'_> {
+    /// A view can not be created from parts, so this returns `None`.
+    fn new(_id: impl Into<Id>, _data: &[u8]) -> Option<Self> {
+        None
+    }
 
-        flags.set(IdFlags::EFF, true);
-        assert_eq!(CAN_RTR_FLAG, flags.bits() & CAN_RTR_FLAG);
-        assert_eq!(CAN_EFF_FLAG, flags.bits() & CAN_EFF_FLAG);
+    /// A view can not be created from parts, so this returns `None`.
+    fn new_remote(_id: impl Into<Id>, _dlc: usize) -> Option<Self> {
+        None
+    }
 
-        flags.set(IdFlags::EFF, false);
-        assert_eq!(CAN_RTR_FLAG, flags.bits() & CAN_RTR_FLAG);
-        assert_eq!(0, flags.bits() & CAN_EFF_FLAG);
+    /// Check if frame uses 29-bit extended ID format.
+    fn is_extended(&self) -> bool {
+        self.id_flags().contains(IdFlags::EFF)
     }
 
-    #[test]
-    fn test_defaults() {
-        let frame = CanFrame::default();
+    /// Check if frame is a remote transmission request.
+    fn is_remote_frame(&self) -> bool {
+        self.id_flags().contains(IdFlags::RTR)
+    }
 
-        assert_eq!(0, frame.id_word());
-        assert_eq!(0, frame.raw_id());
-        assert!(frame.id_flags().is_empty());
+    /// Return the frame identifier.
+    fn id(&self) -> Id {
+        self.hal_id()
+    }
 
-        assert_eq!(0, frame.dlc());
-        assert_eq!(0, frame.len());
-        assert_eq!(EMPTY_DATA, frame.data());
+    /// Data length
+    fn dlc(&self) -> usize {
+        self.0[4] as usize
     }
 
-    #[test]
-    fn test_data_frame() {
-        let frame = CanDataFrame::new(STD_ID, DATA).unwrap();
-        assert_eq!(STD_ID, frame.id());
-        assert_eq!(id_to_raw(STD_ID), frame.raw_id());
-        assert!(frame.is_standard());
-        assert!(!frame.is_extended());
-        assert!(frame.is_data_frame());
-        assert!(!frame.is_remote_frame());
-        assert!(!frame.is_error_frame());
-        assert_eq!(DATA, frame.data());
+    /// A slice into the actual data. Slice will always be <= 8 bytes in length
+    ///
+    /// For a remote frame, this is empty.
+    fn data(&self) -> &[u8] {
+        if self.is_remote_frame() {
+            &[]
+        } else {
+            &self.0[FRAME_DATA_OFFSET..FRAME_DATA_OFFSET + self.dlc()]
+        }
+    }
+}
 
-        let frame = CanFrame::from(frame);
-        assert_eq!(STD_ID, frame.id());
-        assert_eq!(id_to_raw(STD_ID), frame.raw_id());
-        assert!(frame.is_standard());
-        assert!(!frame.is_extended());
-        assert!(frame.is_data_frame());
-        assert!(!frame.is_remote_frame());
-        assert!(!frame.is_error_frame());
-        assert_eq!(DATA, frame.data());
+impl Frame for CanFrameRef<'_> {
+    /// Get the composite SocketCAN ID word, with EFF/RTR/ERR flags
+    fn id_word(&self) -> canid_t {
+        bytes_id_word(self.0)
+    }
 
-        let frame = CanDataFrame::from_raw_id(StandardId::MAX.as_raw() as u32, DATA).unwrap();
-        assert_eq!(STD_ID, frame.id());
-        assert_eq!(id_to_raw(STD_ID), frame.raw_id());
-        assert!(frame.is_standard());
-        assert!(!frame.is_extended());
-        assert!(frame.is_data_frame());
-        assert!(!frame.is_remote_frame());
-        assert!(!frame.is_error_frame());
-        assert_eq!(DATA, frame.data());
+    /// The view is read-only, so this has no effect.
+    fn set_id(&mut self, _id: impl Into<Id>) {}
 
-        let frame = CanFrame::new(EXT_ID, DATA).unwrap();
-        assert_eq!(EXT_ID, frame.id());
-        assert_eq!(id_to_raw(EXT_ID), frame.raw_id());
-        assert!(!frame.is_standard());
-        assert!(frame.is_extended());
-        assert!(frame.is_data_frame());
-        assert!(!frame.is_remote_frame());
+    /// The view is read-only, so this always fails.
+    fn set_data(&mut self, _data: &[u8]) -> Result<(), ConstructionError> {
+        Err(ConstructionError::WrongFrameType)
+    }
+}
+
+impl fmt::Debug for CanFrameRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CanFrameRef {{ ")?;
+        fmt::UpperHex::fmt(&self.to_frame(), f)?;
+        write!(f, " }}")
+    }
+}
+
+impl From<CanFrameRef<'_>> for CanFrame {
+    fn from(frame: CanFrameRef) -> Self {
+        frame.to_frame()
+    }
+}
+
+/// A borrowed, read-only view of a CAN FD frame in a byte buffer.
+///
+/// The bytes have the layout of a kernel `canfd_frame`, in native byte
+/// order, as read from an FD socket. See [`CanFrameRef`] for the details.
+#[derive(Clone, Copy)]
+pub struct CanFdFrameRef<'a>(&'a [u8; CANFD_MTU]);
+
+impl<'a> CanFdFrameRef<'a> {
+    /// Creates a view of a frame from a buffer of exactly `CANFD_MTU` bytes.
+    ///
+    /// Returns `None` if the buffer is the wrong size, or the length of the
+    /// data in the frame is more than 64 bytes.
+    pub fn from_bytes(buf: &'a [u8]) -> Option<Self> {
+        let buf = <&[u8; CANFD_MTU]>::try_from(buf).ok()?;
+        (buf[4] as usize <= CANFD_MAX_DLEN).then_some(Self(buf))
+    }
+
+    /// Gets the underlying bytes of the frame.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// Gets the flags for the FD frame.
+    pub fn flags(&self) -> FdFlags {
+        FdFlags::from_bits_truncate(self.0[5])
+    }
+
+    /// Whether the frame uses a bit rate switch (second bit rate for
+    /// payload data).
+    pub fn is_brs(&self) -> bool {
+        self.flags().contains(FdFlags::BRS)
+    }
+
+    /// Gets the error state indicator of the transmitting node
+    pub fn is_esi(&self) -> bool {
+        self.flags().contains(FdFlags::ESI)
+    }
+
+    /// Copies the frame into an owned frame.
+    pub fn to_frame(&self) -> CanFdFrame {
+        // SAFETY: The buffer is the size of a `canfd_frame`, which is plain
+        // old data, and the read does not need to be aligned.
+        let frame: canfd_frame = unsafe { std::ptr::read_unaligned(self.0.as_ptr().cast()) };
+        frame.into()
+    }
+}
+
+impl EmbeddedFrame for CanFdFrameRef<'_> {
+    /// A view can not be created from parts, so this returns `None`.
+    fn new(_id: impl Into<Id>, _data: &[u8]) -> Option<Self> {
+        None
+    }
+
+    /// A view can not be created from parts, so this returns `None`.
+    fn new_remote(_id: impl Into<Id>, _dlc: usize) -> Option<Self> {
+        None
+    }
+
+    /// Check if frame uses 29-bit extended ID format.
+    fn is_extended(&self) -> bool {
+        self.id_flags().contains(IdFlags::EFF)
+    }
+
+    /// The FD frames don't support remote request
+    fn is_remote_frame(&self) -> bool {
+        false
+    }
+
+    /// Return the frame identifier.
+    fn id(&self) -> Id {
+        self.hal_id()
+    }
+
+    /// Data length code
+    fn dlc(&self) -> usize {
+        self.0[4] as usize
+    }
+
+    /// A slice into the actual data.
+    fn data(&self) -> &[u8] {
+        &self.0[FRAME_DATA_OFFSET..FRAME_DATA_OFFSET + self.dlc()]
+    }
+}
+
+impl Frame for CanFdFrameRef<'_> {
+    /// Get the composite SocketCAN ID word, with EFF/RTR/ERR flags
+    fn id_word(&self) -> canid_t {
+        bytes_id_word(self.0)
+    }
+
+    /// The view is read-only, so this has no effect.
+    fn set_id(&mut self, _id: impl Into<Id>) {}
+
+    /// The view is read-only, so this always fails.
+    fn set_data(&mut self, _data: &[u8]) -> Result<(), ConstructionError> {
+        Err(ConstructionError::WrongFrameType)
+    }
+}
+
+impl fmt::Debug for CanFdFrameRef<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CanFdFrameRef {{ ")?;
+        fmt::UpperHex::fmt(&self.to_frame(), f)?;
+        write!(f, " }}")
+    }
+}
+
+impl From<CanFdFrameRef<'_>> for CanFdFrame {
+    fn from(frame: CanFdFrameRef) -> Self {
+        frame.to_frame()
+    }
+}
+
+// ===== FrameBuilder =====
+
+/// A builder for any type of frame, with the type of ID and the flags set
+/// explicitly.
+///
+/// Unlike [`id_from_raw()`] and [`Frame::from_raw_id()`], the builder
+/// never guesses whether an ID is standard or extended from its value, so
+/// an extended ID <= 0x7FF stays extended. It also reports why a frame
+/// can not be built, rather than returning `None`.
+///
+/// The builder makes a data frame by default, or a remote frame with
+/// [`rtr()`](Self::rtr), or an FD frame with [`fd()`](Self::fd) or either
+/// of the FD flags:
+///
+/// ```
+/// use socketcan::{CanAnyFrame, EmbeddedFrame, ExtendedId, FrameBuilder, Id};
+///
+/// let frame = FrameBuilder::new()
+///     .extended_id(0x123)
+///     .data(&[1, 2, 3])
+///     .brs(true)
+///     .build()
+///     .unwrap();
+///
+/// let CanAnyFrame::Fd(frame) = frame else { panic!() };
+/// assert_eq!(Id::Extended(ExtendedId::new(0x123).unwrap()), frame.id());
+/// assert!(frame.is_brs());
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct FrameBuilder {
+    /// The raw ID, without any flags
+    id: canid_t,
+    /// Whether the ID is extended
+    extended: bool,
+    /// Whether to make a remote frame
+    rtr: bool,
+    /// Whether to make an FD frame
+    fd: bool,
+    /// The FD flags
+    fd_flags: FdFlags,
+    /// An explicit data length code
+    dlc: Option<usize>,
+    /// The data, which is only valid up to `len`
+    data: [u8; CANFD_MAX_DLEN],
+    /// The length of the data, which may be more than fits in the buffer
+    len: usize,
+}
+
+impl Default for FrameBuilder {
+    /// The default builder makes a data frame with a standard ID of zero
+    /// and no data.
+    fn default() -> Self {
+        Self {
+            id: 0,
+            extended: false,
+            rtr: false,
+            fd: false,
+            fd_flags: FdFlags::empty(),
+            dlc: None,
+            data: [0; CANFD_MAX_DLEN],
+            len: 0,
+        }
+    }
+}
+
+impl FrameBuilder {
+    /// Creates a new builder for a data frame with a standard ID of zero
+    /// and no data.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the ID, which is standard or extended by its type.
+    pub fn id(self, id: impl Into<Id>) -> Self {
+        match id.into() {
+            Id::Standard(id) => self.standard_id(id.as_raw()),
+            Id::Extended(id) => self.extended_id(id.as_raw()),
+        }
+    }
+
+    /// Sets a standard, 11-bit, ID.
+    ///
+    /// An ID larger than 0x7FF is an error when the frame is built.
+    pub fn standard_id(mut self, id: u16) -> Self {
+        self.id = id as canid_t;
+        self.extended = false;
+        self
+    }
+
+    /// Sets an extended, 29-bit, ID, even if the value is <= 0x7FF.
+    ///
+    /// An ID larger than 0x1FFFFFFF is an error when the frame is built.
+    pub fn extended_id(mut self, id: u32) -> Self {
+        self.id = id;
+        self.extended = true;
+        self
+    }
+
+    /// Sets whether to make a remote transmission request (RTR) frame.
+    ///
+    /// A remote frame can not have data or be an FD frame.
+    pub fn rtr(mut self, on: bool) -> Self {
+        self.rtr = on;
+        self
+    }
+
+    /// Sets the data length code (DLC).
+    ///
+    /// This is normally taken from the data, but:
+    /// - A remote frame requests DLC bytes, from 0 to 8.
+    /// - A data frame with 8 bytes can have a raw DLC of 9-15. See
+    ///   [`CanDataFrame::len8_dlc()`].
+    /// - An FD frame is padded with zeros out to the length for the DLC.
+    pub fn dlc(mut self, dlc: usize) -> Self {
+        self.dlc = Some(dlc);
+        self
+    }
+
+    /// Sets the data payload.
+    ///
+    /// Up to 8 bytes fit in a classic frame, and 64 in an FD frame.
+    pub fn data(mut self, data: &[u8]) -> Self {
+        let n = data.len().min(CANFD_MAX_DLEN);
+        self.data[..n].copy_from_slice(&data[..n]);
+        self.len = data.len();
+        self
+    }
+
+    /// Sets whether to make an FD frame.
+    pub fn fd(mut self, on: bool) -> Self {
+        self.fd = on;
+        self
+    }
+
+    /// Sets the bit rate switch (BRS) flag, which also makes an FD frame.
+    pub fn brs(mut self, on: bool) -> Self {
+        self.fd_flags.set(FdFlags::BRS, on);
+        self.fd |= on;
+        self
+    }
+
+    /// Sets the error state indicator (ESI) flag, which also makes an FD
+    /// frame.
+    pub fn esi(mut self, on: bool) -> Self {
+        self.fd_flags.set(FdFlags::ESI, on);
+        self.fd |= on;
+        self
+    }
+
+    /// Gets the ID, checked against the range for its type.
+    fn hal_id(&self) -> Result<Id, ConstructionError> {
+        let id = if self.extended {
+            ExtendedId::new(self.id).map(Id::Extended)
+        } else {
+            u16::try_from(self.id)
+                .ok()
+                .and_then(StandardId::new)
+                .map(Id::Standard)
+        };
+        id.ok_or(ConstructionError::IDTooLarge)
+    }
+
+    /// Builds the frame, as the variant for its type.
+    ///
+    /// This fails with `WrongFrameType` for a remote frame with data or FD
+    /// flags, `TooMuchData` if the data does not fit the frame, and
+    /// `InvalidDlc` if an explicit DLC does not match the data.
+    pub fn build(&self) -> Result<CanAnyFrame, ConstructionError> {
+        let id = self.hal_id()?;
+        let data = self
+            .data
+            .get(..self.len)
+            .ok_or(ConstructionError::TooMuchData)?;
+
+        if self.rtr {
+            if self.fd || !data.is_empty() {
+                return Err(ConstructionError::WrongFrameType);
+            }
+            CanRemoteFrame::new_remote(id, self.dlc.unwrap_or(0))
+                .map(CanAnyFrame::Remote)
+                .ok_or(ConstructionError::InvalidDlc)
+        } else if self.fd {
+            let len = match self.dlc {
+                Some(dlc) => dlc_to_len(dlc)
+                    .filter(|&len| len >= data.len())
+                    .ok_or(ConstructionError::InvalidDlc)?,
+                None => data.len(),
+            };
+            let mut frame = CanFdFrame::init(id_to_canid_t(id), data, self.fd_flags)?;
+            if len > frame.len() {
+                // pad out to the length for the DLC
+                let mut buf = [0u8; CANFD_MAX_DLEN];
+                buf[..data.len()].copy_from_slice(data);
+                frame.set_data(&buf[..len])?;
+            }
+            Ok(CanAnyFrame::Fd(frame))
+        } else {
+            let mut frame = CanDataFrame::init(id_to_canid_t(id), data)?;
+            match self.dlc {
+                None => (),
+                Some(dlc) if dlc == data.len() => (),
+                Some(dlc) => frame.set_len8_dlc(Some(dlc))?,
+            }
+            Ok(CanAnyFrame::Normal(frame))
+        }
+    }
+
+    /// Builds a classic CAN 2.0 data or remote frame.
+    ///
+    /// This fails with `WrongFrameType` if the builder would make an FD
+    /// frame.
+    pub fn build_classic(&self) -> Result<CanFrame, ConstructionError> {
+        match self.build()? {
+            CanAnyFrame::Normal(frame) => Ok(frame.into()),
+            CanAnyFrame::Remote(frame) => Ok(frame.into()),
+            _ => Err(ConstructionError::WrongFrameType),
+        }
+    }
+
+    /// Builds an FD frame, even if the FD flags are not set.
+    pub fn build_fd(&self) -> Result<CanFdFrame, ConstructionError> {
+        match self.fd(true).build()? {
+            CanAnyFrame::Fd(frame) => Ok(frame),
+            _ => Err(ConstructionError::WrongFrameType),
+        }
+    }
+}
+
+// ===== cansend text format =====
+//
+// The parsing works directly on bytes, without allocating, since it's
+// also in the path of every record read from a candump log.
+
+/// Lookup table of the values of ASCII hex digits, with 0xFF for
+/// anything else.
+const HEX_DIGITS: [u8; 256] = {
+    let mut tbl = [0xFF; 256];
+    let mut i = 0;
+    while i < 10 {
+        tbl[b'0' as usize + i] = i as u8;
+        i += 1;
+    }
+    let mut i = 0;
+    while i < 6 {
+        tbl[b'a' as usize + i] = 10 + i as u8;
+        tbl[b'A' as usize + i] = 10 + i as u8;
+        i += 1;
+    }
+    tbl
+};
+
+/// Gets the value of an ASCII hex digit.
+#[inline]
+pub(crate) fn hex_digit(c: u8) -> Option<u8> {
+    match HEX_DIGITS[c as usize] {
+        0xFF => None,
+        d => Some(d),
+    }
+}
+
+/// Parses an unsigned hex number.
+///
+/// Fails if the field is empty, has anything other than hex digits, or
+/// overflows.
+#[inline]
+pub(crate) fn parse_hex(bytes: &[u8]) -> Option<u64> {
+    if bytes.is_empty() || bytes.len() > 16 {
+        return None;
+    }
+    let mut n = 0u64;
+    for &c in bytes {
+        n = (n << 4) | hex_digit(c)? as u64;
+    }
+    Some(n)
+}
+
+/// Decodes hex data bytes into the buffer, returning the number of
+/// bytes decoded.
+///
+/// As with `cansend`, each byte may be preceded by a single '.' to make
+/// the data easier to read, like "DE.AD.BE.EF".
+#[inline]
+fn decode_data(text: &[u8], buf: &mut [u8]) -> Result<usize, ParseFrameError> {
+    use ParseFrameError::*;
+
+    let mut text = text;
+    let mut n = 0;
+    loop {
+        if let Some((&b'.', rest)) = text.split_first() {
+            text = rest;
+        }
+        match *text {
+            [] => return Ok(n),
+            [hi, lo, ref rest @ ..] => {
+                let b = buf
+                    .get_mut(n)
+                    .ok_or(Construction(ConstructionError::TooMuchData))?;
+                *b = (hex_digit(hi).ok_or(InvalidFormat)? << 4)
+                    | hex_digit(lo).ok_or(InvalidFormat)?;
+                n += 1;
+                text = rest;
+            }
+            _ => return Err(InvalidFormat),
+        }
+    }
+}
+
+/// Parses a frame in the `cansend` format from the bytes of the text.
+///
+/// The format is `<id>#<data>[_<dlc>]` for a data frame, `<id>#R[<dlc>]`
+/// for a remote frame, and `<id>##<flags><data>` for an FD frame. An ID with
+/// more than 3 digits, or too large for a standard ID, is extended. An
+/// ID with the error flag set makes an error frame.
+pub(crate) fn parse_frame(text: &[u8]) -> Result<CanAnyFrame, ParseFrameError> {
+    use ParseFrameError::*;
+
+    let sep = text.iter().position(|&c| c == b'#').ok_or(InvalidFormat)?;
+    let (id_text, body) = (&text[..sep], &text[sep + 1..]);
+
+    let can_id = parse_hex(id_text)
+        .and_then(|id| canid_t::try_from(id).ok())
+        .ok_or(InvalidFormat)?;
+
+    // extended IDs are written with 8 hex digits, standard with 3
+    let id = || {
+        if id_text.len() > 3 || can_id > CAN_SFF_MASK {
+            ExtendedId::new(can_id).map(Id::Extended)
+        } else {
+            StandardId::new(can_id as u16).map(Id::Standard)
+        }
+        .ok_or(Construction(ConstructionError::IDTooLarge))
+    };
+
+    let mut buf = [0u8; CANFD_MAX_DLEN];
+
+    let frame = match *body {
+        [b'#', flags, ref data @ ..] => {
+            let flags = FdFlags::from_bits_truncate(hex_digit(flags).ok_or(InvalidFormat)?);
+            let n = decode_data(data, &mut buf)?;
+            CanFdFrame::init(id_to_canid_t(id()?), &buf[..n], flags)?.into()
+        }
+        [b'R' | b'r', ref dlc @ ..] => {
+            let dlc = match *dlc {
+                [] => 0,
+                [c] => hex_digit(c).ok_or(InvalidFormat)? as usize,
+                _ => return Err(InvalidFormat),
+            };
+            CanRemoteFrame::new_remote(id()?, dlc)
+                .map(CanAnyFrame::Remote)
+                .ok_or(Construction(ConstructionError::TooMuchData))?
+        }
+        ref data => {
+            // a data frame with 8 bytes can have a raw DLC, like "_F"
+            let (data, len8_dlc) = match *data {
+                [ref data @ .., b'_', dlc] => {
+                    (data, Some(hex_digit(dlc).ok_or(InvalidFormat)? as usize))
+                }
+                _ => (data, None),
+            };
+            let n = decode_data(data, &mut buf)?;
+            if can_id & CAN_ERR_FLAG != 0 {
+                if len8_dlc.is_some() {
+                    return Err(Construction(ConstructionError::InvalidDlc));
+                }
+                CanAnyFrame::Error(CanErrorFrame::new_error(can_id, &buf[..n])?)
+            } else {
+                let mut frame = CanDataFrame::init(id_to_canid_t(id()?), &buf[..n])?;
+                frame.set_len8_dlc(len8_dlc)?;
+                CanAnyFrame::Normal(frame)
+            }
+        }
+    };
+    Ok(frame)
+}
+
+/// Writes the ID of a frame, with 8 hex digits for an extended ID,
+/// and 3 for a standard one.
+fn fmt_id(f: &mut fmt::Formatter, id: Id) -> fmt::Result {
+    match id {
+        Id::Standard(id) => write!(f, "{:03X}", id.as_raw()),
+        Id::Extended(id) => write!(f, "{:08X}", id.as_raw()),
+    }
+}
+
+/// Writes the data bytes of a frame in hex, without separators.
+fn fmt_data(f: &mut fmt::Formatter, data: &[u8]) -> fmt::Result {
+    for b in data {
+        write!(f, "{:02X}", b)?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for CanDataFrame {
+    /// Formats the frame like `cansend`, such as "123#DEADBEEF", with
+    /// any raw DLC above 8 as a suffix, like "123#0011223344556677_F".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_id(f, self.id())?;
+        write!(f, "#")?;
+        fmt_data(f, self.data())?;
+        if let Some(dlc) = self.len8_dlc() {
+            write!(f, "_{:X}", dlc)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CanRemoteFrame {
+    /// Formats the frame like `cansend`, such as "123#R" or "123#R4".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_id(f, self.id())?;
+        write!(f, "#R")?;
+        if self.dlc() > 0 {
+            write!(f, "{:X}", self.dlc())?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CanErrorFrame {
+    /// Formats the frame like `cansend`, with the error flag in the ID,
+    /// such as "20000004#0004000000000000".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:08X}#", self.id_word() & (CAN_ERR_MASK | CAN_ERR_FLAG))?;
+        fmt_data(f, self.data())
+    }
+}
+
+impl fmt::Display for CanFdFrame {
+    /// Formats the frame like `cansend`, such as "123##1DEADBEEF".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt_id(f, self.id())?;
+        write!(f, "##{:X}", self.flags().bits())?;
+        fmt_data(f, self.data())
+    }
+}
+
+impl fmt::Display for CanFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Data(frame) => frame.fmt(f),
+            Self::Remote(frame) => frame.fmt(f),
+            Self::Error(frame) => frame.fmt(f),
+        }
+    }
+}
+
+impl fmt::Display for CanAnyFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Normal(frame) => frame.fmt(f),
+            Self::Remote(frame) => frame.fmt(f),
+            Self::Error(frame) => frame.fmt(f),
+            Self::Fd(frame) => frame.fmt(f),
+        }
+    }
+}
+
+impl FromStr for CanAnyFrame {
+    type Err = ParseFrameError;
+
+    /// Parses any type of frame in the `cansend` format.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_frame(s.as_bytes())
+    }
+}
+
+impl FromStr for CanFrame {
+    type Err = ParseFrameError;
+
+    /// Parses a classic data, remote, or error frame in the `cansend`
+    /// format. FD frames are rejected.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_frame(s.as_bytes())? {
+            CanAnyFrame::Normal(frame) => Ok(frame.into()),
+            CanAnyFrame::Remote(frame) => Ok(frame.into()),
+            CanAnyFrame::Error(frame) => Ok(frame.into()),
+            CanAnyFrame::Fd(_) => Err(ConstructionError::WrongFrameType.into()),
+        }
+    }
+}
+
+impl FromStr for CanFdFrame {
+    type Err = ParseFrameError;
+
+    /// Parses an FD frame in the `cansend` format, with the `##<flags>`
+    /// separator, which is how the frame is displayed. Classic frames are
+    /// rejected.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_frame(s.as_bytes())? {
+            CanAnyFrame::Fd(frame) => Ok(frame),
+            _ => Err(ConstructionError::WrongFrameType.into()),
+        }
+    }
+}
+
+// ===== serde =====
+
+/// Serialization of the frames through an intermediate representation,
+/// as described in the module docs.
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::*;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+    use std::borrow::Cow;
+
+    /// Creates an ID from the serialized fields
+    fn make_id<E: de::Error>(id: u32, extended: bool) -> Result<Id, E> {
+        let id = if extended {
+            ExtendedId::new(id).map(Id::from)
+        } else {
+            u16::try_from(id)
+                .ok()
+                .and_then(StandardId::new)
+                .map(Id::from)
+        };
+        id.ok_or_else(|| E::custom(ConstructionError::IDTooLarge))
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct DataFrameRepr<'a> {
+        id: u32,
+        extended: bool,
+        data: Cow<'a, [u8]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        len8_dlc: Option<usize>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct RemoteFrameRepr {
+        id: u32,
+        extended: bool,
+        dlc: usize,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct ErrorFrameRepr<'a> {
+        id: u32,
+        data: Cow<'a, [u8]>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct FdFrameRepr<'a> {
+        id: u32,
+        extended: bool,
+        brs: bool,
+        esi: bool,
+        data: Cow<'a, [u8]>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(tag = "type", rename_all = "lowercase")]
+    enum FrameRepr<'a> {
+        Data(DataFrameRepr<'a>),
+        Remote(RemoteFrameRepr),
+        Error(ErrorFrameRepr<'a>),
+        Fd(FdFrameRepr<'a>),
+    }
+
+    impl<'a> From<&'a CanDataFrame> for DataFrameRepr<'a> {
+        fn from(frame: &'a CanDataFrame) -> Self {
+            Self {
+                id: frame.raw_id(),
+                extended: frame.is_extended(),
+                data: Cow::Borrowed(frame.data()),
+                len8_dlc: frame.len8_dlc(),
+            }
+        }
+    }
+
+    impl DataFrameRepr<'_> {
+        fn into_frame<E: de::Error>(self) -> Result<CanDataFrame, E> {
+            let id = make_id(self.id, self.extended)?;
+            let mut frame = CanDataFrame::new(id, &self.data)
+                .ok_or_else(|| E::custom(ConstructionError::TooMuchData))?;
+            frame.set_len8_dlc(self.len8_dlc).map_err(E::custom)?;
+            Ok(frame)
+        }
+    }
+
+    impl From<&CanRemoteFrame> for RemoteFrameRepr {
+        fn from(frame: &CanRemoteFrame) -> Self {
+            Self {
+                id: frame.raw_id(),
+                extended: frame.is_extended(),
+                dlc: frame.dlc(),
+            }
+        }
+    }
+
+    impl RemoteFrameRepr {
+        fn into_frame<E: de::Error>(self) -> Result<CanRemoteFrame, E> {
+            let id = make_id(self.id, self.extended)?;
+            CanRemoteFrame::new_remote(id, self.dlc)
+                .ok_or_else(|| E::custom(ConstructionError::TooMuchData))
+        }
+    }
+
+    impl<'a> From<&'a CanErrorFrame> for ErrorFrameRepr<'a> {
+        fn from(frame: &'a CanErrorFrame) -> Self {
+            Self {
+                id: frame.error_bits(),
+                data: Cow::Borrowed(frame.data()),
+            }
+        }
+    }
+
+    impl ErrorFrameRepr<'_> {
+        fn into_frame<E: de::Error>(self) -> Result<CanErrorFrame, E> {
+            if self.id & !CAN_ERR_MASK != 0 {
+                return Err(E::custom(ConstructionError::IDTooLarge));
+            }
+            CanErrorFrame::new_error(self.id, &self.data).map_err(E::custom)
+        }
+    }
+
+    impl<'a> From<&'a CanFdFrame> for FdFrameRepr<'a> {
+        fn from(frame: &'a CanFdFrame) -> Self {
+            Self {
+                id: frame.raw_id(),
+                extended: frame.is_extended(),
+                brs: frame.is_brs(),
+                esi: frame.is_esi(),
+                data: Cow::Borrowed(frame.data()),
+            }
+        }
+    }
+
+    impl FdFrameRepr<'_> {
+        fn into_frame<E: de::Error>(self) -> Result<CanFdFrame, E> {
+            let id = make_id(self.id, self.extended)?;
+            let mut flags = FdFlags::empty();
+            flags.set(FdFlags::BRS, self.brs);
+            flags.set(FdFlags::ESI, self.esi);
+            CanFdFrame::with_flags(id, &self.data, flags)
+                .ok_or_else(|| E::custom(ConstructionError::TooMuchData))
+        }
+    }
+
+    /// Implements Serialize and Deserialize for a frame type through
+    /// its representation.
+    macro_rules! serde_frame {
+        ($frame:ty, $repr:ident) => {
+            impl Serialize for $frame {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    $repr::from(self).serialize(serializer)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $frame {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    $repr::deserialize(deserializer)?.into_frame()
+                }
+            }
+        };
+    }
+
+    serde_frame!(CanDataFrame, DataFrameRepr);
+    serde_frame!(CanRemoteFrame, RemoteFrameRepr);
+    serde_frame!(CanErrorFrame, ErrorFrameRepr);
+    serde_frame!(CanFdFrame, FdFrameRepr);
+
+    impl Serialize for CanFrame {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self {
+                CanFrame::Data(frame) => FrameRepr::Data(frame.into()),
+                CanFrame::Remote(frame) => FrameRepr::Remote(frame.into()),
+                CanFrame::Error(frame) => FrameRepr::Error(frame.into()),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for CanFrame {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            match FrameRepr::deserialize(deserializer)? {
+                FrameRepr::Data(repr) => repr.into_frame().map(CanFrame::Data),
+                FrameRepr::Remote(repr) => repr.into_frame().map(CanFrame::Remote),
+                FrameRepr::Error(repr) => repr.into_frame().map(CanFrame::Error),
+                FrameRepr::Fd(_) => Err(de::Error::custom(ConstructionError::WrongFrameType)),
+            }
+        }
+    }
+
+    impl Serialize for CanAnyFrame {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self {
+                CanAnyFrame::Normal(frame) => FrameRepr::Data(frame.into()),
+                CanAnyFrame::Remote(frame) => FrameRepr::Remote(frame.into()),
+                CanAnyFrame::Error(frame) => FrameRepr::Error(frame.into()),
+                CanAnyFrame::Fd(frame) => FrameRepr::Fd(frame.into()),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for CanAnyFrame {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            match FrameRepr::deserialize(deserializer)? {
+                FrameRepr::Data(repr) => repr.into_frame().map(CanAnyFrame::Normal),
+                FrameRepr::Remote(repr) => repr.into_frame().map(CanAnyFrame::Remote),
+                FrameRepr::Error(repr) => repr.into_frame().map(CanAnyFrame::Error),
+                FrameRepr::Fd(repr) => repr.into_frame().map(CanAnyFrame::Fd),
+            }
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors;
+
+    const STD_ID: Id = Id::Standard(StandardId::MAX);
+    const EXT_ID: Id = Id::Extended(ExtendedId::MAX);
+
+    const EXT_LOW_ID: Id = Id::Extended(unsafe { ExtendedId::new_unchecked(0x7FF) });
+
+    const DATA: &[u8] = &[0, 1, 2, 3];
+    const DATA_LEN: usize = DATA.len();
+
+    const EMPTY_DATA: &[u8] = &[];
+    const ZERO_DATA: &[u8] = &[0u8; DATA_LEN];
+
+    fn id_to_raw(id: Id) -> u32 {
+        match id {
+            Id::Standard(id) => id.as_raw() as u32,
+            Id::Extended(id) => id.as_raw(),
+        }
+    }
+
+    #[test]
+    fn test_bit_flags() {
+        let mut flags = IdFlags::RTR;
+        assert_eq!(CAN_RTR_FLAG, flags.bits());
+
+        flags.set(IdFlags::EFF, true);
+        assert_eq!(CAN_RTR_FLAG, flags.bits() & CAN_RTR_FLAG);
+        assert_eq!(CAN_EFF_FLAG, flags.bits() & CAN_EFF_FLAG);
+
+        flags.set(IdFlags::EFF, false);
+        assert_eq!(CAN_RTR_FLAG, flags.bits() & CAN_RTR_FLAG);
+        assert_eq!(0, flags.bits() & CAN_EFF_FLAG);
+    }
+
+    #[test]
+    fn test_defaults() {
+        let frame = CanFrame::default();
+
+        assert_eq!(0, frame.id_word());
+        assert_eq!(0, frame.raw_id());
+        assert!(frame.id_flags().is_empty());
+
+        assert_eq!(0, frame.dlc());
+        assert_eq!(0, frame.len());
+        assert_eq!(EMPTY_DATA, frame.data());
+    }
+
+    #[test]
+    fn test_data_frame() {
+        let frame = CanDataFrame::new(STD_ID, DATA).unwrap();
+        assert_eq!(STD_ID, frame.id());
+        assert_eq!(id_to_raw(STD_ID), frame.raw_id());
+        assert!(frame.is_standard());
+        assert!(!frame.is_extended());
+        assert!(frame.is_data_frame());
+        assert!(!frame.is_remote_frame());
+        assert!(!frame.is_error_frame());
+        assert_eq!(DATA, frame.data());
+
+        let frame = CanFrame::from(frame);
+        assert_eq!(STD_ID, frame.id());
+        assert_eq!(id_to_raw(STD_ID), frame.raw_id());
+        assert!(frame.is_standard());
+        assert!(!frame.is_extended());
+        assert!(frame.is_data_frame());
+        assert!(!frame.is_remote_frame());
+        assert!(!frame.is_error_frame());
+        assert_eq!(DATA, frame.data());
+
+        let frame = CanDataFrame::from_raw_id(StandardId::MAX.as_raw() as u32, DATA).unwrap();
+        assert_eq!(STD_ID, frame.id());
+        assert_eq!(id_to_raw(STD_ID), frame.raw_id());
+        assert!(frame.is_standard());
+        assert!(!frame.is_extended());
+        assert!(frame.is_data_frame());
+        assert!(!frame.is_remote_frame());
+        assert!(!frame.is_error_frame());
+        assert_eq!(DATA, frame.data());
+
+        let frame = CanFrame::new(EXT_ID, DATA).unwrap();
+        assert_eq!(EXT_ID, frame.id());
+        assert_eq!(id_to_raw(EXT_ID), frame.raw_id());
+        assert!(!frame.is_standard());
+        assert!(frame.is_extended());
+        assert!(frame.is_data_frame());
+        assert!(!frame.is_remote_frame());
         assert!(!frame.is_error_frame());
         assert_eq!(DATA, frame.data());
 
@@ -1494,7 +2946,7 @@ mod tests {
                 assert_eq!(location, errors::Location::Id0400);
             }
             _ => {
-                assert!(false);
+                panic!("Expected protocol violation");
             }
         }
     }
@@ -1540,4 +2992,574 @@ mod tests {
         assert!(!frame.is_error_frame());
         assert_eq!(DATA, frame.data());
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        use serde_json::{from_str, json, to_value};
+
+        let frame = CanDataFrame::new(StandardId::new(0x123).unwrap(), &[1, 2, 3]).unwrap();
+        let val = json!({"type": "data", "id": 0x123, "extended": false, "data": [1, 2, 3]});
+        assert_eq!(val, to_value(CanFrame::Data(frame)).unwrap());
+        assert_eq!(val, to_value(CanAnyFrame::Normal(frame)).unwrap());
+
+        let frame: CanFrame = from_str(&val.to_string()).unwrap();
+        assert!(matches!(frame, CanFrame::Data(_)));
+        assert_eq!(0x123, frame.raw_id());
+        assert_eq!(&[1, 2, 3], frame.data());
+
+        let mut frame = CanDataFrame::new(STD_ID, &[0; 8]).unwrap();
+        frame.set_len8_dlc(Some(0xA)).unwrap();
+        let val = to_value(frame).unwrap();
+        assert_eq!(Some(&json!(0xA)), val.get("len8_dlc"));
+        let frame: CanDataFrame = from_str(&val.to_string()).unwrap();
+        assert_eq!(Some(0xA), frame.len8_dlc());
+
+        let frame = CanRemoteFrame::new_remote(EXT_LOW_ID, 4).unwrap();
+        let val = to_value(frame).unwrap();
+        assert_eq!(val, json!({"id": 0x7FF, "extended": true, "dlc": 4}));
+        let frame: CanRemoteFrame = from_str(&val.to_string()).unwrap();
+        assert_eq!(EXT_LOW_ID, frame.id());
+        assert_eq!(4, frame.dlc());
+
+        let frame = CanErrorFrame::new_error(0x0004, &[0, 0x10]).unwrap();
+        let frame: CanAnyFrame =
+            from_str(&to_value(CanFrame::Error(frame)).unwrap().to_string()).unwrap();
+        let CanAnyFrame::Error(frame) = frame else {
+            panic!("Expected an error frame");
+        };
+        assert_eq!(0x0004, frame.error_bits());
+        assert_eq!(&[0, 0x10, 0, 0, 0, 0, 0, 0], frame.data());
+
+        let frame = CanFdFrame::with_flags(EXT_ID, &[0xAA; 12], FdFlags::BRS).unwrap();
+        let val = to_value(CanAnyFrame::Fd(frame)).unwrap();
+        assert_eq!("fd", val["type"]);
+        assert_eq!(true, val["brs"]);
+        assert_eq!(false, val["esi"]);
+        let CanAnyFrame::Fd(frame) = from_str(&val.to_string()).unwrap() else {
+            panic!("Expected an FD frame");
+        };
+        assert_eq!(EXT_ID, frame.id());
+        assert_eq!(FdFlags::BRS, frame.flags());
+        assert_eq!(&[0xAA; 12], frame.data());
+
+        // FD frames can't be read as classic frames
+        assert!(from_str::<CanFrame>(&val.to_string()).is_err());
+
+        // Bad IDs and data
+        assert!(from_str::<CanDataFrame>(r#"{"id":2048,"extended":false,"data":[]}"#).is_err());
+        assert!(from_str::<CanDataFrame>(
+            r#"{"id":1,"extended":false,"data":[0,1,2,3,4,5,6,7,8]}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_dlc_len() {
+        assert_eq!(Some(0), dlc_to_len(0));
+        assert_eq!(Some(8), dlc_to_len(8));
+        assert_eq!(Some(12), dlc_to_len(9));
+        assert_eq!(Some(64), dlc_to_len(15));
+        assert_eq!(None, dlc_to_len(16));
+
+        assert_eq!(Some(0), len_to_dlc(0));
+        assert_eq!(Some(8), len_to_dlc(8));
+        assert_eq!(Some(9), len_to_dlc(9));
+        assert_eq!(Some(9), len_to_dlc(12));
+        assert_eq!(Some(13), len_to_dlc(25));
+        assert_eq!(Some(15), len_to_dlc(64));
+        assert_eq!(None, len_to_dlc(65));
+
+        for dlc in 0..=CAN_MAX_RAW_DLC {
+            assert_eq!(Some(dlc), len_to_dlc(dlc_to_len(dlc).unwrap()));
+        }
+
+        assert_eq!(Some(7), fd_padded_len(7));
+        assert_eq!(Some(48), fd_padded_len(33));
+        assert_eq!(None, fd_padded_len(65));
+    }
+
+    #[test]
+    fn test_len8_dlc() {
+        let mut frame = CanDataFrame::new(STD_ID, &[0xAA; 8]).unwrap();
+        assert_eq!(None, frame.len8_dlc());
+        assert_eq!(8, frame.raw_dlc());
+
+        frame.set_len8_dlc(Some(0xF)).unwrap();
+        assert_eq!(Some(0xF), frame.len8_dlc());
+        assert_eq!(0xF, frame.raw_dlc());
+        assert_eq!(8, frame.dlc());
+        assert_eq!(&[0xAA; 8], frame.data());
+        assert_eq!(0xF, frame.as_ref().len8_dlc);
+
+        assert_eq!(
+            Err(ConstructionError::InvalidDlc),
+            frame.set_len8_dlc(Some(8))
+        );
+        assert_eq!(
+            Err(ConstructionError::InvalidDlc),
+            frame.set_len8_dlc(Some(16))
+        );
+
+        frame.set_len8_dlc(None).unwrap();
+        assert_eq!(None, frame.len8_dlc());
+
+        // only frames with 8 bytes can have a raw DLC
+        let mut frame = CanDataFrame::new(STD_ID, DATA).unwrap();
+        assert_eq!(
+            Err(ConstructionError::InvalidDlc),
+            frame.set_len8_dlc(Some(9))
+        );
+
+        // changing the data clears it
+        let mut frame = CanDataFrame::new(STD_ID, &[0xAA; 8]).unwrap();
+        frame.set_len8_dlc(Some(9)).unwrap();
+        frame.set_data(DATA).unwrap();
+        frame.set_data(&[0x55; 8]).unwrap();
+        assert_eq!(None, frame.len8_dlc());
+    }
+
+    #[test]
+    fn test_fd_padding() {
+        let frame = CanFdFrame::new(STD_ID, &[0xAA; 9]).unwrap();
+        assert_eq!(12, frame.len());
+        assert_eq!(9, frame.raw_dlc());
+        assert_eq!(&[0xAA; 9], &frame.data()[..9]);
+        assert_eq!(&[0; 3], &frame.data()[9..]);
+
+        let mut frame = CanFdFrame::new(STD_ID, &[0xAA; 64]).unwrap();
+        assert_eq!(15, frame.raw_dlc());
+
+        // shorter data clears out the old bytes in the padding
+        frame.set_data(&[0x55; 33]).unwrap();
+        assert_eq!(48, frame.len());
+        assert_eq!(&[0; 15], &frame.data()[33..]);
+
+        frame.set_data(DATA).unwrap();
+        assert_eq!(DATA, frame.data());
+        assert_eq!(DATA_LEN, frame.raw_dlc());
+
+        assert!(CanFdFrame::new(STD_ID, &[0; 65]).is_none());
+    }
+
+    #[test]
+    fn test_frame_refs() {
+        let frame = CanDataFrame::new(EXT_ID, DATA).unwrap();
+        let bytes = crate::as_bytes(frame.as_ref());
+
+        let view = CanFrameRef::from_bytes(bytes).unwrap();
+        assert_eq!(frame.id_word(), view.id_word());
+        assert_eq!(EXT_ID, view.id());
+        assert!(view.is_extended());
+        assert!(view.is_data_frame());
+        assert_eq!(DATA, view.data());
+        assert_eq!(bytes, view.as_bytes());
+
+        let CanFrame::Data(owned) = view.to_frame() else {
+            panic!("Expected a data frame");
+        };
+        assert_eq!(frame.id_word(), owned.id_word());
+        assert_eq!(DATA, owned.data());
+
+        // the view doesn't need an aligned buffer
+        let mut buf = [0u8; CAN_MTU + 1];
+        buf[1..].copy_from_slice(bytes);
+        let view = CanFrameRef::from_bytes(&buf[1..]).unwrap();
+        assert_eq!(EXT_ID, view.id());
+        assert_eq!(DATA, view.data());
+
+        let frame = CanRemoteFrame::new_remote(STD_ID, 4).unwrap();
+        let view = CanFrameRef::from_bytes(crate::as_bytes(frame.as_ref())).unwrap();
+        assert!(view.is_remote_frame());
+        assert_eq!(4, view.dlc());
+        assert_eq!(EMPTY_DATA, view.data());
+        assert!(matches!(CanFrame::from(view), CanFrame::Remote(_)));
+
+        // read-only
+        let mut view = view;
+        view.set_id(EXT_ID);
+        assert_eq!(STD_ID, view.id());
+        assert!(view.set_data(DATA).is_err());
+
+        // bad buffers
+        assert!(CanFrameRef::from_bytes(&bytes[1..]).is_none());
+        let mut buf = [0u8; CAN_MTU];
+        buf[4] = 9;
+        assert!(CanFrameRef::from_bytes(&buf).is_none());
+
+        let frame = CanFdFrame::with_flags(STD_ID, &[0xAA; 21], FdFlags::BRS).unwrap();
+        let bytes = crate::as_bytes(frame.as_ref());
+        let view = CanFdFrameRef::from_bytes(bytes).unwrap();
+        assert_eq!(STD_ID, view.id());
+        assert!(view.is_brs());
+        assert!(!view.is_esi());
+        assert_eq!(24, view.dlc());
+        assert_eq!(frame.data(), view.data());
+
+        let owned = view.to_frame();
+        assert_eq!(frame.id_word(), owned.id_word());
+        assert_eq!(frame.flags(), owned.flags());
+        assert_eq!(frame.data(), owned.data());
+
+        assert!(CanFdFrameRef::from_bytes(&bytes[..CAN_MTU]).is_none());
+        assert!(CanFrameRef::from_bytes(bytes).is_none());
+    }
+
+    #[test]
+    fn test_frame_builder() {
+        use ConstructionError::*;
+
+        // extended IDs stay extended, even when they're small
+        let frame = FrameBuilder::new()
+            .extended_id(0x7FF)
+            .data(DATA)
+            .build_classic()
+            .unwrap();
+        assert_eq!(EXT_LOW_ID, frame.id());
+        assert!(frame.is_data_frame());
+        assert_eq!(DATA, frame.data());
+
+        let frame = FrameBuilder::new().id(STD_ID).build().unwrap();
+        let CanAnyFrame::Normal(frame) = frame else {
+            panic!("Expected a data frame");
+        };
+        assert_eq!(STD_ID, frame.id());
+        assert_eq!(EMPTY_DATA, frame.data());
+
+        let frame = FrameBuilder::new()
+            .id(EXT_ID)
+            .rtr(true)
+            .dlc(4)
+            .build_classic()
+            .unwrap();
+        assert!(frame.is_remote_frame());
+        assert_eq!(EXT_ID, frame.id());
+        assert_eq!(4, frame.dlc());
+
+        let frame = FrameBuilder::new()
+            .standard_id(0x100)
+            .data(&[0xAA; 8])
+            .dlc(0xC)
+            .build()
+            .unwrap();
+        let CanAnyFrame::Normal(frame) = frame else {
+            panic!("Expected a data frame");
+        };
+        assert_eq!(Some(0xC), frame.len8_dlc());
+
+        let frame = FrameBuilder::new()
+            .standard_id(0x100)
+            .data(DATA)
+            .esi(true)
+            .dlc(9)
+            .build_fd()
+            .unwrap();
+        assert_eq!(FdFlags::ESI, frame.flags());
+        assert_eq!(12, frame.len());
+        assert_eq!(DATA, &frame.data()[..DATA_LEN]);
+
+        let frame = FrameBuilder::new().data(DATA).build_fd().unwrap();
+        assert_eq!(FdFlags::empty(), frame.flags());
+        assert_eq!(DATA, frame.data());
+
+        // errors
+        let b = FrameBuilder::new();
+        assert_eq!(Err(IDTooLarge), b.standard_id(0x800).build().map(|_| ()));
+        assert_eq!(
+            Err(IDTooLarge),
+            b.extended_id(0x2000_0000).build().map(|_| ())
+        );
+        assert_eq!(Err(TooMuchData), b.data(&[0; 9]).build().map(|_| ()));
+        assert_eq!(
+            Err(TooMuchData),
+            b.data(&[0; 65]).fd(true).build().map(|_| ())
+        );
+        assert_eq!(
+            Err(WrongFrameType),
+            b.rtr(true).data(DATA).build().map(|_| ())
+        );
+        assert_eq!(
+            Err(WrongFrameType),
+            b.rtr(true).brs(true).build().map(|_| ())
+        );
+        assert_eq!(Err(InvalidDlc), b.rtr(true).dlc(9).build().map(|_| ()));
+        assert_eq!(Err(InvalidDlc), b.data(DATA).dlc(9).build().map(|_| ()));
+        assert_eq!(
+            Err(InvalidDlc),
+            b.data(&[0; 9]).dlc(8).build_fd().map(|_| ())
+        );
+        assert_eq!(Err(InvalidDlc), b.fd(true).dlc(16).build().map(|_| ()));
+        assert_eq!(Err(WrongFrameType), b.fd(true).build_classic().map(|_| ()));
+    }
+
+    #[test]
+    fn test_signal_layout() {
+        let layout = SignalLayout::new(12, 12, ByteOrder::Intel).signed();
+
+        let mut data = [0u8; 3];
+        layout.insert(&mut data, 0xABC).unwrap();
+        assert_eq!([0x00, 0xC0, 0xAB], data);
+        assert_eq!(Some(0xABC), layout.extract(&data));
+        assert_eq!(0xABC - 0x1000, layout.raw_to_int(0xABC));
+        assert_eq!(-1348.0, layout.raw_to_physical(0xABC));
+
+        // Motorola start bit 3 is the low nibble of byte 0, then byte 1
+        let layout = SignalLayout::new(3, 12, ByteOrder::Motorola);
+        let mut data = [0u8; 3];
+        layout.insert(&mut data, 0xABC).unwrap();
+        assert_eq!([0x0A, 0xBC, 0x00], data);
+        assert_eq!(Some(0xABC), layout.extract(&data));
+
+        // Motorola across three bytes
+        let layout = SignalLayout::new(7, 24, ByteOrder::Motorola);
+        assert_eq!(Some(0x123456), layout.extract(&[0x12, 0x34, 0x56]));
+
+        assert_eq!(
+            Err(ConstructionError::SignalOutOfBounds),
+            layout.insert(&mut [0u8; 2], 0)
+        );
+        assert_eq!(None, layout.extract(&[0u8; 2]));
+        assert_eq!(
+            Err(ConstructionError::ValueOutOfRange),
+            layout.insert(&mut [0u8; 3], 0x1000000)
+        );
+
+        let layout = SignalLayout::new(0, 64, ByteOrder::Intel);
+        assert_eq!(Some(u64::MAX), layout.extract(&[0xFF; 8]));
+        assert_eq!(
+            None,
+            SignalLayout::new(0, 0, ByteOrder::Intel).extract(&[0])
+        );
+        assert_eq!(
+            None,
+            SignalLayout::new(0, 65, ByteOrder::Intel).extract(&[0; 9])
+        );
+
+        let layout = SignalLayout::new(8, 8, ByteOrder::Intel)
+            .signed()
+            .scale(0.5, -10.0);
+        assert_eq!(Some(63), layout.physical_to_raw(21.5));
+        assert_eq!(Some(0xFF), layout.physical_to_raw(-10.5));
+        assert_eq!(None, layout.physical_to_raw(60.0));
+        assert_eq!(None, layout.physical_to_raw(-80.0));
+
+        let layout = SignalLayout::new(0, 32, ByteOrder::Intel).signal_type(SignalType::Float);
+        let data = 1.5f32.to_bits().to_le_bytes();
+        assert_eq!(Some(1.5), layout.decode(&data));
+        assert_eq!(Some(1.5f32.to_bits() as u64), layout.physical_to_raw(1.5));
+
+        // the float has to fill the signal
+        let layout = SignalLayout::new(0, 16, ByteOrder::Intel).signal_type(SignalType::Float);
+        assert_eq!(None, layout.physical_to_raw(1.5));
+    }
+
+    #[test]
+    fn test_frame_signals() {
+        let id = StandardId::new(0x100).unwrap();
+        let mut frame = CanDataFrame::new(id, &[0x0A, 0xBC, 0, 0]).unwrap();
+
+        assert_eq!(Some(0xABC), frame.get_signal(3, 12, ByteOrder::Motorola));
+        assert_eq!(None, frame.get_signal(24, 12, ByteOrder::Intel));
+
+        frame.set_signal(16, 12, ByteOrder::Intel, 0xABC).unwrap();
+        assert_eq!(frame.data(), &[0x0A, 0xBC, 0xBC, 0x0A]);
+        assert_eq!(
+            Err(ConstructionError::SignalOutOfBounds),
+            frame.set_signal(24, 12, ByteOrder::Intel, 0)
+        );
+        assert_eq!(
+            Err(ConstructionError::ValueOutOfRange),
+            frame.set_signal(0, 4, ByteOrder::Intel, 0x10)
+        );
+        assert_eq!(frame.data(), &[0x0A, 0xBC, 0xBC, 0x0A]);
+
+        let temp = SignalLayout::new(8, 8, ByteOrder::Intel).scale(0.5, -10.0);
+        frame.set_signal_value(&temp, 21.5).unwrap();
+        assert_eq!(Some(21.5), frame.get_signal_value(&temp));
+        assert_eq!(frame.data(), &[0x0A, 63, 0xBC, 0x0A]);
+
+        // the raw DLC is kept
+        let mut frame = CanDataFrame::new(id, &[0; 8]).unwrap();
+        frame.set_len8_dlc(Some(12)).unwrap();
+        frame.set_signal(0, 8, ByteOrder::Intel, 0x55).unwrap();
+        assert_eq!(Some(12), frame.len8_dlc());
+
+        let mut frame = CanFrame::from(frame);
+        frame.set_signal_value(&temp, -10.0).unwrap();
+        assert_eq!(frame.data(), &[0x55, 0, 0, 0, 0, 0, 0, 0]);
+
+        // FD frames keep their length
+        let mut frame = CanFdFrame::new(id, &[0; 12]).unwrap();
+        frame.set_signal(95, 8, ByteOrder::Intel, 0xFF).unwrap_err();
+        frame.set_signal(88, 8, ByteOrder::Intel, 0xFF).unwrap();
+        assert_eq!(12, frame.len());
+        assert_eq!(0xFF, frame.data()[11]);
+
+        let mut frame = CanRemoteFrame::new_remote(id, 4).unwrap();
+        assert_eq!(
+            Err(ConstructionError::WrongFrameType),
+            frame.set_signal(0, 8, ByteOrder::Intel, 0)
+        );
+    }
+
+    #[test]
+    fn test_parse_hex() {
+        assert_eq!(Some(0x1F334455), parse_hex(b"1f334455"));
+        assert_eq!(None, parse_hex(b""));
+        assert_eq!(None, parse_hex(b"12G"));
+        assert_eq!(None, parse_hex(b"11112222333344445"));
+
+        let mut buf = [0u8; 4];
+        assert_eq!(Ok(3), decode_data(b"01aBFf", &mut buf));
+        assert_eq!([0x01, 0xAB, 0xFF], buf[..3]);
+        assert_eq!(Ok(3), decode_data(b".01.aB.Ff.", &mut buf));
+        assert_eq!([0x01, 0xAB, 0xFF], buf[..3]);
+        assert_eq!(Ok(0), decode_data(b"", &mut buf));
+        assert_eq!(
+            Err(ParseFrameError::InvalidFormat),
+            decode_data(b"012", &mut buf)
+        );
+        assert_eq!(
+            Err(ParseFrameError::InvalidFormat),
+            decode_data(b"0x", &mut buf)
+        );
+        assert_eq!(
+            Err(ParseFrameError::InvalidFormat),
+            decode_data(b"01..02", &mut buf)
+        );
+        assert_eq!(
+            Err(ParseFrameError::Construction(
+                ConstructionError::TooMuchData
+            )),
+            decode_data(b"0102030405", &mut buf)
+        );
+    }
+
+    #[test]
+    fn test_parse_frame() {
+        let frame: CanFrame = "123#DEADBEEF".parse().unwrap();
+        let CanFrame::Data(frame) = frame else {
+            panic!("Expected a data frame");
+        };
+        assert_eq!(Id::Standard(StandardId::new(0x123).unwrap()), frame.id());
+        assert_eq!(&[0xDE, 0xAD, 0xBE, 0xEF], frame.data());
+
+        let frame: CanFrame = "00000123#11.22.33".parse().unwrap();
+        assert_eq!(Id::Extended(ExtendedId::new(0x123).unwrap()), frame.id());
+        assert_eq!(&[0x11, 0x22, 0x33], frame.data());
+
+        let frame: CanFrame = "1F334455#R3".parse().unwrap();
+        let CanFrame::Remote(frame) = frame else {
+            panic!("Expected a remote frame");
+        };
+        assert_eq!(
+            Id::Extended(ExtendedId::new(0x1F334455).unwrap()),
+            frame.id()
+        );
+        assert_eq!(3, frame.dlc());
+
+        let frame: CanFrame = "123#r".parse().unwrap();
+        assert!(frame.is_remote_frame());
+        assert_eq!(0, frame.dlc());
+
+        let frame: CanFrame = "20000004#0004000000000000".parse().unwrap();
+        let CanFrame::Error(frame) = frame else {
+            panic!("Expected an error frame");
+        };
+        assert_eq!(0x0004, frame.error_bits());
+
+        let frame: CanFdFrame = "123##1AABB".parse().unwrap();
+        assert_eq!(Id::Standard(StandardId::new(0x123).unwrap()), frame.id());
+        assert_eq!(FdFlags::BRS, frame.flags());
+        assert_eq!(&[0xAA, 0xBB], frame.data());
+
+        // the classic and FD forms don't mix, so that they round-trip
+        let err = "123#AABB".parse::<CanFdFrame>().unwrap_err();
+        assert_eq!(
+            ParseFrameError::Construction(ConstructionError::WrongFrameType),
+            err
+        );
+        let err = "123##1AABB".parse::<CanFrame>().unwrap_err();
+        assert_eq!(
+            ParseFrameError::Construction(ConstructionError::WrongFrameType),
+            err
+        );
+        let err = "123#R".parse::<CanFdFrame>().unwrap_err();
+        assert_eq!(
+            ParseFrameError::Construction(ConstructionError::WrongFrameType),
+            err
+        );
+
+        let frame: CanAnyFrame = "7FF##0".parse().unwrap();
+        assert!(matches!(frame, CanAnyFrame::Fd(_)));
+
+        let frame: CanFrame = "123#0011223344556677_E".parse().unwrap();
+        let CanFrame::Data(frame) = frame else {
+            panic!("Expected a data frame");
+        };
+        assert_eq!(Some(0xE), frame.len8_dlc());
+        assert_eq!(8, frame.data().len());
+
+        // FD frames are padded to a valid size
+        let frame: CanFdFrame = "123##0112233445566778899".parse().unwrap();
+        assert_eq!(12, frame.len());
+
+        // errors
+        for s in [
+            "", "123", "#11", "12X#11", "123#1", "123#R12", "123##", "123##X11",
+        ] {
+            assert_eq!(
+                Err(ParseFrameError::InvalidFormat),
+                s.parse::<CanAnyFrame>().map(|_| ()),
+                "{:?}",
+                s
+            );
+        }
+        for s in [
+            "123#R9",
+            "123#001122334455667788",
+            "40000000#11",
+            "123#00112233_E",
+            "123#0011223344556677_8",
+        ] {
+            assert!(matches!(
+                s.parse::<CanAnyFrame>(),
+                Err(ParseFrameError::Construction(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        for s in [
+            "123#DEADBEEF",
+            "7FF#",
+            "00000123#1122334455667788",
+            "1F334455#R3",
+            "123#R",
+            "20000004#0004000000000000",
+            "123##1AABB",
+            "1F334455##3",
+            "123#0011223344556677_F",
+        ] {
+            let frame: CanAnyFrame = s.parse().unwrap();
+            assert_eq!(s, frame.to_string());
+        }
+
+        let frame: CanFrame = "123#de.ad.be.ef".parse().unwrap();
+        assert_eq!("123#DEADBEEF", frame.to_string());
+
+        let frame = CanFdFrame::with_flags(
+            ExtendedId::new(0x1234).unwrap(),
+            &[0x55; 12],
+            FdFlags::BRS | FdFlags::ESI,
+        )
+        .unwrap();
+        let s = frame.to_string();
+        assert_eq!("00001234##3555555555555555555555555", s);
+        let parsed: CanFdFrame = s.parse().unwrap();
+        assert_eq!(frame.id_word(), parsed.id_word());
+        assert_eq!(frame.flags(), parsed.flags());
+        assert_eq!(frame.data(), parsed.data());
+    }
 }
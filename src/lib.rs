@@ -62,10 +62,26 @@
 //!   [neli](https://docs.rs/neli/latest/neli/) library and its dependencies.
 //!
 //! * **dump** -
-//!   Whether to include candump parsing capabilities.
+//!   Whether to include candump and other log-file format capabilities,
+//!   like PEAK TRC trace files and pcap/pcapng captures.
 //!
 //! ### Non-default
 //!
+//! * **gzip**, **xz**, **zstd** -
+//!   Read and write candump logs compressed with gzip, xz, or Zstandard,
+//!   using [flate2](https://crates.io/crates/flate2),
+//!   [xz2](https://crates.io/crates/xz2), and
+//!   [zstd](https://crates.io/crates/zstd). Each implies **dump**.
+//!
+//! * **serde** -
+//!   Implement `Serialize` and `Deserialize` from [serde](https://crates.io/crates/serde)
+//!   for the frames, filters, errors, and netlink interface details. See
+//!   the [frame] module for the representation of the frames.
+//!
+//! * **dbc** -
+//!   Parse DBC database files, and use them to decode frames into signal
+//!   values, and encode signal values into frames. See the `dbc` module.
+//!
 //! * **utils** -
 //!   Whether to build command-line utilities. This brings in additional
 //!   dependencies like [anyhow](https://docs.rs/anyhow/latest/anyhow/) and
@@ -115,7 +131,7 @@ pub use embedded_can::{
 pub mod errors;
 pub use errors::{
     CanError, CanErrorDecodingFailure, ConstructionError, Error, IoError, IoErrorKind, IoResult,
-    Result,
+    ParseFrameError, Result,
 };
 
 pub mod addr;
@@ -123,13 +139,31 @@ pub use addr::CanAddr;
 
 pub mod frame;
 pub use frame::{
-    CanAnyFrame, CanDataFrame, CanErrorFrame, CanFdFrame, CanFrame, CanRawFrame, CanRemoteFrame,
-    Frame,
+    ByteOrder, CanAnyFrame, CanDataFrame, CanErrorFrame, CanFdFrame, CanFdFrameRef, CanFrame,
+    CanFrameRef, CanRawFrame, CanRemoteFrame, Frame, FrameBuilder, SignalLayout, SignalType,
 };
 
+#[cfg(feature = "dump")]
+pub mod compress;
+
+#[cfg(feature = "dump")]
+pub mod logfile;
+
 #[cfg(feature = "dump")]
 pub mod dump;
 
+#[cfg(feature = "dump")]
+pub mod trc;
+
+#[cfg(feature = "dump")]
+pub mod pcap;
+
+#[cfg(feature = "dump")]
+pub mod replay;
+
+#[cfg(feature = "dbc")]
+pub mod dbc;
+
 pub mod socket;
 pub use socket::{CanFdSocket, CanFilter, CanSocket, ShouldRetry, Socket, SocketOptions};
 
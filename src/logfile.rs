@@ -0,0 +1,298 @@
+// socketcan/src/logfile.rs
+//
+// Common types and traits for CAN log file formats.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Common interface to the CAN log file formats.
+//!
+//! Each of the log file formats in this crate, like [candump](crate::dump),
+//! [PEAK TRC](crate::trc), and [pcap](crate::pcap), has a reader and writer
+//! with records specific to the format. They also implement the
+//! [`LogReader`] and [`LogWriter`] traits here, which use an owned
+//! [`LogRecord`] that is common to all of them.
+//!
+//! This allows an application to handle logs without caring about the
+//! format of the files. Readers can be boxed and chained, and records
+//! can be copied from one format to another:
+//!
+//! ```no_run
+//! use socketcan::{dump, logfile, trc};
+//!
+//! let mut rdr = trc::Reader::from_file("capture.trc").unwrap();
+//! let mut wtr = dump::Writer::create("capture.log").unwrap();
+//! logfile::copy(&mut rdr, &mut wtr).unwrap();
+//! ```
+
+use crate::{dump, pcap, trc, CanAnyFrame};
+use std::{fmt, io, time::SystemTime};
+use thiserror::Error;
+
+// ===== Direction =====
+
+/// The direction of a message on the bus, as seen by the recording node.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// A message received from the bus.
+    #[default]
+    Rx,
+    /// A message transmitted to the bus.
+    Tx,
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Direction::Rx => write!(f, "Rx"),
+            Direction::Tx => write!(f, "Tx"),
+        }
+    }
+}
+
+// ===== LogRecord =====
+
+/// A single CAN frame from a log file, in any format.
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    /// The time that the frame was recorded
+    pub timestamp: SystemTime,
+    /// The name of the interface that the frame was recorded on.
+    ///
+    /// This is empty if the log did not record it.
+    pub interface: String,
+    /// Whether the frame was received or transmitted
+    pub direction: Direction,
+    /// The CAN frame
+    pub frame: CanAnyFrame,
+}
+
+impl LogRecord {
+    /// Creates a record of a received frame.
+    pub fn new<S>(timestamp: SystemTime, interface: S, frame: CanAnyFrame) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            timestamp,
+            interface: interface.into(),
+            direction: Direction::Rx,
+            frame,
+        }
+    }
+}
+
+// ===== Error =====
+
+/// An error reading a log file, in any format.
+#[derive(Error, Debug)]
+pub enum Error {
+    /// I/O Error
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// Error parsing a candump log
+    #[error(transparent)]
+    Dump(#[from] dump::ParseError),
+    /// Error parsing a PEAK TRC file
+    #[error(transparent)]
+    Trc(#[from] trc::ParseError),
+    /// Error parsing a pcap or pcapng file
+    #[error(transparent)]
+    Pcap(#[from] pcap::ParseError),
+}
+
+// ===== LogReader =====
+
+/// A reader of CAN log records, in any format.
+pub trait LogReader {
+    /// Reads the next record from the log.
+    ///
+    /// Returns `None` at the end of the log.
+    fn read_record(&mut self) -> Result<Option<LogRecord>, Error>;
+
+    /// Returns an iterator over all the remaining records.
+    fn records(&mut self) -> LogRecords<'_, Self>
+    where
+        Self: Sized,
+    {
+        LogRecords { src: self }
+    }
+}
+
+impl<R: LogReader + ?Sized> LogReader for &mut R {
+    fn read_record(&mut self) -> Result<Option<LogRecord>, Error> {
+        (**self).read_record()
+    }
+}
+
+impl<R: LogReader + ?Sized> LogReader for Box<R> {
+    fn read_record(&mut self) -> Result<Option<LogRecord>, Error> {
+        (**self).read_record()
+    }
+}
+
+/// Iterator over the records of a [`LogReader`].
+#[derive(Debug)]
+pub struct LogRecords<'a, R: ?Sized + 'a> {
+    src: &'a mut R,
+}
+
+impl<R: LogReader + ?Sized> Iterator for LogRecords<'_, R> {
+    type Item = Result<LogRecord, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.src.read_record().transpose()
+    }
+}
+
+// ===== LogWriter =====
+
+/// A writer of CAN log records, in any format.
+pub trait LogWriter {
+    /// Writes a record to the log.
+    ///
+    /// This fails with an `InvalidInput` error if the format can not
+    /// represent the frame in the record.
+    fn write_record(&mut self, rec: &LogRecord) -> io::Result<()>;
+
+    /// Flushes any buffered records to the underlying writer.
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+impl<W: LogWriter + ?Sized> LogWriter for &mut W {
+    fn write_record(&mut self, rec: &LogRecord) -> io::Result<()> {
+        (**self).write_record(rec)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
+impl<W: LogWriter + ?Sized> LogWriter for Box<W> {
+    fn write_record(&mut self, rec: &LogRecord) -> io::Result<()> {
+        (**self).write_record(rec)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (**self).flush()
+    }
+}
+
+/// Copies all the remaining records from a reader to a writer, converting
+/// between the formats.
+///
+/// On success, returns the number of records that were copied.
+pub fn copy<R, W>(rdr: &mut R, wtr: &mut W) -> Result<usize, Error>
+where
+    R: LogReader + ?Sized,
+    W: LogWriter + ?Sized,
+{
+    let mut n = 0;
+    while let Some(rec) = rdr.read_record()? {
+        wtr.write_record(&rec)?;
+        n += 1;
+    }
+    wtr.flush()?;
+    Ok(n)
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CanDataFrame, CanFdFrame, EmbeddedFrame, StandardId};
+    use std::time::Duration;
+
+    fn test_records() -> Vec<LogRecord> {
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_697_630_400);
+        let id = StandardId::new(0x123).unwrap();
+        vec![
+            LogRecord::new(
+                t0,
+                "1",
+                CanAnyFrame::Normal(CanDataFrame::new(id, &[1, 2, 3]).unwrap()),
+            ),
+            LogRecord::new(
+                t0 + Duration::from_millis(5),
+                "2",
+                CanAnyFrame::Fd(CanFdFrame::new(id, &[0xAA; 12]).unwrap()),
+            ),
+        ]
+    }
+
+    fn assert_records_eq(a: &[LogRecord], b: &[LogRecord]) {
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.iter().zip(b.iter()) {
+            assert_eq!(a.timestamp, b.timestamp);
+            assert_eq!(a.interface, b.interface);
+            assert_eq!(a.direction, b.direction);
+            match (a.frame, b.frame) {
+                (CanAnyFrame::Normal(a), CanAnyFrame::Normal(b)) => {
+                    assert_eq!(a.id(), b.id());
+                    assert_eq!(a.data(), b.data());
+                }
+                (CanAnyFrame::Fd(a), CanAnyFrame::Fd(b)) => {
+                    assert_eq!(a.id(), b.id());
+                    assert_eq!(a.data(), b.data());
+                }
+                _ => panic!("Frame type mismatch"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_convert() {
+        let mut dump_wtr = dump::Writer::new(Vec::new());
+        for rec in test_records() {
+            dump_wtr.write_record(&rec).unwrap();
+        }
+        let buf = dump_wtr.into_inner();
+
+        // candump -> TRC
+        let mut rdr = dump::Reader::from_reader(buf.as_slice());
+        let mut wtr = trc::Writer::new(Vec::new(), trc::TrcVersion::V2_1, 0).unwrap();
+        assert_eq!(2, copy(&mut rdr, &mut wtr).unwrap());
+        let buf = wtr.into_inner();
+
+        // TRC -> pcapng
+        let mut rdr = trc::Reader::from_reader(buf.as_slice()).unwrap();
+        let mut wtr = pcap::Writer::new(Vec::new(), pcap::Format::PcapNg).unwrap();
+        assert_eq!(2, copy(&mut rdr, &mut wtr).unwrap());
+        let buf = wtr.into_inner();
+
+        let mut rdr: Box<dyn LogReader> =
+            Box::new(pcap::Reader::from_reader(buf.as_slice()).unwrap());
+        let recs = rdr.records().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_records_eq(&test_records(), &recs);
+    }
+
+    #[test]
+    fn test_chain() {
+        let mut wtr = dump::Writer::new(Vec::new());
+        for rec in test_records() {
+            wtr.write_record(&rec).unwrap();
+        }
+        let buf = wtr.into_inner();
+
+        let readers: Vec<Box<dyn LogReader + '_>> = vec![
+            Box::new(dump::Reader::from_reader(buf.as_slice())),
+            Box::new(dump::Reader::from_reader(buf.as_slice())),
+        ];
+
+        let recs = readers
+            .into_iter()
+            .flat_map(|mut rdr| rdr.records().collect::<Vec<_>>())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let mut expected = test_records();
+        expected.extend(test_records());
+        assert_records_eq(&expected, &recs);
+    }
+}
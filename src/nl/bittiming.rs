@@ -0,0 +1,451 @@
+// socketcan/src/nl/bittiming.rs
+//
+// Calculates CAN bit timing parameters.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Calculation of the CAN bit timing parameters.
+//!
+//! This is a port of the kernel's `can_calc_bittiming()`, from:
+//!
+//! <https://github.com/torvalds/linux/blob/master/drivers/net/can/dev/calc_bittiming.c>
+//!
+//! It gives the same results as the kernel, so timings can be calculated
+//! and checked before they are sent to an interface, including for drivers
+//! in kernels built without `CONFIG_CAN_CALC_BITTIMING`, which only accept
+//! fully specified timings.
+
+use super::{CanBitTiming, CanBitTimingConst};
+use thiserror::Error;
+
+/// The synchronization segment is always one time quantum.
+const CAN_SYNC_SEG: u32 = 1;
+
+/// The largest bitrate error allowed, in tenths of a percent.
+const CAN_CALC_MAX_ERROR: u32 = 50;
+
+/// An error calculating the bit timing for an interface
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CanBitTimingError {
+    /// The bitrate, sample point, clock frequency, or timing constants are
+    /// out of range
+    #[error("invalid bitrate, sample point, clock, or timing constants")]
+    InvalidParams,
+    /// The closest bitrate that the controller can make is too far from the
+    /// requested one. This is the error in tenths of a percent.
+    #[error("bitrate error: {}.{}% too high", .0 / 10, .0 % 10)]
+    BitrateError(u32),
+    /// The default synchronization jump width is larger than the controller
+    /// allows.
+    #[error("sjw: {0} greater than max sjw: {1}")]
+    SjwTooLarge(u32, u32),
+}
+
+/// Checks that the minimum of each of the timing constants is not past its
+/// maximum, and that the time segments are small enough for the sample
+/// point, in tenths of a percent of the whole bit, not to overflow.
+fn check_const(btc: &CanBitTimingConst) -> Result<(), CanBitTimingError> {
+    let in_order = btc.tseg1_min <= btc.tseg1_max
+        && btc.tseg2_min <= btc.tseg2_max
+        && btc.brp_min <= btc.brp_max
+        && btc.brp_inc != 0;
+
+    let fits = btc
+        .tseg1_max
+        .checked_add(btc.tseg2_max)
+        .and_then(|tseg| tseg.checked_add(CAN_SYNC_SEG))
+        .and_then(|bit_time| bit_time.checked_mul(2 * 1000))
+        .is_some();
+
+    if in_order && fits {
+        Ok(())
+    } else {
+        Err(CanBitTimingError::InvalidParams)
+    }
+}
+
+/// Finds the segments closest to the nominal sample point, without going
+/// past it, for a total of `tseg` time quanta in the two time segments.
+///
+/// Returns the sample point, the error, and the segments.
+fn update_sample_point(
+    btc: &CanBitTimingConst,
+    sample_point_nominal: u32,
+    tseg: u32,
+) -> (u32, u32, u32, u32) {
+    let mut best_sample_point = 0;
+    let mut best_sample_point_error = u32::MAX;
+    let (mut best_tseg1, mut best_tseg2) = (0, 0);
+
+    for i in 0..=1 {
+        // These wrap like the kernel's unsigned math, then get clamped
+        let tseg2 = (tseg + CAN_SYNC_SEG)
+            .wrapping_sub(sample_point_nominal * (tseg + CAN_SYNC_SEG) / 1000)
+            .wrapping_sub(i)
+            .clamp(btc.tseg2_min, btc.tseg2_max);
+        let mut tseg1 = tseg.wrapping_sub(tseg2);
+        let mut tseg2 = tseg2;
+        if tseg1 > btc.tseg1_max {
+            tseg1 = btc.tseg1_max;
+            tseg2 = tseg.wrapping_sub(tseg1);
+        }
+
+        let sample_point =
+            (tseg + CAN_SYNC_SEG).wrapping_sub(tseg2).wrapping_mul(1000) / (tseg + CAN_SYNC_SEG);
+
+        if sample_point <= sample_point_nominal {
+            let sample_point_error = sample_point_nominal - sample_point;
+            if sample_point_error < best_sample_point_error {
+                best_sample_point = sample_point;
+                best_sample_point_error = sample_point_error;
+                best_tseg1 = tseg1;
+                best_tseg2 = tseg2;
+            }
+        }
+    }
+
+    (
+        best_sample_point,
+        best_sample_point_error,
+        best_tseg1,
+        best_tseg2,
+    )
+}
+
+impl CanBitTiming {
+    /// Calculates the bit timing for a bitrate, the same way as the kernel.
+    ///
+    /// This searches for the prescaler and time segments that come closest
+    /// to the bitrate, then to the sample point, for a controller with the
+    /// timing constants, `btc`, and the clock frequency, `clock`, in Hz.
+    /// Both of these can be read from an interface with
+    /// [`CanInterface::bit_timing_const()`](super::CanInterface::bit_timing_const)
+    /// and [`CanInterface::clock()`](super::CanInterface::clock).
+    ///
+    /// The sample point is in tenths of a percent. If it's not given, the
+    /// CiA recommended value is used: 75% above 800 kbps, 80% above 500 kbps,
+    /// and 87.5% otherwise.
+    ///
+    /// The returned timing has the actual bitrate and sample point, which
+    /// can be a little different from the ones requested. It fails if the
+    /// bitrate is off by more than 5%, or if the timing constants are out of
+    /// order, like a minimum that's larger than its maximum.
+    ///
+    /// The result can be passed to
+    /// [`CanInterface::set_bit_timing()`](super::CanInterface::set_bit_timing),
+    /// which sends the kernel just the time quantum and segments, since it
+    /// rejects a timing that also has a bitrate.
+    ///
+    /// ```
+    /// use socketcan::nl::CanBitTiming;
+    /// # use socketcan::nl::CanBitTimingConst;
+    /// # let mut btc = CanBitTimingConst::default();
+    /// # (btc.tseg1_min, btc.tseg1_max, btc.tseg2_min, btc.tseg2_max) = (1, 16, 1, 8);
+    /// # (btc.sjw_max, btc.brp_min, btc.brp_max, btc.brp_inc) = (4, 1, 64, 1);
+    ///
+    /// // With the constants for an SJA1000 controller
+    /// let bt = CanBitTiming::calculate(500_000, None, &btc, 8_000_000).unwrap();
+    /// assert_eq!((bt.brp, bt.tq), (1, 125));
+    /// assert_eq!((bt.prop_seg, bt.phase_seg1, bt.phase_seg2), (6, 7, 2));
+    /// assert_eq!(bt.sample_point, 875);
+    /// ```
+    pub fn calculate<P>(
+        bitrate: u32,
+        sample_point: P,
+        btc: &CanBitTimingConst,
+        clock: u32,
+    ) -> Result<Self, CanBitTimingError>
+    where
+        P: Into<Option<u32>>,
+    {
+        // Use CiA recommended sample points
+        let sample_point_nominal = match sample_point.into() {
+            Some(sp) if sp > 0 => sp,
+            _ if bitrate > 800_000 => 750,
+            _ if bitrate > 500_000 => 800,
+            _ => 875,
+        };
+
+        if bitrate == 0 || clock == 0 || sample_point_nominal >= 1000 {
+            return Err(CanBitTimingError::InvalidParams);
+        }
+        check_const(btc)?;
+
+        let mut best_bitrate_error = u32::MAX;
+        let mut best_sample_point_error = u32::MAX;
+        let mut best_tseg = 0;
+        let mut best_brp = 0;
+
+        // tseg even = round down, odd = round up
+        let tseg_max = (btc.tseg1_max + btc.tseg2_max) * 2 + 1;
+        let tseg_min = (btc.tseg1_min + btc.tseg2_min) * 2;
+
+        for tseg in (tseg_min..=tseg_max).rev() {
+            let tsegall = CAN_SYNC_SEG + tseg / 2;
+
+            // Compute all possible tseg choices (tseg=tseg1+tseg2)
+            let brp = (clock as u64 / (tsegall as u64 * bitrate as u64)) as u32 + tseg % 2;
+
+            // Choose the brp step which is possible in the system
+            let brp = (brp / btc.brp_inc) * btc.brp_inc;
+            if brp < btc.brp_min || brp > btc.brp_max || brp == 0 {
+                continue;
+            }
+
+            let rate = (clock as u64 / (brp as u64 * tsegall as u64)) as u32;
+            let bitrate_error = bitrate.abs_diff(rate);
+
+            if bitrate_error > best_bitrate_error {
+                continue;
+            }
+
+            // Reset the sample point error if we have a better bitrate
+            if bitrate_error < best_bitrate_error {
+                best_sample_point_error = u32::MAX;
+            }
+
+            let (_, sample_point_error, _, _) =
+                update_sample_point(btc, sample_point_nominal, tseg / 2);
+            if sample_point_error >= best_sample_point_error {
+                continue;
+            }
+
+            best_sample_point_error = sample_point_error;
+            best_bitrate_error = bitrate_error;
+            best_tseg = tseg / 2;
+            best_brp = brp;
+
+            if bitrate_error == 0 && sample_point_error == 0 {
+                break;
+            }
+        }
+
+        if best_bitrate_error != 0 {
+            // Error in one-tenth of a percent
+            let err = (best_bitrate_error as u64 * 1000 / bitrate as u64) as u32;
+            if err > CAN_CALC_MAX_ERROR {
+                return Err(CanBitTimingError::BitrateError(err));
+            }
+        }
+
+        // The real sample point
+        let (sample_point, _, tseg1, tseg2) =
+            update_sample_point(btc, sample_point_nominal, best_tseg);
+
+        let prop_seg = tseg1 / 2;
+        let phase_seg1 = tseg1 - prop_seg;
+        let phase_seg2 = tseg2;
+
+        // The default sjw, as a sane fraction of phase_seg2
+        let sjw = (phase_seg2 / 2).min(phase_seg1).max(1);
+        if sjw > btc.sjw_max {
+            return Err(CanBitTimingError::SjwTooLarge(sjw, btc.sjw_max));
+        }
+
+        let tq = (best_brp as u64 * 1_000_000_000 / clock as u64) as u32;
+        let bit_time = CAN_SYNC_SEG + tseg1 + tseg2;
+
+        Ok(Self {
+            // The real bitrate
+            bitrate: (clock as u64 / (best_brp as u64 * bit_time as u64)) as u32,
+            sample_point,
+            tq,
+            prop_seg,
+            phase_seg1,
+            phase_seg2,
+            sjw,
+            brp: best_brp,
+        })
+    }
+
+    /// Gets the timing in the form that the kernel takes it.
+    ///
+    /// The kernel calculates the timing from the bitrate and sample point
+    /// if the time quantum is zero, and otherwise takes the time quantum
+    /// and segments as they are. It rejects a timing with both a bitrate
+    /// and a time quantum, like one read back from an interface, or from
+    /// [`calculate()`](Self::calculate), so this clears the bitrate and
+    /// sample point of a timing that has a time quantum.
+    pub(crate) fn for_kernel(self) -> Self {
+        if self.tq == 0 {
+            self
+        } else {
+            Self {
+                bitrate: 0,
+                sample_point: 0,
+                ..self
+            }
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bittiming_const(
+        tseg1: (u32, u32),
+        tseg2: (u32, u32),
+        sjw_max: u32,
+        brp: (u32, u32, u32),
+    ) -> CanBitTimingConst {
+        CanBitTimingConst {
+            tseg1_min: tseg1.0,
+            tseg1_max: tseg1.1,
+            tseg2_min: tseg2.0,
+            tseg2_max: tseg2.1,
+            sjw_max,
+            brp_min: brp.0,
+            brp_max: brp.1,
+            brp_inc: brp.2,
+            ..CanBitTimingConst::default()
+        }
+    }
+
+    // (bitrate, tq, prop_seg, phase_seg1, phase_seg2, sjw, brp, real bitrate, sample point)
+    type Row = (u32, u32, u32, u32, u32, u32, u32, u32, u32);
+
+    fn check(btc: &CanBitTimingConst, clock: u32, table: &[Row]) {
+        for &(bitrate, tq, prop_seg, phase_seg1, phase_seg2, sjw, brp, real, sp) in table {
+            let bt = CanBitTiming::calculate(bitrate, None, btc, clock).unwrap();
+            assert_eq!(
+                (tq, prop_seg, phase_seg1, phase_seg2, sjw, brp, real, sp),
+                (
+                    bt.tq,
+                    bt.prop_seg,
+                    bt.phase_seg1,
+                    bt.phase_seg2,
+                    bt.sjw,
+                    bt.brp,
+                    bt.bitrate,
+                    bt.sample_point
+                ),
+                "bitrate {} @ {} Hz",
+                bitrate,
+                clock
+            );
+        }
+    }
+
+    #[test]
+    fn test_sja1000() {
+        // The reference table from can-calc-bit-timing for an SJA1000
+        // with an 8 MHz clock
+        let btc = bittiming_const((1, 16), (1, 8), 4, (1, 64, 1));
+        check(
+            &btc,
+            8_000_000,
+            &[
+                (1_000_000, 125, 2, 3, 2, 1, 1, 1_000_000, 750),
+                (800_000, 125, 3, 4, 2, 1, 1, 800_000, 800),
+                (500_000, 125, 6, 7, 2, 1, 1, 500_000, 875),
+                (250_000, 250, 6, 7, 2, 1, 2, 250_000, 875),
+                (125_000, 500, 6, 7, 2, 1, 4, 125_000, 875),
+                (100_000, 625, 6, 7, 2, 1, 5, 100_000, 875),
+                (50_000, 1250, 6, 7, 2, 1, 10, 50_000, 875),
+                (20_000, 3125, 6, 7, 2, 1, 25, 20_000, 875),
+                (10_000, 6250, 6, 7, 2, 1, 50, 10_000, 875),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_sample_point() {
+        let btc = bittiming_const((1, 16), (1, 8), 4, (1, 64, 1));
+
+        let bt = CanBitTiming::calculate(500_000, 750, &btc, 8_000_000).unwrap();
+        assert_eq!(750, bt.sample_point);
+        assert_eq!(
+            (1, 5, 6, 4, 2),
+            (bt.brp, bt.prop_seg, bt.phase_seg1, bt.phase_seg2, bt.sjw)
+        );
+
+        // The sample point never goes past the nominal one
+        let bt = CanBitTiming::calculate(1_000_000, 800, &btc, 8_000_000).unwrap();
+        assert!(bt.sample_point <= 800);
+    }
+
+    #[test]
+    fn test_errors() {
+        let btc = bittiming_const((1, 16), (1, 8), 4, (1, 64, 1));
+
+        assert_eq!(
+            CanBitTimingError::InvalidParams,
+            CanBitTiming::calculate(0, None, &btc, 8_000_000).unwrap_err()
+        );
+        assert_eq!(
+            CanBitTimingError::InvalidParams,
+            CanBitTiming::calculate(500_000, 1000, &btc, 8_000_000).unwrap_err()
+        );
+
+        // Too fast for the clock
+        assert!(matches!(
+            CanBitTiming::calculate(5_000_000, None, &btc, 8_000_000),
+            Err(CanBitTimingError::BitrateError(_))
+        ));
+
+        // Constants with a minimum past the maximum, or so large that the
+        // calculations would overflow
+        for btc in [
+            bittiming_const((1, 16), (8, 1), 4, (1, 64, 1)),
+            bittiming_const((16, 1), (1, 8), 4, (1, 64, 1)),
+            bittiming_const((1, 16), (1, 8), 4, (64, 1, 1)),
+            bittiming_const((1, 16), (1, 8), 4, (1, 64, 0)),
+            bittiming_const((1, u32::MAX), (1, 8), 4, (1, 64, 1)),
+            bittiming_const((1, u32::MAX / 2), (1, u32::MAX / 2), 4, (1, 64, 1)),
+        ] {
+            assert_eq!(
+                CanBitTimingError::InvalidParams,
+                CanBitTiming::calculate(500_000, None, &btc, 8_000_000).unwrap_err()
+            );
+        }
+
+        // A bitrate that's a little off is OK
+        let bt = CanBitTiming::calculate(33_333, None, &btc, 8_000_000).unwrap();
+        assert!(bt.bitrate.abs_diff(33_333) * 1000 / 33_333 <= CAN_CALC_MAX_ERROR);
+    }
+
+    #[test]
+    fn test_for_kernel() {
+        let btc = bittiming_const((1, 16), (1, 8), 4, (1, 64, 1));
+
+        // A calculated timing is sent as its time quantum and segments
+        let bt = CanBitTiming::calculate(500_000, None, &btc, 8_000_000).unwrap();
+        let kbt = bt.for_kernel();
+        assert_eq!((0, 0), (kbt.bitrate, kbt.sample_point));
+        assert_eq!(
+            (
+                bt.tq,
+                bt.prop_seg,
+                bt.phase_seg1,
+                bt.phase_seg2,
+                bt.sjw,
+                bt.brp
+            ),
+            (
+                kbt.tq,
+                kbt.prop_seg,
+                kbt.phase_seg1,
+                kbt.phase_seg2,
+                kbt.sjw,
+                kbt.brp
+            )
+        );
+
+        // A bitrate for the kernel to calculate is sent as it is
+        let bt = CanBitTiming {
+            bitrate: 250_000,
+            sample_point: 800,
+            ..CanBitTiming::default()
+        };
+        let kbt = bt.for_kernel();
+        assert_eq!((250_000, 800), (kbt.bitrate, kbt.sample_point));
+    }
+}
@@ -0,0 +1,906 @@
+// socketcan/src/nl/gw.rs
+//
+// Netlink management of the kernel CAN gateway.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Management of the kernel CAN gateway rules over netlink.
+//!
+//! The kernel CAN gateway (`can-gw`) routes frames from one CAN interface
+//! to another, inside the kernel, optionally filtering and modifying them
+//! on the way. It's usually configured with the `cangw` tool from
+//! can-utils. This does the same with the `RTM_NEWROUTE`, `RTM_DELROUTE`,
+//! and `RTM_GETROUTE` messages for the `PF_CAN` family.
+//!
+//! The kernel code is here:
+//!
+//! <https://github.com/torvalds/linux/blob/master/net/can/gw.c>
+//!
+//! ```no_run
+//! use socketcan::{
+//!     nl::gw::{self, GwMod, GwModOp, GwRule},
+//!     CanFilter,
+//! };
+//!
+//! // Route frames with ID 0x100 from can0 to can1, as ID 0x200
+//! let rule = GwRule::from_names("can0", "can1")
+//!     .unwrap()
+//!     .filter(CanFilter::new(0x100, 0x7FF))
+//!     .modification(GwMod::new(GwModOp::Set).id(0x200));
+//! rule.create().unwrap();
+//!
+//! for info in gw::list().unwrap() {
+//!     println!("{:?}: {} frames", info.rule, info.stats.handled);
+//! }
+//!
+//! gw::flush().unwrap();
+//! ```
+
+use super::{rt::*, CanInterface, NlResult};
+use crate::{
+    frame::{CANFD_MAX_DLEN, CAN_MAX_DLEN},
+    CanFilter,
+};
+use neli::{
+    consts::{
+        nl::{NlmF, NlmFFlags},
+        rtnl::Rtm,
+    },
+    err::{DeError, NlError},
+    nl::{NlPayload, Nlmsghdr},
+    rtnl::Rtattr,
+    types::{Buffer, RtBuffer},
+    FromBytesWithInput, Header, Size, ToBytes,
+};
+use nix::net::if_::if_nametoindex;
+use std::{io::Cursor, os::raw::c_uint};
+
+/// A Netlink error from a gateway query
+pub type GwError = NlError<Rtm, Buffer>;
+
+/// The header of a CAN gateway netlink message, followed by its attributes.
+///
+/// This is the kernel's `struct rtcanmsg`.
+#[derive(Debug, Size, ToBytes, FromBytesWithInput, Header)]
+pub struct Rtcanmsg {
+    /// The address family, always `AF_CAN`
+    pub can_family: u8,
+    /// The type of gateway, always CAN to CAN
+    pub gwtype: u8,
+    /// The `CGW_FLAGS_CAN_*` flags for the rule
+    pub flags: u16,
+    /// The attributes of the rule
+    #[neli(input = "input.checked_sub(Self::header_size()).ok_or(DeError::UnexpectedEOB)?")]
+    pub attrs: RtBuffer<Cgw, Buffer>,
+}
+
+impl Rtcanmsg {
+    /// Creates a CAN to CAN gateway message.
+    fn new(flags: u16, attrs: RtBuffer<Cgw, Buffer>) -> Self {
+        Self {
+            can_family: libc::AF_CAN as u8,
+            gwtype: CGW_TYPE_CAN_CAN,
+            flags,
+            attrs,
+        }
+    }
+}
+
+// ===== GwMod =====
+
+/// The operation that a gateway modification applies to a frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GwModOp {
+    /// Bitwise AND of the values into the frame
+    And,
+    /// Bitwise OR of the values into the frame
+    Or,
+    /// Bitwise XOR of the values into the frame
+    Xor,
+    /// Replace the frame values
+    Set,
+}
+
+impl GwModOp {
+    /// All of the operations, in the order the kernel applies them
+    const ALL: [GwModOp; 4] = [Self::And, Self::Or, Self::Xor, Self::Set];
+
+    /// Gets the netlink attribute for the operation, for classic or FD
+    /// frames.
+    fn attr(&self, fd: bool) -> Cgw {
+        use GwModOp::*;
+        match (self, fd) {
+            (And, false) => Cgw::ModAnd,
+            (Or, false) => Cgw::ModOr,
+            (Xor, false) => Cgw::ModXor,
+            (Set, false) => Cgw::ModSet,
+            (And, true) => Cgw::FdModAnd,
+            (Or, true) => Cgw::FdModOr,
+            (Xor, true) => Cgw::FdModXor,
+            (Set, true) => Cgw::FdModSet,
+        }
+    }
+
+    /// Gets the operation for a netlink attribute, and whether it's for
+    /// FD frames.
+    fn from_attr(attr: &Cgw) -> Option<(Self, bool)> {
+        use GwModOp::*;
+        match attr {
+            Cgw::ModAnd => Some((And, false)),
+            Cgw::ModOr => Some((Or, false)),
+            Cgw::ModXor => Some((Xor, false)),
+            Cgw::ModSet => Some((Set, false)),
+            Cgw::FdModAnd => Some((And, true)),
+            Cgw::FdModOr => Some((Or, true)),
+            Cgw::FdModXor => Some((Xor, true)),
+            Cgw::FdModSet => Some((Set, true)),
+            _ => None,
+        }
+    }
+}
+
+/// A modification of the frames passing through a gateway rule.
+///
+/// Each of the fields that is set is combined with the frame using the
+/// operation. The kernel applies all of the AND modifications first, then
+/// OR, XOR, and SET.
+///
+/// The data is padded out to the full length of the frame with zeros, or
+/// with ones for an AND, so that any bytes past the end are unchanged by
+/// the bitwise operations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GwMod {
+    /// The operation
+    pub op: GwModOp,
+    /// The CAN ID, including any of the EFF/RTR/ERR flag bits
+    pub id: Option<u32>,
+    /// The data length code of a classic frame, or the length of an FD one
+    pub len: Option<u8>,
+    /// The data
+    pub data: Option<Vec<u8>>,
+    /// The flags of an FD frame. This is only for FD rules.
+    pub flags: Option<u8>,
+}
+
+impl GwMod {
+    /// Creates a modification that doesn't change anything yet.
+    pub fn new(op: GwModOp) -> Self {
+        Self {
+            op,
+            id: None,
+            len: None,
+            data: None,
+            flags: None,
+        }
+    }
+
+    /// Modifies the CAN ID.
+    pub fn id(mut self, id: u32) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Modifies the data length code, or the length of an FD frame.
+    pub fn len(mut self, len: u8) -> Self {
+        self.len = Some(len);
+        self
+    }
+
+    /// Modifies the data.
+    pub fn data(mut self, data: &[u8]) -> Self {
+        self.data = Some(data.to_vec());
+        self
+    }
+
+    /// Modifies the flags of an FD frame.
+    pub fn flags(mut self, flags: u8) -> Self {
+        self.flags = Some(flags);
+        self
+    }
+
+    /// Merges the modifications for one operation into the kernel's
+    /// `struct cgw_frame_mod` or `struct cgw_fdframe_mod`.
+    ///
+    /// Returns `None` if there aren't any for the operation.
+    fn encode<'a, I>(op: GwModOp, mods: I, fd: bool) -> NlResult<Option<Vec<u8>>>
+    where
+        I: IntoIterator<Item = &'a GwMod>,
+    {
+        let (max_dlen, len) = if fd {
+            (CANFD_MAX_DLEN, CGW_FDMODATTR_LEN)
+        } else {
+            (CAN_MAX_DLEN, CGW_MODATTR_LEN)
+        };
+
+        let pad = if op == GwModOp::And { 0xFF } else { 0 };
+        let mut buf = vec![0u8; len];
+        buf[8..8 + max_dlen].fill(pad);
+        let mut modtype = 0;
+
+        for m in mods.into_iter().filter(|m| m.op == op) {
+            if let Some(id) = m.id {
+                buf[0..4].copy_from_slice(&id.to_ne_bytes());
+                modtype |= CGW_MOD_ID;
+            }
+            if let Some(n) = m.len {
+                buf[4] = n;
+                modtype |= CGW_MOD_DLC;
+            }
+            if let Some(flags) = m.flags {
+                if !fd {
+                    return Err(NlError::Msg(
+                        "Flags can only be modified in FD rules".into(),
+                    ));
+                }
+                buf[5] = flags;
+                modtype |= CGW_MOD_FLAGS;
+            }
+            if let Some(data) = &m.data {
+                if data.len() > max_dlen {
+                    return Err(NlError::Msg(format!(
+                        "Modification data is longer than {} bytes",
+                        max_dlen
+                    )));
+                }
+                buf[8..8 + max_dlen].fill(pad);
+                buf[8..8 + data.len()].copy_from_slice(data);
+                modtype |= CGW_MOD_DATA;
+            }
+        }
+
+        if modtype == 0 {
+            return Ok(None);
+        }
+        buf[len - 1] = modtype;
+        Ok(Some(buf))
+    }
+
+    /// Parses a modification from the kernel's `struct cgw_frame_mod` or
+    /// `struct cgw_fdframe_mod`.
+    fn decode(op: GwModOp, buf: &[u8], fd: bool) -> Option<Self> {
+        let (max_dlen, len) = if fd {
+            (CANFD_MAX_DLEN, CGW_FDMODATTR_LEN)
+        } else {
+            (CAN_MAX_DLEN, CGW_MODATTR_LEN)
+        };
+        if buf.len() < len {
+            return None;
+        }
+
+        let modtype = buf[len - 1];
+        let mut m = Self::new(op);
+        if modtype & CGW_MOD_ID != 0 {
+            m.id = Some(u32::from_ne_bytes(buf[0..4].try_into().unwrap()));
+        }
+        if modtype & CGW_MOD_DLC != 0 {
+            m.len = Some(buf[4]);
+        }
+        if fd && modtype & CGW_MOD_FLAGS != 0 {
+            m.flags = Some(buf[5]);
+        }
+        if modtype & CGW_MOD_DATA != 0 {
+            m.data = Some(buf[8..8 + max_dlen].to_vec());
+        }
+        Some(m)
+    }
+}
+
+// ===== Checksums =====
+
+/// An XOR checksum, calculated over the data of the frames after they are
+/// modified, and written into the data.
+///
+/// The indexes are into the frame data. Negative ones count back from the
+/// end of the received data, so -1 is the last byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GwXorChecksum {
+    /// The index of the first byte of the checksum
+    pub from_idx: i8,
+    /// The index of the last byte of the checksum
+    pub to_idx: i8,
+    /// The index where the checksum is written
+    pub result_idx: i8,
+    /// The initial value of the checksum
+    pub init: u8,
+}
+
+impl GwXorChecksum {
+    /// Creates an XOR checksum over the bytes from `from_idx` to `to_idx`,
+    /// inclusive, written at `result_idx`.
+    pub fn new(from_idx: i8, to_idx: i8, result_idx: i8) -> Self {
+        Self {
+            from_idx,
+            to_idx,
+            result_idx,
+            init: 0,
+        }
+    }
+
+    /// Sets the initial value of the checksum.
+    pub fn init(mut self, init: u8) -> Self {
+        self.init = init;
+        self
+    }
+
+    /// Creates the kernel's `struct cgw_csum_xor`.
+    fn encode(&self) -> [u8; CGW_CS_XOR_LEN] {
+        [
+            self.from_idx as u8,
+            self.to_idx as u8,
+            self.result_idx as u8,
+            self.init,
+        ]
+    }
+
+    /// Parses the kernel's `struct cgw_csum_xor`.
+    fn decode(buf: &[u8]) -> Option<Self> {
+        match *buf {
+            [from, to, result, init, ..] => Some(Self {
+                from_idx: from as i8,
+                to_idx: to as i8,
+                result_idx: result as i8,
+                init,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Additional values that are added to a CRC8 checksum, after the data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GwCrc8Profile {
+    /// No additional values
+    None,
+    /// A single value
+    OneU8(u8),
+    /// A value from a table, indexed by the low nibble of `data[1]`
+    SixteenU8([u8; 16]),
+    /// The low two bytes of the CAN ID, XOR'ed together
+    SffIdXor,
+}
+
+/// A CRC8 checksum, calculated over the data of the frames after they are
+/// modified, and written into the data.
+///
+/// The indexes are into the frame data. Negative ones count back from the
+/// end of the received data, so -1 is the last byte.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GwCrc8Checksum {
+    /// The index of the first byte of the checksum
+    pub from_idx: i8,
+    /// The index of the last byte of the checksum
+    pub to_idx: i8,
+    /// The index where the checksum is written
+    pub result_idx: i8,
+    /// The initial value of the checksum
+    pub init: u8,
+    /// The value XOR'ed into the final checksum
+    pub final_xor: u8,
+    /// The lookup table for the CRC polynomial
+    pub table: [u8; 256],
+    /// Additional values added to the checksum
+    pub profile: GwCrc8Profile,
+}
+
+impl GwCrc8Checksum {
+    /// Creates a CRC8 checksum over the bytes from `from_idx` to `to_idx`,
+    /// inclusive, written at `result_idx`, using the polynomial, `poly`.
+    ///
+    /// For example, SAE J1850 uses the polynomial 0x1D, with an initial
+    /// value and final XOR of 0xFF.
+    pub fn new(from_idx: i8, to_idx: i8, result_idx: i8, poly: u8) -> Self {
+        Self {
+            from_idx,
+            to_idx,
+            result_idx,
+            init: 0,
+            final_xor: 0,
+            table: Self::table(poly),
+            profile: GwCrc8Profile::None,
+        }
+    }
+
+    /// Sets the initial value of the checksum.
+    pub fn init(mut self, init: u8) -> Self {
+        self.init = init;
+        self
+    }
+
+    /// Sets the value XOR'ed into the final checksum.
+    pub fn final_xor(mut self, final_xor: u8) -> Self {
+        self.final_xor = final_xor;
+        self
+    }
+
+    /// Sets the additional values added to the checksum.
+    pub fn profile(mut self, profile: GwCrc8Profile) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Creates the lookup table for a CRC8 polynomial, MSB first, as the
+    /// kernel uses it: `crc = table[crc ^ byte]`.
+    pub fn table(poly: u8) -> [u8; 256] {
+        let mut table = [0u8; 256];
+        for (i, entry) in table.iter_mut().enumerate() {
+            let mut crc = i as u8;
+            for _ in 0..8 {
+                crc = if crc & 0x80 != 0 {
+                    (crc << 1) ^ poly
+                } else {
+                    crc << 1
+                };
+            }
+            *entry = crc;
+        }
+        table
+    }
+
+    /// Creates the kernel's `struct cgw_csum_crc8`.
+    fn encode(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(CGW_CS_CRC8_LEN);
+        buf.extend_from_slice(&[
+            self.from_idx as u8,
+            self.to_idx as u8,
+            self.result_idx as u8,
+            self.init,
+            self.final_xor,
+        ]);
+        buf.extend_from_slice(&self.table);
+
+        let mut profile_data = [0u8; 20];
+        let profile = match self.profile {
+            GwCrc8Profile::None => CGW_CRC8PRF_UNSPEC,
+            GwCrc8Profile::OneU8(val) => {
+                profile_data[0] = val;
+                CGW_CRC8PRF_1U8
+            }
+            GwCrc8Profile::SixteenU8(vals) => {
+                profile_data[..16].copy_from_slice(&vals);
+                CGW_CRC8PRF_16U8
+            }
+            GwCrc8Profile::SffIdXor => CGW_CRC8PRF_SFFID_XOR,
+        };
+        buf.push(profile);
+        buf.extend_from_slice(&profile_data);
+        buf
+    }
+
+    /// Parses the kernel's `struct cgw_csum_crc8`.
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < CGW_CS_CRC8_LEN {
+            return None;
+        }
+        let profile_data = &buf[262..282];
+        let profile = match buf[261] {
+            CGW_CRC8PRF_1U8 => GwCrc8Profile::OneU8(profile_data[0]),
+            CGW_CRC8PRF_16U8 => GwCrc8Profile::SixteenU8(profile_data[..16].try_into().unwrap()),
+            CGW_CRC8PRF_SFFID_XOR => GwCrc8Profile::SffIdXor,
+            _ => GwCrc8Profile::None,
+        };
+        Some(Self {
+            from_idx: buf[0] as i8,
+            to_idx: buf[1] as i8,
+            result_idx: buf[2] as i8,
+            init: buf[3],
+            final_xor: buf[4],
+            table: buf[5..261].try_into().unwrap(),
+            profile,
+        })
+    }
+}
+
+// ===== GwRule =====
+
+/// A kernel CAN gateway rule, to route frames from one CAN interface to
+/// another.
+///
+/// To delete a rule, all of its settings need to match the ones it was
+/// created with, unless it has a modification ID.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GwRule {
+    /// The index of the interface that frames are received on
+    pub src_if: c_uint,
+    /// The index of the interface that frames are sent out on
+    pub dst_if: c_uint,
+    /// Echo the routed frames back to the sockets on the destination
+    /// interface, like they were sent locally
+    pub echo: bool,
+    /// Keep the timestamp of the received frames
+    pub src_timestamp: bool,
+    /// Allow routing frames back out of the interface they came in on
+    pub iif_tx_ok: bool,
+    /// Route CAN FD frames instead of classic ones
+    pub fd: bool,
+    /// Only route the frames that match the filter
+    pub filter: Option<CanFilter>,
+    /// The modifications made to the routed frames
+    pub mods: Vec<GwMod>,
+    /// An XOR checksum written into the routed frames
+    pub xor_checksum: Option<GwXorChecksum>,
+    /// A CRC8 checksum written into the routed frames
+    pub crc8_checksum: Option<GwCrc8Checksum>,
+    /// The number of gateway hops a frame can take before being dropped,
+    /// if limited more than the kernel's default
+    pub hop_limit: Option<u8>,
+    /// A user-defined ID for the modifications. Creating a rule with the
+    /// same ID, interfaces, and flags as an existing one updates its
+    /// modifications in place.
+    pub mod_uid: Option<u32>,
+}
+
+impl GwRule {
+    /// Creates a rule to route all frames from the source interface to
+    /// the destination one, by interface index.
+    pub fn new(src_if: c_uint, dst_if: c_uint) -> Self {
+        Self {
+            src_if,
+            dst_if,
+            ..Self::default()
+        }
+    }
+
+    /// Creates a rule to route all frames from the source interface to
+    /// the destination one, by interface name.
+    pub fn from_names(src: &str, dst: &str) -> Result<Self, nix::Error> {
+        Ok(Self::new(if_nametoindex(src)?, if_nametoindex(dst)?))
+    }
+
+    /// Echos the routed frames back to the sockets on the destination
+    /// interface.
+    pub fn echo(mut self) -> Self {
+        self.echo = true;
+        self
+    }
+
+    /// Keeps the timestamp of the received frames.
+    pub fn src_timestamp(mut self) -> Self {
+        self.src_timestamp = true;
+        self
+    }
+
+    /// Allows routing frames back out of the interface they came in on.
+    pub fn iif_tx_ok(mut self) -> Self {
+        self.iif_tx_ok = true;
+        self
+    }
+
+    /// Routes CAN FD frames instead of classic ones.
+    pub fn fd(mut self) -> Self {
+        self.fd = true;
+        self
+    }
+
+    /// Only routes the frames that match the filter.
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: Into<CanFilter>,
+    {
+        self.filter = Some(filter.into());
+        self
+    }
+
+    /// Adds a modification of the routed frames.
+    pub fn modification(mut self, m: GwMod) -> Self {
+        self.mods.push(m);
+        self
+    }
+
+    /// Writes an XOR checksum into the routed frames.
+    pub fn xor_checksum(mut self, cs: GwXorChecksum) -> Self {
+        self.xor_checksum = Some(cs);
+        self
+    }
+
+    /// Writes a CRC8 checksum into the routed frames.
+    pub fn crc8_checksum(mut self, cs: GwCrc8Checksum) -> Self {
+        self.crc8_checksum = Some(cs);
+        self
+    }
+
+    /// Limits the number of gateway hops a frame can take.
+    pub fn hop_limit(mut self, hops: u8) -> Self {
+        self.hop_limit = Some(hops);
+        self
+    }
+
+    /// Sets a user-defined ID for the modifications, so they can be
+    /// updated later.
+    pub fn mod_uid(mut self, uid: u32) -> Self {
+        self.mod_uid = Some(uid);
+        self
+    }
+
+    /// Creates the rule in the kernel.
+    ///
+    /// If the rule has a modification ID that matches an existing rule,
+    /// with the same interfaces and flags, that rule's modifications are
+    /// updated instead.
+    ///
+    /// PRIVILEGED: This requires root privilege.
+    ///
+    pub fn create(&self) -> NlResult<()> {
+        send_msg(Rtm::Newroute, self.to_msg()?)
+    }
+
+    /// Deletes the rule from the kernel.
+    ///
+    /// PRIVILEGED: This requires root privilege.
+    ///
+    pub fn delete(&self) -> NlResult<()> {
+        send_msg(Rtm::Delroute, self.to_msg()?)
+    }
+
+    /// Gets the `CGW_FLAGS_CAN_*` flags for the rule.
+    fn flags(&self) -> u16 {
+        let mut flags = 0;
+        for (on, flag) in [
+            (self.echo, CGW_FLAGS_CAN_ECHO),
+            (self.src_timestamp, CGW_FLAGS_CAN_SRC_TSTAMP),
+            (self.iif_tx_ok, CGW_FLAGS_CAN_IIF_TX_OK),
+            (self.fd, CGW_FLAGS_CAN_FD),
+        ] {
+            if on {
+                flags |= flag;
+            }
+        }
+        flags
+    }
+
+    /// Creates the netlink message for the rule.
+    fn to_msg(&self) -> NlResult<Rtcanmsg> {
+        let mut attrs = RtBuffer::new();
+
+        for op in GwModOp::ALL {
+            if let Some(buf) = GwMod::encode(op, &self.mods, self.fd)? {
+                attrs.push(Rtattr::new(None, op.attr(self.fd), &buf[..])?);
+            }
+        }
+        if let Some(cs) = &self.xor_checksum {
+            attrs.push(Rtattr::new(None, Cgw::CsXor, &cs.encode()[..])?);
+        }
+        if let Some(cs) = &self.crc8_checksum {
+            attrs.push(Rtattr::new(None, Cgw::CsCrc8, &cs.encode()[..])?);
+        }
+        if let Some(uid) = self.mod_uid {
+            attrs.push(Rtattr::new(None, Cgw::ModUid, uid)?);
+        }
+        if let Some(hops) = self.hop_limit {
+            attrs.push(Rtattr::new(None, Cgw::LimHops, hops)?);
+        }
+        if let Some(filter) = &self.filter {
+            let filter = filter.as_ref();
+            let mut buf = filter.can_id.to_ne_bytes().to_vec();
+            buf.extend_from_slice(&filter.can_mask.to_ne_bytes());
+            attrs.push(Rtattr::new(None, Cgw::Filter, &buf[..])?);
+        }
+        attrs.push(Rtattr::new(None, Cgw::SrcIf, self.src_if)?);
+        attrs.push(Rtattr::new(None, Cgw::DstIf, self.dst_if)?);
+
+        Ok(Rtcanmsg::new(self.flags(), attrs))
+    }
+}
+
+impl From<&Rtcanmsg> for GwRuleInfo {
+    /// Parses a rule, and its statistics, from a gateway message.
+    fn from(msg: &Rtcanmsg) -> Self {
+        let mut rule = GwRule {
+            echo: msg.flags & CGW_FLAGS_CAN_ECHO != 0,
+            src_timestamp: msg.flags & CGW_FLAGS_CAN_SRC_TSTAMP != 0,
+            iif_tx_ok: msg.flags & CGW_FLAGS_CAN_IIF_TX_OK != 0,
+            fd: msg.flags & CGW_FLAGS_CAN_FD != 0,
+            ..GwRule::default()
+        };
+        let mut stats = GwStats::default();
+
+        let u32_val = |buf: &[u8]| {
+            buf.get(0..4)
+                .map(|b| u32::from_ne_bytes(b.try_into().unwrap()))
+                .unwrap_or_default()
+        };
+
+        for attr in msg.attrs.iter() {
+            let buf = attr.rta_payload.as_ref();
+            match attr.rta_type {
+                Cgw::SrcIf => rule.src_if = u32_val(buf),
+                Cgw::DstIf => rule.dst_if = u32_val(buf),
+                Cgw::Handled => stats.handled = u32_val(buf),
+                Cgw::Dropped => stats.dropped = u32_val(buf),
+                Cgw::Deleted => stats.deleted = u32_val(buf),
+                Cgw::ModUid => rule.mod_uid = Some(u32_val(buf)),
+                Cgw::LimHops => rule.hop_limit = buf.first().copied(),
+                Cgw::Filter if buf.len() >= 8 => {
+                    rule.filter = Some(CanFilter::new(u32_val(buf), u32_val(&buf[4..])));
+                }
+                Cgw::CsXor => rule.xor_checksum = GwXorChecksum::decode(buf),
+                Cgw::CsCrc8 => rule.crc8_checksum = GwCrc8Checksum::decode(buf),
+                ref ty => {
+                    if let Some(m) =
+                        GwModOp::from_attr(ty).and_then(|(op, fd)| GwMod::decode(op, buf, fd))
+                    {
+                        rule.mods.push(m);
+                    }
+                }
+            }
+        }
+
+        Self { rule, stats }
+    }
+}
+
+/// The counts of the frames handled by a gateway rule.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct GwStats {
+    /// The number of frames routed
+    pub handled: u32,
+    /// The number of frames that couldn't be sent on the destination
+    pub dropped: u32,
+    /// The number of frames dropped for going over the hop limit
+    pub deleted: u32,
+}
+
+/// A gateway rule, as read back from the kernel, with its statistics.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GwRuleInfo {
+    /// The rule
+    pub rule: GwRule,
+    /// The counts of frames handled by the rule
+    pub stats: GwStats,
+}
+
+// ===== Module functions =====
+
+/// Gets all of the CAN gateway rules from the kernel, with their
+/// statistics.
+pub fn list() -> Result<Vec<GwRuleInfo>, GwError> {
+    let mut sock = CanInterface::open_route_socket()?;
+
+    let hdr = Nlmsghdr::new(
+        None,
+        Rtm::Getroute,
+        NlmFFlags::new(&[NlmF::Request, NlmF::Dump]),
+        None,
+        None,
+        NlPayload::Payload(Rtcanmsg::new(0, RtBuffer::new())),
+    );
+    sock.send(hdr)?;
+
+    let mut rules = Vec::new();
+    for msg in sock.iter::<Rtm, Buffer>(false) {
+        // The final "done" message has no payload
+        if let Ok(payload) = msg?.get_payload() {
+            // If the gateway isn't loaded, the kernel dumps the routes for
+            // all of the other families instead
+            let buf = payload.as_ref();
+            if buf.first() != Some(&(libc::AF_CAN as u8)) {
+                continue;
+            }
+            let msg = Rtcanmsg::from_bytes_with_input(&mut Cursor::new(buf), buf.len())?;
+            rules.push(GwRuleInfo::from(&msg));
+        }
+    }
+    Ok(rules)
+}
+
+/// Deletes all of the CAN gateway rules.
+///
+/// PRIVILEGED: This requires root privilege.
+///
+pub fn flush() -> NlResult<()> {
+    // The kernel deletes everything for a rule with both interfaces zero
+    GwRule::default().delete()
+}
+
+/// Sends a gateway message to the kernel, and waits for the acknowledgment.
+fn send_msg(msg_type: Rtm, msg: Rtcanmsg) -> NlResult<()> {
+    let mut sock = CanInterface::open_route_socket()?;
+
+    let hdr = Nlmsghdr::new(
+        None,
+        msg_type,
+        NlmFFlags::new(&[NlmF::Request, NlmF::Ack]),
+        None,
+        None,
+        NlPayload::Payload(msg),
+    );
+    CanInterface::send_and_read_ack(&mut sock, hdr)
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Sends the rule through the netlink (de)serialization, and back.
+    fn round_trip(rule: &GwRule) -> GwRule {
+        let msg = rule.to_msg().unwrap();
+        let mut buf = Cursor::new(Vec::new());
+        msg.to_bytes(&mut buf).unwrap();
+
+        let bytes = buf.into_inner();
+        let len = bytes.len();
+        let msg = Rtcanmsg::from_bytes_with_input(&mut Cursor::new(&bytes[..]), len).unwrap();
+        GwRuleInfo::from(&msg).rule
+    }
+
+    #[test]
+    fn test_rule_round_trip() {
+        let rule = GwRule::new(3, 4)
+            .echo()
+            .filter(CanFilter::new(0x100, 0x7FF))
+            .modification(GwMod::new(GwModOp::Xor).data(&[0xFF; 8]))
+            .modification(GwMod::new(GwModOp::Set).id(0x200).len(8))
+            .xor_checksum(GwXorChecksum::new(0, 6, 7).init(0x55))
+            .hop_limit(2)
+            .mod_uid(42);
+
+        let msg = rule.to_msg().unwrap();
+        assert_eq!(libc::AF_CAN as u8, msg.can_family);
+        assert_eq!(CGW_TYPE_CAN_CAN, msg.gwtype);
+        assert_eq!(CGW_FLAGS_CAN_ECHO, msg.flags);
+
+        assert_eq!(rule, round_trip(&rule));
+
+        // Flushing is a delete with both interfaces zero
+        let msg = GwRule::default().to_msg().unwrap();
+        assert_eq!(2, msg.attrs.len());
+    }
+
+    #[test]
+    fn test_fd_mods() {
+        let rule = GwRule::new(1, 2)
+            .fd()
+            .modification(GwMod::new(GwModOp::Or).flags(0x01))
+            .modification(GwMod::new(GwModOp::And).data(&[0x0F]));
+
+        let parsed = round_trip(&rule);
+        assert!(parsed.fd);
+        assert_eq!(2, parsed.mods.len());
+
+        // The kernel order is AND, then OR
+        let and = &parsed.mods[0];
+        assert_eq!(GwModOp::And, and.op);
+        let data = and.data.as_ref().unwrap();
+        assert_eq!(CANFD_MAX_DLEN, data.len());
+        assert_eq!(0x0F, data[0]);
+        assert!(data[1..].iter().all(|&b| b == 0xFF));
+
+        assert_eq!(GwMod::new(GwModOp::Or).flags(0x01), parsed.mods[1]);
+
+        // Only FD rules can change the flags
+        let rule = GwRule::new(1, 2).modification(GwMod::new(GwModOp::Set).flags(1));
+        assert!(rule.to_msg().is_err());
+
+        // ...and data can't be longer than the frame
+        let rule = GwRule::new(1, 2).modification(GwMod::new(GwModOp::Set).data(&[0; 9]));
+        assert!(rule.to_msg().is_err());
+    }
+
+    #[test]
+    fn test_crc8() {
+        // SAE J1850
+        let cs = GwCrc8Checksum::new(0, 6, 7, 0x1D)
+            .init(0xFF)
+            .final_xor(0xFF)
+            .profile(GwCrc8Profile::OneU8(0x33));
+
+        // The kernel calculation, with the standard check value
+        let crc = b"123456789"
+            .iter()
+            .fold(cs.init, |crc, &b| cs.table[(crc ^ b) as usize]);
+        assert_eq!(0x4B, crc ^ cs.final_xor);
+
+        let buf = cs.encode();
+        assert_eq!(CGW_CS_CRC8_LEN, buf.len());
+        assert_eq!(Some(cs.clone()), GwCrc8Checksum::decode(&buf));
+
+        let rule = GwRule::new(1, 2).crc8_checksum(cs);
+        assert_eq!(rule, round_trip(&rule));
+    }
+}
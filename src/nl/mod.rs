@@ -67,25 +67,40 @@ use neli::{
     types::{Buffer, RtBuffer},
     FromBytes, ToBytes,
 };
-use nix::{self, net::if_::if_nametoindex, unistd};
-use rt::IflaCan;
+use nix::{self, net::if_::if_nametoindex};
+use rt::{IflaCan, IflaCanCtrlModeExt, VxcanInfo, NLA_TYPE_MASK};
 use std::{
     ffi::CStr,
     fmt::Debug,
-    os::raw::{c_int, c_uint},
+    os::{
+        raw::{c_int, c_uint},
+        unix::io::RawFd,
+    },
 };
 
 /// Low-level Netlink CAN struct bindings.
-mod rt;
+pub(crate) mod rt;
+
+pub mod gw;
+
+mod bittiming;
+pub use bittiming::CanBitTimingError;
+
+mod tdc;
+use tdc::parse_tdc;
+pub use tdc::{CanTdc, CanTdcConst};
+
+mod monitor;
+pub use monitor::{LinkEvent, LinkEventKind, LinkMonitor};
 
 use rt::can_ctrlmode;
 pub use rt::CanState;
 
 /// A result for Netlink errors.
-type NlResult<T> = Result<T, NlError>;
+pub(crate) type NlResult<T> = Result<T, NlError>;
 
 /// A Netlink error from an info query
-type NlInfoError = NlError<Rtm, Ifinfomsg>;
+pub(crate) type NlInfoError = NlError<Rtm, Ifinfomsg>;
 
 /// CAN bit-timing parameters
 pub type CanBitTiming = rt::can_bittiming;
@@ -95,16 +110,23 @@ pub type CanBitTimingConst = rt::can_bittiming_const;
 pub type CanClock = rt::can_clock;
 /// CAN bus error counters
 pub type CanBerrCounter = rt::can_berr_counter;
+/// CAN device statistics, like bus errors and error state changes
+pub type CanDeviceStats = rt::can_device_stats;
+/// Network interface statistics, like the packets and bytes sent and received
+pub type LinkStats = rt::rtnl_link_stats64;
 
 /// The details of the interface which can be obtained with the
 /// `CanInterface::details()` function.
 #[allow(missing_copy_implementations)]
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InterfaceDetails {
     /// The name of the interface
     pub name: Option<String>,
     /// The index of the interface
     pub index: c_uint,
+    /// The kind of interface, like "can", "vcan", or "vxcan"
+    pub kind: Option<String>,
     /// Whether the interface is currently up
     pub is_up: bool,
     /// The MTU size of the interface (Standard or FD frames support)
@@ -123,10 +145,86 @@ impl InterfaceDetails {
     }
 }
 
+impl TryFrom<&Ifinfomsg> for InterfaceDetails {
+    type Error = NlInfoError;
+
+    /// Try to parse the interface details out of a link info message
+    fn try_from(payload: &Ifinfomsg) -> Result<Self, Self::Error> {
+        let mut info = Self::new(payload.ifi_index as c_uint);
+        info.is_up = payload.ifi_flags.contains(&Iff::Up);
+
+        for attr in payload.rtattrs.iter() {
+            match attr.rta_type {
+                Ifla::Ifname => {
+                    // Note: Use `CStr::from_bytes_until_nul` when MSRV >= 1.69
+                    info.name = CStr::from_bytes_with_nul(attr.rta_payload.as_ref())
+                        .map(|s| s.to_string_lossy().into_owned())
+                        .ok();
+                }
+                Ifla::Mtu => {
+                    info.mtu = attr
+                        .get_payload_as::<u32>()
+                        .ok()
+                        .and_then(|mtu| Mtu::try_from(mtu).ok());
+                }
+                Ifla::Linkinfo => {
+                    for link_info in attr.get_attr_handle::<IflaInfo>()?.get_attrs() {
+                        if link_info.rta_type == IflaInfo::Kind {
+                            info.kind = CStr::from_bytes_with_nul(link_info.rta_payload.as_ref())
+                                .map(|s| s.to_string_lossy().into_owned())
+                                .ok();
+                        }
+                    }
+                    info.can = InterfaceCanParams::try_from(attr)?;
+                }
+                _ => (),
+            }
+        }
+        Ok(info)
+    }
+}
+
+/// The messages to reconfigure an interface with a set of CAN parameters.
+///
+/// These are the steps of [`CanInterface::reconfigure()`] as plain data,
+/// so that the blocking and async interfaces only differ in how they send
+/// the messages.
+#[derive(Debug)]
+pub(crate) struct ReconfigureSteps {
+    /// The messages that make the change, sent in order until one fails
+    pub apply: Vec<Ifinfomsg>,
+    /// The messages that put the interface back the way it was, if one of
+    /// the changes fails. These are sent on a best effort basis, ignoring
+    /// any errors.
+    pub rollback: Vec<Ifinfomsg>,
+}
+
+impl ReconfigureSteps {
+    /// Sends the messages, one at a time, with a blocking `send`.
+    fn run<F>(self, mut send: F) -> NlResult<()>
+    where
+        F: FnMut(Ifinfomsg) -> NlResult<()>,
+    {
+        let res = self.apply.into_iter().try_for_each(&mut send);
+        if res.is_err() {
+            for info in self.rollback {
+                let _ = send(info);
+            }
+        }
+        res
+    }
+}
+
+/// Determines if a link info message is for a CAN interface.
+fn is_can_link(payload: &Ifinfomsg) -> bool {
+    u16::from(payload.ifi_type) == libc::ARPHRD_CAN
+}
+
 /// The MTU size for the interface
 ///
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Mtu {
     /// Standard CAN frame, 8-byte data (16-byte total)
     Standard = 16,
@@ -149,6 +247,7 @@ impl TryFrom<u32> for Mtu {
 /// The CAN-specific parameters for the interface.
 #[allow(missing_copy_implementations)]
 #[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InterfaceCanParams {
     /// The CAN bit timing parameters
     pub bit_timing: Option<CanBitTiming>,
@@ -165,12 +264,152 @@ pub struct InterfaceCanParams {
     pub berr_counter: Option<CanBerrCounter>,
     /// The control mode bits
     pub ctrl_mode: CanCtrlModes,
+    /// The control modes supported by the interface, as a mask of
+    /// [`CanCtrlMode::mask()`] bits
+    pub ctrl_mode_supported: Option<u32>,
     /// The FD data bit timing
     pub data_bit_timing: Option<CanBitTiming>,
     /// The FD data bit timing const parameters
     pub data_bit_timing_const: Option<CanBitTimingConst>,
     /// The CANbus termination resistance
     pub termination: u16,
+    /// The FD transmitter delay compensation, if it's enabled
+    pub tdc: Option<CanTdc>,
+    /// The FD transmitter delay compensation limits, if the interface
+    /// supports it
+    pub tdc_const: Option<CanTdcConst>,
+    /// The fixed bitrates supported by the interface, if it doesn't allow
+    /// arbitrary bit timings
+    pub bitrate_const: Vec<u32>,
+    /// The fixed FD data bitrates supported by the interface, if it doesn't
+    /// allow arbitrary data bit timings
+    pub data_bitrate_const: Vec<u32>,
+    /// The termination resistances supported by the interface, if it can
+    /// set the termination
+    pub termination_const: Vec<u16>,
+    /// The highest bitrate supported by the transceiver, if it's limited
+    pub bitrate_max: Option<u32>,
+}
+
+impl InterfaceCanParams {
+    /// Determines if the interface supports the bitrate, as far as it reports.
+    pub fn supports_bitrate(&self, bitrate: u32) -> bool {
+        Self::supports_rate(&self.bitrate_const, self.bitrate_max, bitrate)
+    }
+
+    /// Determines if the interface supports the FD data bitrate, as far as
+    /// it reports.
+    pub fn supports_data_bitrate(&self, bitrate: u32) -> bool {
+        Self::supports_rate(&self.data_bitrate_const, self.bitrate_max, bitrate)
+    }
+
+    /// Determines if the interface supports the termination resistance,
+    /// as far as it reports.
+    pub fn supports_termination(&self, termination: u16) -> bool {
+        self.termination_const.is_empty() || self.termination_const.contains(&termination)
+    }
+
+    /// Determines if the interface supports turning on the control mode,
+    /// as far as it reports.
+    ///
+    /// Modes that are always on for an interface, like FD for some
+    /// controllers, are supported even if they can't be changed.
+    pub fn supports_ctrlmode(&self, mode: CanCtrlMode) -> bool {
+        let mask = mode.mask();
+        self.ctrl_mode_supported.map_or(true, |supported| {
+            (supported | self.ctrl_mode.0.flags) & mask != 0
+        })
+    }
+
+    /// Determines if a bitrate is in a list of fixed rates, if there is
+    /// one, and is not over the maximum.
+    fn supports_rate(rates: &[u32], max: Option<u32>, bitrate: u32) -> bool {
+        (rates.is_empty() || rates.contains(&bitrate))
+            && max.map_or(true, |max| max == 0 || bitrate <= max)
+    }
+
+    /// Checks that the interface supports the bitrate, for the setters.
+    pub(crate) fn check_bitrate(&self, bitrate: u32) -> NlResult<()> {
+        if self.supports_bitrate(bitrate) {
+            Ok(())
+        } else {
+            Err(unsupported_rate(
+                "Bitrate",
+                bitrate,
+                &self.bitrate_const,
+                self.bitrate_max,
+            ))
+        }
+    }
+
+    /// Checks that the interface supports the FD data bitrate, for the
+    /// setters.
+    pub(crate) fn check_data_bitrate(&self, bitrate: u32) -> NlResult<()> {
+        if self.supports_data_bitrate(bitrate) {
+            Ok(())
+        } else {
+            Err(unsupported_rate(
+                "Data bitrate",
+                bitrate,
+                &self.data_bitrate_const,
+                self.bitrate_max,
+            ))
+        }
+    }
+
+    /// Checks that the interface supports the termination, for the setters.
+    pub(crate) fn check_termination(&self, termination: u16) -> NlResult<()> {
+        if self.supports_termination(termination) {
+            Ok(())
+        } else {
+            Err(NlError::Msg(format!(
+                "Termination {} not supported by the interface, use one of {:?}",
+                termination, self.termination_const
+            )))
+        }
+    }
+
+    /// Checks that the interface supports all the control modes that
+    /// would be turned on, for the setters.
+    pub(crate) fn check_ctrlmodes(&self, modes: &can_ctrlmode) -> NlResult<()> {
+        let on = modes.flags & modes.mask;
+        if let Some(supported) = self.ctrl_mode_supported {
+            let unsupported = on & !(supported | self.ctrl_mode.0.flags);
+            if unsupported != 0 {
+                return Err(NlError::Msg(format!(
+                    "Control mode(s) 0x{:X} not supported by the interface",
+                    unsupported
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the parameters to set the FD transmitter delay compensation,
+    /// which the kernel only takes along with the bit timings.
+    pub(crate) fn tdc_params(&self, tdc: Option<CanTdc>) -> NlResult<SetCanParams> {
+        let (Some(bit_timing), Some(data_bit_timing)) = (self.bit_timing, self.data_bit_timing)
+        else {
+            return Err(NlError::Msg(
+                "The bit timing and data bit timing must be set for TDC".into(),
+            ));
+        };
+
+        let mode = tdc.map(|tdc| tdc.mode());
+        let mut ctrl_mode = CanCtrlModes::from_mode(CanCtrlMode::Fd, true);
+        ctrl_mode.add(CanCtrlMode::TdcAuto, mode == Some(CanCtrlMode::TdcAuto));
+        ctrl_mode.add(CanCtrlMode::TdcManual, mode == Some(CanCtrlMode::TdcManual));
+
+        // The timings read back have both a bitrate and a time quantum,
+        // which the kernel won't take together.
+        Ok(SetCanParams {
+            bit_timing: Some(bit_timing.for_kernel()),
+            ctrl_mode: Some(ctrl_mode),
+            data_bit_timing: Some(data_bit_timing.for_kernel()),
+            tdc,
+            ..SetCanParams::default()
+        })
+    }
 }
 
 impl TryFrom<&Rtattr<Ifla, Buffer>> for InterfaceCanParams {
@@ -183,7 +422,8 @@ impl TryFrom<&Rtattr<Ifla, Buffer>> for InterfaceCanParams {
         for info in link_info.get_attr_handle::<IflaInfo>()?.get_attrs() {
             if info.rta_type == IflaInfo::Data {
                 for attr in info.get_attr_handle::<IflaCan>()?.get_attrs() {
-                    match attr.rta_type {
+                    // The kernel flags some of these as nested
+                    match IflaCan::from(u16::from(&attr.rta_type) & NLA_TYPE_MASK) {
                         IflaCan::BitTiming => {
                             params.bit_timing = Some(attr.get_payload_as::<CanBitTiming>()?);
                         }
@@ -217,6 +457,28 @@ impl TryFrom<&Rtattr<Ifla, Buffer>> for InterfaceCanParams {
                         IflaCan::Termination => {
                             params.termination = attr.get_payload_as::<u16>()?;
                         }
+                        IflaCan::Tdc => {
+                            (params.tdc, params.tdc_const) = parse_tdc(attr)?;
+                        }
+                        IflaCan::BitRateConst => {
+                            params.bitrate_const = parse_array(attr, u32::from_ne_bytes);
+                        }
+                        IflaCan::DataBitRateConst => {
+                            params.data_bitrate_const = parse_array(attr, u32::from_ne_bytes);
+                        }
+                        IflaCan::TerminationConst => {
+                            params.termination_const = parse_array(attr, u16::from_ne_bytes);
+                        }
+                        IflaCan::BitRateMax => {
+                            params.bitrate_max = Some(attr.get_payload_as::<u32>()?);
+                        }
+                        IflaCan::CtrlModeExt => {
+                            for ext in attr.get_attr_handle::<IflaCanCtrlModeExt>()?.get_attrs() {
+                                if ext.rta_type == IflaCanCtrlModeExt::Supported {
+                                    params.ctrl_mode_supported = Some(ext.get_payload_as::<u32>()?);
+                                }
+                            }
+                        }
                         _ => (),
                     }
                 }
@@ -226,6 +488,33 @@ impl TryFrom<&Rtattr<Ifla, Buffer>> for InterfaceCanParams {
     }
 }
 
+/// Parses an attribute that holds an array of integers, like the
+/// supported bitrates.
+fn parse_array<T, const N: usize>(attr: &Rtattr<IflaCan, Buffer>, f: fn([u8; N]) -> T) -> Vec<T> {
+    attr.rta_payload
+        .as_ref()
+        .chunks_exact(N)
+        .map(|b| f(b.try_into().unwrap()))
+        .collect()
+}
+
+/// Creates the error for a bitrate that the interface doesn't support.
+fn unsupported_rate(what: &str, bitrate: u32, rates: &[u32], max: Option<u32>) -> NlError {
+    let msg = match max {
+        Some(max) if max != 0 && bitrate > max => {
+            format!(
+                "{} {} is over the interface maximum of {}",
+                what, bitrate, max
+            )
+        }
+        _ => format!(
+            "{} {} not supported by the interface, use one of {:?}",
+            what, bitrate, rates
+        ),
+    };
+    NlError::Msg(msg)
+}
+
 // ===== CanCtrlMode(s) =====
 
 ///
@@ -253,6 +542,14 @@ pub enum CanCtrlMode {
     NonIso,
     /// Classic CAN DLC option
     CcLen8Dlc,
+    /// FD transmitter delay compensation, with the TDCV measured by the
+    /// controller. This is set along with the TDC parameters, by
+    /// [`CanInterface::set_tdc()`].
+    TdcAuto,
+    /// FD transmitter delay compensation, with the TDCV set by the user.
+    /// This is set along with the TDC parameters, by
+    /// [`CanInterface::set_tdc()`].
+    TdcManual,
 }
 
 impl CanCtrlMode {
@@ -264,6 +561,7 @@ impl CanCtrlMode {
 
 /// The collection of control modes
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct CanCtrlModes(can_ctrlmode);
 
 impl CanCtrlModes {
@@ -322,6 +620,71 @@ pub struct SetCanParams {
     pub data_bit_timing: Option<CanBitTiming>,
     /// The CANbus termination resistance
     pub termination: Option<u16>,
+    /// The FD transmitter delay compensation. The kernel only takes this
+    /// along with the FD data bit timing, and the matching TDC control mode.
+    pub tdc: Option<CanTdc>,
+    /// The MTU size, for standard or FD frames
+    pub mtu: Option<Mtu>,
+}
+
+impl SetCanParams {
+    /// Creates the parameters that would undo these ones, from the
+    /// details of the interface before they were set.
+    ///
+    /// Only the parameters that are set here are restored, and only if
+    /// the interface reported a previous value for them. The bit timings
+    /// are restored by their time quantum and segments.
+    pub(crate) fn undo(&self, prev: &InterfaceDetails) -> Self {
+        let can = &prev.can;
+        let ctrl_mode = self.ctrl_mode.map(|modes| {
+            let mask = modes.0.mask;
+            CanCtrlModes::new(mask, can.ctrl_mode.0.flags & mask)
+        });
+
+        // The kernel needs both bit timings to turn FD mode on
+        let fd_on = ctrl_mode.is_some_and(|modes| modes.0.flags & CanCtrlMode::Fd.mask() != 0);
+
+        // ...and the TDC parameters to match a TDC mode. A TDCV read back
+        // in automatic mode is the measured one, which can't be set.
+        let flags = ctrl_mode.map_or(0, |modes| modes.0.flags);
+        let tdc = if flags & CanCtrlMode::TdcManual.mask() != 0 {
+            can.tdc
+        } else if flags & CanCtrlMode::TdcAuto.mask() != 0 {
+            can.tdc.map(|tdc| CanTdc { tdcv: None, ..tdc })
+        } else {
+            None
+        };
+
+        Self {
+            bit_timing: if self.bit_timing.is_some() || fd_on {
+                can.bit_timing.map(CanBitTiming::for_kernel)
+            } else {
+                None
+            },
+            restart_ms: self.restart_ms.map(|_| can.restart_ms),
+            ctrl_mode,
+            data_bit_timing: if self.data_bit_timing.is_some() || fd_on {
+                can.data_bit_timing.map(CanBitTiming::for_kernel)
+            } else {
+                None
+            },
+            termination: self.termination.map(|_| can.termination),
+            tdc,
+            mtu: self.mtu.and(prev.mtu),
+        }
+    }
+}
+
+// ===== NetNs =====
+
+/// A network namespace that an interface can be moved into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetNs {
+    /// The network namespace of a process, by its ID
+    Pid(u32),
+    /// A network namespace by an open file descriptor, like for a file
+    /// under `/var/run/netns/` or `/proc/<pid>/ns/net`
+    Fd(RawFd),
 }
 
 // ===== CanInterface =====
@@ -382,9 +745,18 @@ impl CanInterface {
     /// Sends an info message to the kernel.
     fn send_info_msg(msg_type: Rtm, info: Ifinfomsg, additional_flags: &[NlmF]) -> NlResult<()> {
         let mut nl = Self::open_route_socket()?;
+        let hdr = Self::info_hdr(msg_type, info, additional_flags);
+        Self::send_and_read_ack(&mut nl, hdr)
+    }
 
-        // prepare message
-        let hdr = Nlmsghdr::new(
+    /// Creates the netlink message to send an info message to the kernel,
+    /// asking for an ACK.
+    pub(crate) fn info_hdr(
+        msg_type: Rtm,
+        info: Ifinfomsg,
+        additional_flags: &[NlmF],
+    ) -> Nlmsghdr<Rtm, Ifinfomsg> {
+        Nlmsghdr::new(
             None,
             msg_type,
             {
@@ -397,9 +769,7 @@ impl CanInterface {
             None,
             None,
             NlPayload::Payload(info),
-        );
-        // send the message
-        Self::send_and_read_ack(&mut nl, hdr)
+        )
     }
 
     /// Sends a message down a netlink socket, and checks if an ACK was
@@ -410,13 +780,18 @@ impl CanInterface {
         P: ToBytes + Debug,
     {
         sock.send(msg)?;
+        Self::check_ack(sock.recv()?)
+    }
 
-        // This will actually produce an Err if the response is a netlink error,
-        // no need to match.
+    /// Checks that the response to a message is an ACK.
+    ///
+    /// Receiving the response will actually produce an Err if it's a
+    /// netlink error, so this only needs to check for the ACK.
+    pub(crate) fn check_ack(msg: Option<Nlmsghdr<u16, Buffer>>) -> NlResult<()> {
         if let Some(Nlmsghdr {
             nl_payload: NlPayload::Ack(_),
             ..
-        }) = sock.recv()?
+        }) = msg
         {
             Ok(())
         } else {
@@ -424,69 +799,135 @@ impl CanInterface {
         }
     }
 
-    /// Opens a new netlink socket, bound to this process' PID.
+    /// Opens a new netlink socket.
     /// The function is generic to allow for usage in contexts where NlError
     /// has specific, non-default, generic parameters.
-    fn open_route_socket<T, P>() -> Result<NlSocketHandle, NlError<T, P>> {
-        // retrieve PID
-        let pid = unistd::getpid().as_raw() as u32;
-
+    pub(crate) fn open_route_socket<T, P>() -> Result<NlSocketHandle, NlError<T, P>> {
         // open and bind socket
+        // The port ID is left for the kernel to assign, so that this can
+        // be open at the same time as other netlink sockets in the process,
+        // like a `LinkMonitor`.
         // groups is set to None(0), because we want no notifications
-        let sock = NlSocketHandle::connect(NlFamily::Route, Some(pid), &[])?;
+        let sock = NlSocketHandle::connect(NlFamily::Route, None, &[])?;
         Ok(sock)
     }
 
+    /// Gets the details of all of the CAN interfaces on the system.
+    ///
+    /// This dumps all of the network links from the kernel, and keeps the
+    /// ones with the CAN hardware type, including virtual interfaces like
+    /// vcan and vxcan. Unlike `enumerate::available_interfaces()`, it
+    /// only uses netlink, so works without udev, like in minimal containers.
+    pub fn list() -> Result<Vec<InterfaceDetails>, NlInfoError> {
+        let mut sock = Self::open_route_socket()?;
+
+        let info = Ifinfomsg::new(
+            RtAddrFamily::Unspecified,
+            Arphrd::Netrom,
+            0,
+            IffFlags::empty(),
+            IffFlags::empty(),
+            {
+                let mut buffer = RtBuffer::new();
+                buffer.push(Rtattr::new(None, Ifla::ExtMask, rt::EXT_FILTER_VF).unwrap());
+                buffer
+            },
+        );
+
+        let hdr = Nlmsghdr::new(
+            None,
+            Rtm::Getlink,
+            NlmFFlags::new(&[NlmF::Request, NlmF::Dump]),
+            None,
+            None,
+            NlPayload::Payload(info),
+        );
+        sock.send(hdr)?;
+
+        let mut links = Vec::new();
+        for msg in sock.iter::<Rtm, Ifinfomsg>(false) {
+            // The final "done" message has no payload
+            if let Ok(payload) = msg?.get_payload() {
+                if is_can_link(payload) {
+                    links.push(InterfaceDetails::try_from(payload)?);
+                }
+            }
+        }
+        Ok(links)
+    }
+
     /// Sends a query to the kernel and returns the response info message
     /// to the caller.
     fn query_details(&self) -> Result<Option<Nlmsghdr<Rtm, Ifinfomsg>>, NlInfoError> {
         let mut sock = Self::open_route_socket()?;
+        sock.send(self.details_hdr())?;
+        sock.recv::<'_, Rtm, Ifinfomsg>()
+    }
 
+    /// Creates the netlink message to query the details of the interface.
+    pub(crate) fn details_hdr(&self) -> Nlmsghdr<Rtm, Ifinfomsg> {
         let info = self.info_msg({
             let mut buffer = RtBuffer::new();
             buffer.push(Rtattr::new(None, Ifla::ExtMask, rt::EXT_FILTER_VF).unwrap());
             buffer
         });
 
-        let hdr = Nlmsghdr::new(
+        Nlmsghdr::new(
             None,
             Rtm::Getlink,
             NlmFFlags::new(&[NlmF::Request]),
             None,
             None,
             NlPayload::Payload(info),
-        );
+        )
+    }
 
-        sock.send(hdr)?;
-        sock.recv::<'_, Rtm, Ifinfomsg>()
+    /// Gets the details of the interface from the response to a query.
+    pub(crate) fn details_from(
+        &self,
+        msg: Option<Nlmsghdr<Rtm, Ifinfomsg>>,
+    ) -> Result<InterfaceDetails, NlInfoError> {
+        match msg {
+            Some(msg_hdr) => match msg_hdr.get_payload() {
+                Ok(payload) => InterfaceDetails::try_from(payload),
+                Err(_) => Ok(InterfaceDetails::new(self.if_index)),
+            },
+            None => Err(NlError::NoAck),
+        }
     }
 
     /// Bring down this interface.
     ///
     /// Use a netlink control socket to set the interface status to "down".
     pub fn bring_down(&self) -> NlResult<()> {
-        // Specific iface down info
-        let info = Ifinfomsg::down(
-            RtAddrFamily::Unspecified,
-            Arphrd::Netrom,
-            self.if_index as c_int,
-            RtBuffer::new(),
-        );
-        Self::send_info_msg(Rtm::Newlink, info, &[])
+        Self::send_info_msg(Rtm::Newlink, self.up_down_msg(false), &[])
     }
 
     /// Bring up this interface
     ///
     /// Brings the interface up by settings its "up" flag enabled via netlink.
     pub fn bring_up(&self) -> NlResult<()> {
-        // Specific iface up info
-        let info = Ifinfomsg::up(
-            RtAddrFamily::Unspecified,
-            Arphrd::Netrom,
-            self.if_index as c_int,
-            RtBuffer::new(),
-        );
-        Self::send_info_msg(Rtm::Newlink, info, &[])
+        Self::send_info_msg(Rtm::Newlink, self.up_down_msg(true), &[])
+    }
+
+    /// Creates the info message to bring the interface up or down.
+    pub(crate) fn up_down_msg(&self, up: bool) -> Ifinfomsg {
+        let index = self.if_index as c_int;
+        if up {
+            Ifinfomsg::up(
+                RtAddrFamily::Unspecified,
+                Arphrd::Netrom,
+                index,
+                RtBuffer::new(),
+            )
+        } else {
+            Ifinfomsg::down(
+                RtAddrFamily::Unspecified,
+                Arphrd::Netrom,
+                index,
+                RtBuffer::new(),
+            )
+        }
     }
 
     /// Create a virtual CAN (VCAN) interface.
@@ -512,10 +953,67 @@ impl CanInterface {
     where
         I: Into<Option<u32>>,
     {
+        Self::create_link(name, index.into(), kind, None)
+    }
+
+    /// Create a pair of virtual CAN tunnel (vxcan) interfaces.
+    ///
+    /// Frames sent on either one of the pair are received on the other.
+    /// One end is usually moved into another network namespace, with
+    /// [`move_to_netns()`](Self::move_to_netns), to connect it to the one
+    /// that created the pair. Deleting either interface deletes both.
+    ///
+    /// Note that the length of the names is capped by ```libc::IFNAMSIZ```.
+    ///
+    /// PRIVILEGED: This requires root privilege.
+    ///
+    pub fn create_vxcan<I>(name: &str, peer_name: &str, index: I) -> NlResult<(Self, Self)>
+    where
+        I: Into<Option<u32>>,
+    {
+        if peer_name.len() > libc::IFNAMSIZ {
+            return Err(NlError::Msg("Peer interface name too long".into()));
+        }
+
+        // The peer is described by its own link info message
+        let peer = Ifinfomsg::new(
+            RtAddrFamily::Unspecified,
+            Arphrd::Netrom,
+            0,
+            IffFlags::empty(),
+            IffFlags::empty(),
+            {
+                let mut buffer = RtBuffer::new();
+                buffer.push(Rtattr::new(None, Ifla::Ifname, peer_name)?);
+                buffer
+            },
+        );
+        let mut data = Rtattr::new(None, IflaInfo::Data, Buffer::new())?;
+        data.add_nested_attribute(&Rtattr::new(None, VxcanInfo::Peer, peer)?)?;
+
+        let iface = Self::create_link(name, index.into(), "vxcan", Some(data))?;
+        match Self::open(peer_name) {
+            Ok(peer) => Ok((iface, peer)),
+            Err(_) => {
+                // Don't leave the pair behind. Deleting one end deletes both.
+                let _ = iface.delete();
+                Err(NlError::Msg(
+                    "Peer interface not found after creation".into(),
+                ))
+            }
+        }
+    }
+
+    /// Create an interface of the given kind, with optional link info data.
+    fn create_link(
+        name: &str,
+        index: Option<u32>,
+        kind: &str,
+        data: Option<Rtattr<IflaInfo, Buffer>>,
+    ) -> NlResult<Self> {
         if name.len() > libc::IFNAMSIZ {
             return Err(NlError::Msg("Interface name too long".into()));
         }
-        let index = index.into();
 
         let info = Ifinfomsg::new(
             RtAddrFamily::Unspecified,
@@ -528,6 +1026,9 @@ impl CanInterface {
                 buffer.push(Rtattr::new(None, Ifla::Ifname, name)?);
                 let mut linkinfo = Rtattr::new(None, Ifla::Linkinfo, Vec::<u8>::new())?;
                 linkinfo.add_nested_attribute(&Rtattr::new(None, IflaInfo::Kind, kind)?)?;
+                if let Some(data) = data {
+                    linkinfo.add_nested_attribute(&data)?;
+                }
                 buffer.push(linkinfo);
                 buffer
             },
@@ -561,41 +1062,37 @@ impl CanInterface {
         }
     }
 
+    /// Move the interface into another network namespace.
+    ///
+    /// The interface can't be reached from this namespace after it's
+    /// moved, so this consumes the object. On failure, it is returned
+    /// along with the error.
+    ///
+    /// PRIVILEGED: This requires root privilege.
+    ///
+    pub fn move_to_netns(self, netns: NetNs) -> Result<(), (Self, NlError)> {
+        let attr = match netns {
+            NetNs::Pid(pid) => Rtattr::new(None, Ifla::NetNsPid, pid),
+            NetNs::Fd(fd) => Rtattr::new(None, Ifla::NetNsFd, fd as u32),
+        };
+        let attr = match attr {
+            Ok(attr) => attr,
+            Err(err) => return Err((self, err.into())),
+        };
+
+        let mut buffer = RtBuffer::new();
+        buffer.push(attr);
+        let info = self.info_msg(buffer);
+        match Self::send_info_msg(Rtm::Newlink, info, &[]) {
+            Ok(()) => Ok(()),
+            Err(err) => Err((self, err)),
+        }
+    }
+
     /// Attempt to query detailed information on the interface.
     pub fn details(&self) -> Result<InterfaceDetails, NlInfoError> {
-        match self.query_details()? {
-            Some(msg_hdr) => {
-                let mut info = InterfaceDetails::new(self.if_index);
-
-                if let Ok(payload) = msg_hdr.get_payload() {
-                    info.is_up = payload.ifi_flags.contains(&Iff::Up);
-
-                    for attr in payload.rtattrs.iter() {
-                        match attr.rta_type {
-                            Ifla::Ifname => {
-                                // Note: Use `CStr::from_bytes_until_nul` when MSRV >= 1.69
-                                info.name = CStr::from_bytes_with_nul(attr.rta_payload.as_ref())
-                                    .map(|s| s.to_string_lossy().into_owned())
-                                    .ok();
-                            }
-                            Ifla::Mtu => {
-                                info.mtu = attr
-                                    .get_payload_as::<u32>()
-                                    .ok()
-                                    .and_then(|mtu| Mtu::try_from(mtu).ok());
-                            }
-                            Ifla::Linkinfo => {
-                                info.can = InterfaceCanParams::try_from(attr)?;
-                            }
-                            _ => (),
-                        }
-                    }
-                }
-
-                Ok(info)
-            }
-            None => Err(NlError::NoAck),
-        }
+        let msg = self.query_details()?;
+        self.details_from(msg)
     }
 
     /// Set the MTU of this interface.
@@ -603,13 +1100,17 @@ impl CanInterface {
     /// PRIVILEGED: This requires root privilege.
     ///
     pub fn set_mtu(&self, mtu: Mtu) -> NlResult<()> {
+        Self::send_info_msg(Rtm::Newlink, self.mtu_msg(mtu)?, &[])
+    }
+
+    /// Creates the info message to set the MTU of the interface.
+    pub(crate) fn mtu_msg(&self, mtu: Mtu) -> NlResult<Ifinfomsg> {
         let mtu = mtu as u32;
-        let info = self.info_msg({
+        Ok(self.info_msg({
             let mut buffer = RtBuffer::new();
             buffer.push(Rtattr::new(None, Ifla::Mtu, &mtu.to_ne_bytes()[..])?);
             buffer
-        });
-        Self::send_info_msg(Rtm::Newlink, info, &[])
+        }))
     }
 
     /// Set a CAN-specific parameter.
@@ -623,7 +1124,15 @@ impl CanInterface {
     where
         P: ToBytes + neli::Size,
     {
-        let info = self.info_msg({
+        Self::send_info_msg(Rtm::Newlink, self.can_param_msg(param_type, param)?, &[])
+    }
+
+    /// Creates the info message to set a CAN-specific parameter.
+    pub(crate) fn can_param_msg<P>(&self, param_type: IflaCan, param: P) -> NlResult<Ifinfomsg>
+    where
+        P: ToBytes + neli::Size,
+    {
+        Ok(self.info_msg({
             let mut data = Rtattr::new(None, IflaInfo::Data, Buffer::new())?;
             data.add_nested_attribute(&Rtattr::new(None, param_type, param)?)?;
 
@@ -634,24 +1143,31 @@ impl CanInterface {
             let mut rtattrs = RtBuffer::new();
             rtattrs.push(link_info);
             rtattrs
-        });
-        Self::send_info_msg(Rtm::Newlink, info, &[])
+        }))
     }
 
     /// Set a CAN-specific set of parameters.
     ///
     /// This sends a netlink message down to the kernel to set multiple
-    /// attributes in the link info, such as bitrate, control modes, etc. 
+    /// attributes in the link info, such as bitrate, control modes, etc.
     ///
     /// If you have many attributes to set this is preferred to calling
     /// [set_can_params][CanInterface::set_can_param] multiple times, since this only sends a
     /// single netlink message. Also some CAN drivers might only accept
     /// a set of attributes, not over multiple messages.
     ///
+    /// This doesn't bring the interface down to change the parameters
+    /// that need it. For that, see [reconfigure][CanInterface::reconfigure].
+    ///
     /// PRIVILEGED: This requires root privilege.
     ///
     pub fn set_can_params(&self, params: &SetCanParams) -> NlResult<()> {
-        let info = self.info_msg({
+        Self::send_info_msg(Rtm::Newlink, self.can_params_msg(params)?, &[])
+    }
+
+    /// Creates the info message to set a CAN-specific set of parameters.
+    pub(crate) fn can_params_msg(&self, params: &SetCanParams) -> NlResult<Ifinfomsg> {
+        Ok(self.info_msg({
             let mut rtattrs: RtBuffer<Ifla, Buffer> = RtBuffer::new();
             let mut data = Rtattr::new(None, IflaInfo::Data, Buffer::new())?;
 
@@ -678,6 +1194,13 @@ impl CanInterface {
             if let Some(t) = params.termination {
                 data.add_nested_attribute(&Rtattr::new(None, IflaCan::Termination, t)?)?;
             }
+            if let Some(tdc) = params.tdc {
+                data.add_nested_attribute(&tdc.to_attr()?)?;
+            }
+            if let Some(mtu) = params.mtu {
+                let mtu = mtu as u32;
+                rtattrs.push(Rtattr::new(None, Ifla::Mtu, &mtu.to_ne_bytes()[..])?);
+            }
 
             let mut link_info = Rtattr::new(None, Ifla::Linkinfo, Buffer::new())?;
             link_info.add_nested_attribute(&Rtattr::new(None, IflaInfo::Kind, "can")?)?;
@@ -685,8 +1208,64 @@ impl CanInterface {
 
             rtattrs.push(link_info);
             rtattrs
-        });
-        Self::send_info_msg(Rtm::Newlink, info, &[])
+        }))
+    }
+
+    /// Queries the CAN parameters of the interface, for use by the setters.
+    fn can_params(&self) -> NlResult<InterfaceCanParams> {
+        self.details()
+            .map(|details| details.can)
+            .map_err(|err| NlError::Msg(err.to_string()))
+    }
+
+    /// Reconfigure the interface with a set of CAN-specific parameters,
+    /// all or nothing.
+    ///
+    /// Most of the parameters can only be changed while the interface is
+    /// down, so if it's up, this brings it down, sets the parameters in a
+    /// single netlink message, and then brings it back up.
+    ///
+    /// If setting the parameters, or bringing the interface back up, fails,
+    /// this tries to restore the previous values of the parameters, and
+    /// the previous up/down state, before returning the error.
+    ///
+    /// PRIVILEGED: This requires root privilege.
+    ///
+    pub fn reconfigure(&self, params: &SetCanParams) -> NlResult<()> {
+        let prev = self
+            .details()
+            .map_err(|err| NlError::Msg(err.to_string()))?;
+
+        self.reconfigure_steps(&prev, params)?
+            .run(|info| Self::send_info_msg(Rtm::Newlink, info, &[]))
+    }
+
+    /// Gets the messages to reconfigure the interface from its details
+    /// before the change.
+    ///
+    /// If the interface was up, it's brought down for the change, then back
+    /// up. The rollback restores the previous values of the parameters and
+    /// the previous up/down state.
+    pub(crate) fn reconfigure_steps(
+        &self,
+        prev: &InterfaceDetails,
+        params: &SetCanParams,
+    ) -> NlResult<ReconfigureSteps> {
+        let mut apply = Vec::new();
+        let mut rollback = vec![self.up_down_msg(false)];
+
+        if prev.is_up {
+            apply.push(self.up_down_msg(false));
+        }
+        apply.push(self.can_params_msg(params)?);
+        if let Ok(info) = self.can_params_msg(&params.undo(prev)) {
+            rollback.push(info);
+        }
+        if prev.is_up {
+            apply.push(self.up_down_msg(true));
+            rollback.push(self.up_down_msg(true));
+        }
+        Ok(ReconfigureSteps { apply, rollback })
     }
 
     /// Attempt to query an individual CAN parameter on the interface.
@@ -716,6 +1295,52 @@ impl CanInterface {
         }
     }
 
+    /// Gets the CAN device statistics for the interface.
+    ///
+    /// These count the bus errors, the changes to the error warning, error
+    /// passive, and bus-off states, the lost arbitrations, and the
+    /// controller restarts. They are reported by the CAN device driver in
+    /// the extended link statistics, so are not available for virtual
+    /// interfaces, like vcan, which return `None`.
+    pub fn device_stats(&self) -> Result<Option<CanDeviceStats>, NlInfoError> {
+        if let Some(hdr) = self.query_details()? {
+            if let Ok(payload) = hdr.get_payload() {
+                for top_attr in payload.rtattrs.iter() {
+                    if top_attr.rta_type == Ifla::Linkinfo {
+                        for info in top_attr.get_attr_handle::<IflaInfo>()?.get_attrs() {
+                            if info.rta_type == IflaInfo::Xstats {
+                                return Ok(Some(info.get_payload_as::<CanDeviceStats>()?));
+                            }
+                        }
+                    }
+                }
+            }
+            Ok(None)
+        } else {
+            Err(NlError::NoAck)
+        }
+    }
+
+    /// Gets the network statistics for the interface.
+    ///
+    /// These are the packets and bytes received and transmitted, and the
+    /// number that were dropped or had errors, as shown by
+    /// `ip -statistics link`.
+    pub fn link_stats(&self) -> Result<Option<LinkStats>, NlInfoError> {
+        if let Some(hdr) = self.query_details()? {
+            if let Ok(payload) = hdr.get_payload() {
+                for attr in payload.rtattrs.iter() {
+                    if attr.rta_type == Ifla::Stats64 {
+                        return Ok(Some(attr.get_payload_as::<LinkStats>()?));
+                    }
+                }
+            }
+            Ok(None)
+        } else {
+            Err(NlError::NoAck)
+        }
+    }
+
     /// Gets the current bit rate for the interface.
     pub fn bit_rate(&self) -> Result<Option<u32>, NlInfoError> {
         Ok(self.bit_timing()?.map(|timing| timing.bitrate))
@@ -727,6 +1352,10 @@ impl CanInterface {
     /// specified in Hz (bps) while the sample point is given in tenths
     /// of a percent/
     ///
+    /// This fails, without sending anything, if the interface reports that
+    /// it doesn't support the bitrate, like for an adapter with a fixed set
+    /// of bitrates.
+    ///
     /// PRIVILEGED: This requires root privilege.
     ///
     pub fn set_bitrate<P>(&self, bitrate: u32, sample_point: P) -> NlResult<()>
@@ -746,6 +1375,8 @@ impl CanInterface {
             sample_point
         );
 
+        self.can_params()?.check_bitrate(bitrate)?;
+
         self.set_bit_timing(CanBitTiming {
             bitrate,
             sample_point,
@@ -760,10 +1391,15 @@ impl CanInterface {
 
     /// Sets the bit timing params for the interface
     ///
+    /// The full timing can be computed ahead of time with
+    /// `CanBitTiming::calculate()`. If the timing has a time quantum, only
+    /// it and the segments are sent, since the kernel rejects a timing that
+    /// also has a bitrate.
+    ///
     /// PRIVILEGED: This requires root privilege.
     ///
     pub fn set_bit_timing(&self, timing: CanBitTiming) -> NlResult<()> {
-        self.set_can_param(IflaCan::BitTiming, timing)
+        self.set_can_param(IflaCan::BitTiming, timing.for_kernel())
     }
 
     /// Gets the bit timing const data for the interface
@@ -796,19 +1432,25 @@ impl CanInterface {
 
     /// Set the full control mode (bit) collection.
     ///
+    /// This fails, without sending anything, if it would turn on a mode
+    /// that the interface reports it doesn't support.
+    ///
     /// PRIVILEGED: This requires root privilege.
     ///
     pub fn set_ctrlmodes<M>(&self, ctrlmode: M) -> NlResult<()>
     where
         M: Into<CanCtrlModes>,
     {
-        let modes = ctrlmode.into();
-        let modes: can_ctrlmode = modes.into();
+        let modes: can_ctrlmode = ctrlmode.into().into();
+        self.can_params()?.check_ctrlmodes(&modes)?;
         self.set_can_param(IflaCan::CtrlMode, modes)
     }
 
     /// Set or clear an individual control mode parameter.
     ///
+    /// This fails, without sending anything, if it would turn on a mode
+    /// that the interface reports it doesn't support.
+    ///
     /// PRIVILEGED: This requires root privilege.
     ///
     pub fn set_ctrlmode(&self, mode: CanCtrlMode, on: bool) -> NlResult<()> {
@@ -841,12 +1483,17 @@ impl CanInterface {
     ///     EBUSY - The interface is not in a bus-off state
     ///
     pub fn restart(&self) -> NlResult<()> {
+        Self::send_info_msg(Rtm::Newlink, self.restart_msg()?, &[])
+    }
+
+    /// Creates the info message to manually restart the interface.
+    pub(crate) fn restart_msg(&self) -> NlResult<Ifinfomsg> {
         // Note: The linux code shows the data type to be u32, but never
         // appears to access the value sent. iproute2 sends a 1, so we do
         // too!
         // See: linux/drivers/net/can/dev/netlink.c
         let restart_data: u32 = 1;
-        self.set_can_param(IflaCan::Restart, &restart_data.to_ne_bytes()[..])
+        self.can_param_msg(IflaCan::Restart, &restart_data.to_ne_bytes()[..])
     }
 
     /// Gets the bus error counter from the interface
@@ -861,10 +1508,13 @@ impl CanInterface {
 
     /// Sets the data bit timing params for the interface
     ///
+    /// Like [`set_bit_timing()`](Self::set_bit_timing), if the timing has a
+    /// time quantum, only it and the segments are sent.
+    ///
     /// PRIVILEGED: This requires root privilege.
     ///
     pub fn set_data_bit_timing(&self, timing: CanBitTiming) -> NlResult<()> {
-        self.set_can_param(IflaCan::DataBitTiming, timing)
+        self.set_can_param(IflaCan::DataBitTiming, timing.for_kernel())
     }
 
     /// Set the data bitrate and, optionally, data sample point of this
@@ -876,6 +1526,10 @@ impl CanInterface {
     /// specified in Hz (bps) while the sample point is given in tenths
     /// of a percent/
     ///
+    /// This fails, without sending anything, if the interface reports that
+    /// it doesn't support the bitrate, like for an adapter with a fixed set
+    /// of bitrates.
+    ///
     /// PRIVILEGED: This requires root privilege.
     ///
     pub fn set_data_bitrate<P>(&self, bitrate: u32, sample_point: P) -> NlResult<()>
@@ -884,6 +1538,8 @@ impl CanInterface {
     {
         let sample_point: u32 = sample_point.into().unwrap_or(0);
 
+        self.can_params()?.check_data_bitrate(bitrate)?;
+
         self.set_data_bit_timing(CanBitTiming {
             bitrate,
             sample_point,
@@ -900,11 +1556,14 @@ impl CanInterface {
     ///
     /// Not all interfaces support setting a termination.
     /// Termination is in ohms. Your interface most likely only supports
-    /// certain values. Common values are 0 and 120.
+    /// certain values. Common values are 0 and 120. This fails, without
+    /// sending anything, if the interface reports the values it supports,
+    /// and `termination` isn't one of them.
     ///
     /// PRIVILEGED: This requires root privilege.
     ///
     pub fn set_termination(&self, termination: u16) -> NlResult<()> {
+        self.can_params()?.check_termination(termination)?;
         self.set_can_param(IflaCan::Termination, termination)
     }
 
@@ -912,8 +1571,324 @@ impl CanInterface {
     pub fn termination(&self) -> Result<Option<u16>, NlInfoError> {
         self.can_param::<u16>(IflaCan::Termination)
     }
+
+    /// Gets the FD transmitter delay compensation (TDC) parameters for the
+    /// interface, if TDC is enabled.
+    pub fn tdc(&self) -> Result<Option<CanTdc>, NlInfoError> {
+        Ok(self.details()?.can.tdc)
+    }
+
+    /// Gets the limits of the FD transmitter delay compensation (TDC)
+    /// parameters, if the interface supports TDC.
+    pub fn tdc_const(&self) -> Result<Option<CanTdcConst>, NlInfoError> {
+        Ok(self.details()?.can.tdc_const)
+    }
+
+    /// Sets the FD transmitter delay compensation (TDC) for the interface,
+    /// or turns it off with `None`.
+    ///
+    /// The TDC is in automatic mode if the parameters don't have a TDCV,
+    /// and manual mode if they do.
+    ///
+    /// The kernel only takes the TDC along with the FD data bit timing, so
+    /// this sends the current bit timings again, along with FD mode and
+    /// the TDC mode. The interface must be down, and have its bitrates set.
+    /// If the TDC is never set, the kernel calculates the TDCO itself when
+    /// the data bit timing is set, for controllers that support it.
+    ///
+    /// PRIVILEGED: This requires root privilege.
+    ///
+    pub fn set_tdc<T>(&self, tdc: T) -> NlResult<()>
+    where
+        T: Into<Option<CanTdc>>,
+    {
+        let params = self.can_params()?.tdc_params(tdc.into())?;
+        self.set_can_params(&params)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+/// Defines the async `CanInterface` for a runtime.
+///
+/// The async interfaces only differ in how they wait for the response to
+/// a request, so this expects the module that uses it to have an
+/// `async fn nl_request()` that sends a request, then waits for the
+/// response on a non-blocking socket that is registered with the runtime.
+/// The messages are all made, and checked, by the blocking interface.
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+macro_rules! async_can_interface {
+    () => {
+        mod iface {
+            use super::nl_request;
+            use crate::nl::{
+                rt::{can_ctrlmode, IflaCan},
+                CanBitTiming, CanCtrlMode, CanCtrlModes, CanTdc, InterfaceCanParams,
+                InterfaceDetails, Mtu, NlInfoError, NlResult, SetCanParams,
+            };
+            use neli::{consts::rtnl::Rtm, err::NlError, rtnl::Ifinfomsg};
+
+            /// An asynchronous CAN interface, to configure it over netlink.
+            ///
+            /// This is the async equivalent of [`nl::CanInterface`](crate::nl::CanInterface).
+            /// Like that one, each call opens its own netlink socket, but this waits
+            /// for the kernel's response without blocking the thread.
+            ///
+            /// The values for the individual getters of the blocking interface are
+            /// all in the [`details()`](Self::details).
+            #[derive(Debug)]
+            pub struct CanInterface(crate::nl::CanInterface);
+
+            impl CanInterface {
+                /// Open a CAN interface by name.
+                pub fn open(ifname: &str) -> Result<Self, nix::Error> {
+                    crate::nl::CanInterface::open(ifname).map(Self)
+                }
+
+                /// Open a CAN interface by index.
+                ///
+                /// Note that this doesn't check that the interface exists.
+                pub fn open_iface(if_index: u32) -> Self {
+                    Self(crate::nl::CanInterface::open_iface(if_index))
+                }
+
+                /// Gets a reference to the underlying blocking interface.
+                pub fn get_ref(&self) -> &crate::nl::CanInterface {
+                    &self.0
+                }
+
+                /// Sends an info message to the kernel, and waits for the ACK.
+                async fn send_info_msg(info: Ifinfomsg) -> NlResult<()> {
+                    let hdr = crate::nl::CanInterface::info_hdr(Rtm::Newlink, info, &[]);
+                    crate::nl::CanInterface::check_ack(nl_request(hdr).await?)
+                }
+
+                /// Attempt to query detailed information on the interface.
+                pub async fn details(&self) -> Result<InterfaceDetails, NlInfoError> {
+                    let msg = nl_request(self.0.details_hdr()).await?;
+                    self.0.details_from(msg)
+                }
+
+                /// Queries the CAN parameters of the interface, for use by the setters.
+                async fn can_params(&self) -> NlResult<InterfaceCanParams> {
+                    self.details()
+                        .await
+                        .map(|details| details.can)
+                        .map_err(|err| NlError::Msg(err.to_string()))
+                }
+
+                /// Bring down this interface.
+                pub async fn bring_down(&self) -> NlResult<()> {
+                    Self::send_info_msg(self.0.up_down_msg(false)).await
+                }
+
+                /// Bring up this interface.
+                pub async fn bring_up(&self) -> NlResult<()> {
+                    Self::send_info_msg(self.0.up_down_msg(true)).await
+                }
+
+                /// Set the MTU of this interface.
+                ///
+                /// PRIVILEGED: This requires root privilege.
+                pub async fn set_mtu(&self, mtu: Mtu) -> NlResult<()> {
+                    Self::send_info_msg(self.0.mtu_msg(mtu)?).await
+                }
+
+                /// Set a CAN-specific set of parameters, in a single netlink message.
+                ///
+                /// PRIVILEGED: This requires root privilege.
+                pub async fn set_can_params(&self, params: &SetCanParams) -> NlResult<()> {
+                    Self::send_info_msg(self.0.can_params_msg(params)?).await
+                }
+
+                /// Reconfigure the interface with a set of CAN-specific parameters,
+                /// all or nothing.
+                ///
+                /// See [`nl::CanInterface::reconfigure()`](crate::nl::CanInterface::reconfigure).
+                ///
+                /// PRIVILEGED: This requires root privilege.
+                pub async fn reconfigure(&self, params: &SetCanParams) -> NlResult<()> {
+                    let prev = self
+                        .details()
+                        .await
+                        .map_err(|err| NlError::Msg(err.to_string()))?;
+                    let steps = self.0.reconfigure_steps(&prev, params)?;
+
+                    let mut res = Ok(());
+                    for info in steps.apply {
+                        res = Self::send_info_msg(info).await;
+                        if res.is_err() {
+                            break;
+                        }
+                    }
+                    if res.is_err() {
+                        for info in steps.rollback {
+                            let _ = Self::send_info_msg(info).await;
+                        }
+                    }
+                    res
+                }
+
+                /// Set the bitrate and, optionally, sample point of this interface.
+                ///
+                /// This fails, without sending anything, if the interface reports that
+                /// it doesn't support the bitrate.
+                ///
+                /// PRIVILEGED: This requires root privilege.
+                pub async fn set_bitrate<P>(&self, bitrate: u32, sample_point: P) -> NlResult<()>
+                where
+                    P: Into<Option<u32>>,
+                {
+                    let sample_point: u32 = sample_point.into().unwrap_or(0);
+                    self.can_params().await?.check_bitrate(bitrate)?;
+                    self.set_bit_timing(CanBitTiming {
+                        bitrate,
+                        sample_point,
+                        ..CanBitTiming::default()
+                    })
+                    .await
+                }
+
+                /// Sets the bit timing params for the interface
+                ///
+                /// PRIVILEGED: This requires root privilege.
+                pub async fn set_bit_timing(&self, timing: CanBitTiming) -> NlResult<()> {
+                    Self::send_info_msg(
+                        self.0
+                            .can_param_msg(IflaCan::BitTiming, timing.for_kernel())?,
+                    )
+                    .await
+                }
+
+                /// Set the full control mode (bit) collection.
+                ///
+                /// This fails, without sending anything, if it would turn on a mode
+                /// that the interface reports it doesn't support.
+                ///
+                /// PRIVILEGED: This requires root privilege.
+                pub async fn set_ctrlmodes<M>(&self, ctrlmode: M) -> NlResult<()>
+                where
+                    M: Into<CanCtrlModes>,
+                {
+                    let modes: can_ctrlmode = ctrlmode.into().into();
+                    self.can_params().await?.check_ctrlmodes(&modes)?;
+                    Self::send_info_msg(self.0.can_param_msg(IflaCan::CtrlMode, modes)?).await
+                }
+
+                /// Set or clear an individual control mode parameter.
+                ///
+                /// PRIVILEGED: This requires root privilege.
+                pub async fn set_ctrlmode(&self, mode: CanCtrlMode, on: bool) -> NlResult<()> {
+                    self.set_ctrlmodes(CanCtrlModes::from_mode(mode, on)).await
+                }
+
+                /// Set the automatic restart milliseconds of the interface
+                ///
+                /// PRIVILEGED: This requires root privilege.
+                pub async fn set_restart_ms(&self, restart_ms: u32) -> NlResult<()> {
+                    let info = self
+                        .0
+                        .can_param_msg(IflaCan::RestartMs, &restart_ms.to_ne_bytes()[..])?;
+                    Self::send_info_msg(info).await
+                }
+
+                /// Manually restart the interface.
+                ///
+                /// This is only permitted if automatic restart is disabled and the
+                /// device is in the bus-off state.
+                ///
+                /// PRIVILEGED: This requires root privilege.
+                pub async fn restart(&self) -> NlResult<()> {
+                    Self::send_info_msg(self.0.restart_msg()?).await
+                }
+
+                /// Sets the data bit timing params for the interface
+                ///
+                /// PRIVILEGED: This requires root privilege.
+                pub async fn set_data_bit_timing(&self, timing: CanBitTiming) -> NlResult<()> {
+                    Self::send_info_msg(
+                        self.0
+                            .can_param_msg(IflaCan::DataBitTiming, timing.for_kernel())?,
+                    )
+                    .await
+                }
+
+                /// Set the data bitrate and, optionally, data sample point of this
+                /// interface.
+                ///
+                /// This fails, without sending anything, if the interface reports that
+                /// it doesn't support the bitrate.
+                ///
+                /// PRIVILEGED: This requires root privilege.
+                pub async fn set_data_bitrate<P>(
+                    &self,
+                    bitrate: u32,
+                    sample_point: P,
+                ) -> NlResult<()>
+                where
+                    P: Into<Option<u32>>,
+                {
+                    let sample_point: u32 = sample_point.into().unwrap_or(0);
+                    self.can_params().await?.check_data_bitrate(bitrate)?;
+                    self.set_data_bit_timing(CanBitTiming {
+                        bitrate,
+                        sample_point,
+                        ..CanBitTiming::default()
+                    })
+                    .await
+                }
+
+                /// Sets the CANbus termination for the interface
+                ///
+                /// This fails, without sending anything, if the interface reports the
+                /// values it supports, and `termination` isn't one of them.
+                ///
+                /// PRIVILEGED: This requires root privilege.
+                pub async fn set_termination(&self, termination: u16) -> NlResult<()> {
+                    self.can_params().await?.check_termination(termination)?;
+                    let info = self.0.can_param_msg(IflaCan::Termination, termination)?;
+                    Self::send_info_msg(info).await
+                }
+
+                /// Sets the FD transmitter delay compensation (TDC) for the interface,
+                /// or turns it off with `None`.
+                ///
+                /// See [`nl::CanInterface::set_tdc()`](crate::nl::CanInterface::set_tdc).
+                ///
+                /// PRIVILEGED: This requires root privilege.
+                pub async fn set_tdc<T>(&self, tdc: T) -> NlResult<()>
+                where
+                    T: Into<Option<CanTdc>>,
+                {
+                    let params = self.can_params().await?.tdc_params(tdc.into())?;
+                    self.set_can_params(&params).await
+                }
+            }
+
+            impl From<crate::nl::CanInterface> for CanInterface {
+                fn from(iface: crate::nl::CanInterface) -> Self {
+                    Self(iface)
+                }
+            }
+        }
+
+        pub use iface::CanInterface;
+    };
 }
 
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+pub(crate) use async_can_interface;
+
 /////////////////////////////////////////////////////////////////////////////
 
 #[cfg(feature = "netlink_tests")]
@@ -921,7 +1896,7 @@ impl CanInterface {
 pub mod tests {
     use super::*;
     use serial_test::serial;
-    use std::ops::Deref;
+    use std::{fs::File, ops::Deref, os::unix::io::AsRawFd, thread};
 
     /// RAII-style helper to create and clean-up a specific vcan interface for a single test.
     /// Using drop here ensures that the interface always gets cleaned up
@@ -968,6 +1943,32 @@ pub mod tests {
         }
     }
 
+    /// Creates a new, empty network namespace, which lasts as long as the
+    /// file is open.
+    fn scratch_netns() -> File {
+        // Namespaces are per thread, so this leaves the test's own alone
+        thread::spawn(|| {
+            assert_eq!(0, unsafe { libc::unshare(libc::CLONE_NEWNET) });
+            File::open("/proc/thread-self/ns/net").unwrap()
+        })
+        .join()
+        .unwrap()
+    }
+
+    /// Runs a function in the network namespace of the file.
+    fn in_netns<T: Send>(netns: &File, f: impl FnOnce() -> T + Send) -> T {
+        thread::scope(|s| {
+            s.spawn(|| {
+                assert_eq!(0, unsafe {
+                    libc::setns(netns.as_raw_fd(), libc::CLONE_NEWNET)
+                });
+                f()
+            })
+            .join()
+            .unwrap()
+        })
+    }
+
     #[test]
     #[serial]
     fn up_down() {
@@ -990,6 +1991,319 @@ pub mod tests {
         assert!(!details.is_up);
     }
 
+    #[test]
+    #[serial]
+    fn list() {
+        let interface = TemporaryInterface::new("list").unwrap();
+        let links = CanInterface::list().unwrap();
+        let details = links
+            .iter()
+            .find(|details| details.index == interface.if_index)
+            .unwrap();
+        assert_eq!(Some("list"), details.name.as_deref());
+        assert_eq!(Some("vcan"), details.kind.as_deref());
+    }
+
+    #[test]
+    #[serial]
+    fn stats() {
+        let interface = TemporaryInterface::new("stats").unwrap();
+
+        let stats = interface.link_stats().unwrap().unwrap();
+        assert_eq!(0, stats.tx_packets);
+
+        // vcan doesn't have a CAN device driver to report these
+        assert!(interface.device_stats().unwrap().is_none());
+    }
+
+    #[test]
+    #[serial]
+    fn vxcan() {
+        let (iface, peer) = CanInterface::create_vxcan("vxcan_a", "vxcan_b", None).unwrap();
+
+        let details = iface.details().unwrap();
+        assert_eq!(Some("vxcan_a"), details.name.as_deref());
+        assert_eq!(Some("vxcan"), details.kind.as_deref());
+
+        let details = peer.details().unwrap();
+        assert_eq!(Some("vxcan_b"), details.name.as_deref());
+        assert_eq!(Some("vxcan"), details.kind.as_deref());
+
+        // Deleting one end deletes the pair
+        iface.delete().unwrap();
+        assert!(peer.details().is_err());
+    }
+
+    #[test]
+    #[serial]
+    fn move_to_netns() {
+        let iface = CanInterface::create_vcan("netns", None).unwrap();
+        let netns = scratch_netns();
+
+        iface
+            .move_to_netns(NetNs::Fd(netns.as_raw_fd()))
+            .map_err(|(_, err)| err)
+            .unwrap();
+        assert!(CanInterface::open("netns").is_err());
+
+        in_netns(&netns, || {
+            let iface = CanInterface::open("netns").unwrap();
+            assert_eq!(Some("netns"), iface.details().unwrap().name.as_deref());
+            iface.delete().unwrap();
+        });
+    }
+
+    #[test]
+    #[serial]
+    fn reconfigure() {
+        let interface = TemporaryInterface::new("reconfig").unwrap();
+        interface.bring_up().unwrap();
+
+        // A vcan doesn't take CAN parameters, so this fails before anything
+        // is applied, and leaves the interface up. See reconfigure_rollback()
+        // for values that get put back.
+        let params = SetCanParams {
+            bit_timing: Some(CanBitTiming {
+                bitrate: 500_000,
+                ..CanBitTiming::default()
+            }),
+            mtu: Some(Mtu::Fd),
+            ..SetCanParams::default()
+        };
+        assert!(interface.reconfigure(&params).is_err());
+
+        let details = interface.details().unwrap();
+        assert!(details.is_up);
+        assert_eq!(Some(Mtu::Standard), details.mtu);
+    }
+
+    /// Parses the CAN parameters back out of a message to set them.
+    fn sent_can_params(info: &Ifinfomsg) -> InterfaceCanParams {
+        let link_info = info
+            .rtattrs
+            .iter()
+            .find(|attr| attr.rta_type == Ifla::Linkinfo)
+            .unwrap();
+        InterfaceCanParams::try_from(link_info).unwrap()
+    }
+
+    /// A bit timing like the kernel reports, with the bitrate, sample
+    /// point, and the time quantum and segments that it calculated.
+    fn reported_timing(bitrate: u32, tq: u32) -> CanBitTiming {
+        CanBitTiming {
+            bitrate,
+            sample_point: 875,
+            tq,
+            prop_seg: 6,
+            phase_seg1: 7,
+            phase_seg2: 2,
+            sjw: 1,
+            brp: 1,
+        }
+    }
+
+    #[test]
+    fn tdc_params() {
+        let mut can = InterfaceCanParams::default();
+        assert!(can.tdc_params(Some(CanTdc::auto(5))).is_err());
+
+        can.bit_timing = Some(reported_timing(500_000, 125));
+        can.data_bit_timing = Some(reported_timing(2_000_000, 25));
+
+        let params = can.tdc_params(Some(CanTdc::manual(10, 5))).unwrap();
+        let info = CanInterface::open_iface(1).can_params_msg(&params).unwrap();
+        let sent = sent_can_params(&info);
+
+        // Only the time quantum and segments of the timings are sent
+        for (sent, reported) in [
+            (sent.bit_timing.unwrap(), can.bit_timing.unwrap()),
+            (sent.data_bit_timing.unwrap(), can.data_bit_timing.unwrap()),
+        ] {
+            assert_eq!((0, 0), (sent.bitrate, sent.sample_point));
+            assert_eq!(
+                (
+                    reported.tq,
+                    reported.prop_seg,
+                    reported.phase_seg1,
+                    reported.phase_seg2,
+                    reported.sjw,
+                    reported.brp
+                ),
+                (
+                    sent.tq,
+                    sent.prop_seg,
+                    sent.phase_seg1,
+                    sent.phase_seg2,
+                    sent.sjw,
+                    sent.brp
+                )
+            );
+        }
+
+        let modes: can_ctrlmode = sent.ctrl_mode.into();
+        assert_eq!(
+            CanCtrlMode::Fd.mask() | CanCtrlMode::TdcManual.mask(),
+            modes.flags
+        );
+        assert_eq!(Some(CanTdc::manual(10, 5)), sent.tdc);
+    }
+
+    /// A link that takes the messages from `reconfigure_steps()` like the
+    /// kernel would, or fails to come up, to test the rollback.
+    #[derive(Debug)]
+    struct FakeLink {
+        details: InterfaceDetails,
+        fail_up: bool,
+    }
+
+    impl FakeLink {
+        fn send(&mut self, info: Ifinfomsg) -> NlResult<()> {
+            let err = |msg: &str| Err(NlError::Msg(msg.into()));
+
+            if info.ifi_change.contains(&Iff::Up) {
+                let up = info.ifi_flags.contains(&Iff::Up);
+                if up && self.fail_up {
+                    return err("ENETDOWN");
+                }
+                self.details.is_up = up;
+                return Ok(());
+            }
+            if self.details.is_up {
+                return err("EBUSY");
+            }
+
+            for attr in info.rtattrs.iter() {
+                if attr.rta_type == Ifla::Mtu {
+                    self.details.mtu = Mtu::try_from(attr.get_payload_as::<u32>().unwrap()).ok();
+                }
+            }
+
+            // The kernel calculates the segments from a bitrate, or the
+            // bitrate from the segments, but won't take both.
+            if let Some(bt) = sent_can_params(&info).bit_timing {
+                let bt = match (bt.bitrate, bt.tq) {
+                    (bitrate, 0) => reported_timing(bitrate, 1_000_000_000 / (bitrate * 16)),
+                    (0, tq) => {
+                        let bit_time = 1 + bt.prop_seg + bt.phase_seg1 + bt.phase_seg2;
+                        CanBitTiming {
+                            bitrate: 1_000_000_000 / (tq * bit_time),
+                            sample_point: 875,
+                            ..bt
+                        }
+                    }
+                    _ => return err("EINVAL"),
+                };
+                self.details.can.bit_timing = Some(bt);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn reconfigure_rollback() {
+        let iface = CanInterface::open_iface(1);
+        let mut prev = InterfaceDetails::new(1);
+        prev.is_up = true;
+        prev.mtu = Some(Mtu::Standard);
+        prev.can.bit_timing = Some(reported_timing(500_000, 125));
+
+        let params = SetCanParams {
+            bit_timing: Some(CanBitTiming {
+                bitrate: 250_000,
+                ..CanBitTiming::default()
+            }),
+            mtu: Some(Mtu::Fd),
+            ..SetCanParams::default()
+        };
+        let bitrate = |link: &FakeLink| link.details.can.bit_timing.unwrap().bitrate;
+
+        let mut link = FakeLink {
+            details: prev.clone(),
+            fail_up: false,
+        };
+        let steps = iface.reconfigure_steps(&prev, &params).unwrap();
+        steps.run(|info| link.send(info)).unwrap();
+        assert!(link.details.is_up);
+        assert_eq!(Some(Mtu::Fd), link.details.mtu);
+        assert_eq!(250_000, bitrate(&link));
+
+        // The new values are set, then put back when it fails to come up
+        let mut link = FakeLink {
+            details: prev.clone(),
+            fail_up: true,
+        };
+        let mut bitrates = Vec::new();
+        let steps = iface.reconfigure_steps(&prev, &params).unwrap();
+        let res = steps.run(|info| {
+            let res = link.send(info);
+            bitrates.push(bitrate(&link));
+            res
+        });
+        assert!(matches!(res, Err(NlError::Msg(msg)) if msg == "ENETDOWN"));
+        assert!(bitrates.contains(&250_000));
+        assert_eq!(Some(Mtu::Standard), link.details.mtu);
+        assert_eq!(500_000, bitrate(&link));
+        assert_eq!(125, link.details.can.bit_timing.unwrap().tq);
+    }
+
+    #[test]
+    fn undo() {
+        let mut prev = InterfaceDetails::new(1);
+        prev.mtu = Some(Mtu::Fd);
+        prev.can.restart_ms = 100;
+        prev.can.bit_timing = Some(CanBitTiming {
+            bitrate: 500_000,
+            ..CanBitTiming::default()
+        });
+        prev.can.data_bit_timing = Some(CanBitTiming {
+            bitrate: 2_000_000,
+            ..CanBitTiming::default()
+        });
+        prev.can.ctrl_mode = CanCtrlModes::new(0, CanCtrlMode::Fd.mask());
+
+        let params = SetCanParams {
+            restart_ms: Some(0),
+            mtu: Some(Mtu::Standard),
+            ..SetCanParams::default()
+        };
+        let undo = params.undo(&prev);
+        assert_eq!(Some(100), undo.restart_ms);
+        assert_eq!(Some(Mtu::Fd), undo.mtu);
+        assert!(undo.bit_timing.is_none());
+        assert!(undo.ctrl_mode.is_none());
+
+        // Turning FD back on needs both bit timings
+        let params = SetCanParams {
+            ctrl_mode: Some(CanCtrlModes::from_mode(CanCtrlMode::Fd, false)),
+            ..SetCanParams::default()
+        };
+        let undo = params.undo(&prev);
+        let modes: can_ctrlmode = undo.ctrl_mode.unwrap().into();
+        assert_eq!(CanCtrlMode::Fd.mask(), modes.flags);
+        assert_eq!(500_000, undo.bit_timing.unwrap().bitrate);
+        assert_eq!(2_000_000, undo.data_bit_timing.unwrap().bitrate);
+    }
+
+    #[test]
+    fn supported() {
+        let mut params = InterfaceCanParams::default();
+        assert!(params.supports_bitrate(250_000));
+        assert!(params.supports_termination(120));
+        assert!(params.supports_ctrlmode(CanCtrlMode::Fd));
+
+        params.bitrate_const = vec![125_000, 250_000, 500_000];
+        params.bitrate_max = Some(500_000);
+        params.termination_const = vec![0, 120];
+        params.ctrl_mode_supported = Some(CanCtrlMode::Loopback.mask());
+
+        assert!(params.supports_bitrate(250_000));
+        assert!(!params.supports_bitrate(1_000_000));
+        assert!(!params.supports_data_bitrate(2_000_000));
+        assert!(!params.supports_termination(60));
+        assert!(params.supports_ctrlmode(CanCtrlMode::Loopback));
+        assert!(!params.supports_ctrlmode(CanCtrlMode::Fd));
+    }
+
     #[test]
     #[serial]
     fn mtu() {
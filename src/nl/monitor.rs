@@ -0,0 +1,449 @@
+// socketcan/src/nl/monitor.rs
+//
+// Netlink monitor for changes to the SocketCAN interfaces.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Netlink monitor for changes to the CAN interfaces.
+//!
+//! The kernel multicasts a message to the `RTNLGRP_LINK` netlink group
+//! whenever a network interface is added, removed, or changed. The
+//! [`LinkMonitor`] listens for these, and turns the ones for CAN interfaces
+//! into [`LinkEvent`]s, like an interface appearing, going up or down, or
+//! changing its bus state.
+//!
+//! ```no_run
+//! use socketcan::nl::{LinkEventKind, LinkMonitor};
+//!
+//! let monitor = LinkMonitor::new().unwrap();
+//!
+//! for event in monitor {
+//!     let event = event.unwrap();
+//!     if event.kind == LinkEventKind::Up {
+//!         println!("Interface {:?} is up", event.details.name);
+//!     }
+//! }
+//! ```
+
+use super::{is_can_link, CanInterface, InterfaceDetails, NlInfoError};
+use neli::{
+    consts::{rtnl::Rtm, socket::NlFamily},
+    rtnl::Ifinfomsg,
+    socket::NlSocketHandle,
+};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt, io,
+    os::{
+        raw::c_uint,
+        unix::io::{AsRawFd, RawFd},
+    },
+};
+
+/// The netlink multicast group for link changes.
+const RTNLGRP_LINK: u32 = 1;
+
+/// The kind of change to a CAN interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LinkEventKind {
+    /// The interface was added to the system
+    Added,
+    /// The interface was removed from the system
+    Removed,
+    /// The interface was renamed
+    Renamed,
+    /// The interface was brought up
+    Up,
+    /// The interface was brought down
+    Down,
+    /// The CAN bus state changed, like going into error passive or bus-off
+    StateChanged,
+    /// The bitrate, or the FD data bitrate, changed
+    BitrateChanged,
+    /// The control modes changed
+    CtrlModeChanged,
+}
+
+/// A change to a CAN interface
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinkEvent {
+    /// What changed
+    pub kind: LinkEventKind,
+    /// The details of the interface after the change, or, if it was
+    /// removed, the last details that were seen.
+    pub details: InterfaceDetails,
+}
+
+impl LinkEvent {
+    /// Creates a new event
+    fn new(kind: LinkEventKind, details: &InterfaceDetails) -> Self {
+        Self {
+            kind,
+            details: details.clone(),
+        }
+    }
+}
+
+/// A monitor for changes to the CAN interfaces on the system.
+///
+/// This is an iterator that blocks until the next event. For use with an
+/// async runtime, it can be put into non-blocking mode, and then
+/// [`next_event()`](Self::next_event) returns `None` when no event is
+/// ready. The `tokio` and `async_io` modules have streams that wrap it.
+///
+/// The monitor keeps the details of the CAN interfaces that it has seen,
+/// starting with the ones that exist when it is created, to tell what
+/// changed in each message from the kernel. A single message can produce
+/// several events, like when an interface is brought up and its bus state
+/// changes at the same time.
+///
+/// If the monitor falls too far behind, the kernel drops messages, and the
+/// next read fails with `ENOBUFS`. The monitor can keep being used after
+/// that, but might have missed some changes. Calling
+/// [`resync()`](Self::resync) reloads the interfaces, and reports any
+/// changes since they were last seen.
+pub struct LinkMonitor {
+    /// The netlink socket, subscribed to the link group
+    sock: NlSocketHandle,
+    /// The CAN interfaces that were seen, by index
+    links: HashMap<c_uint, InterfaceDetails>,
+    /// Events waiting to be returned
+    events: VecDeque<LinkEvent>,
+}
+
+impl LinkMonitor {
+    /// Creates a monitor for the CAN interfaces.
+    ///
+    /// This subscribes to the link changes, then reads the details of the
+    /// CAN interfaces that already exist, which do not produce events.
+    pub fn new() -> Result<Self, NlInfoError> {
+        let sock = NlSocketHandle::connect(NlFamily::Route, None, &[RTNLGRP_LINK])?;
+        let links = CanInterface::list()?
+            .into_iter()
+            .map(|details| (details.index, details))
+            .collect();
+        Ok(Self {
+            sock,
+            links,
+            events: VecDeque::new(),
+        })
+    }
+
+    /// Sets the monitor into blocking or non-blocking mode.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        if nonblocking {
+            self.sock.nonblock()
+        } else {
+            self.sock.block()
+        }
+    }
+
+    /// Gets the details of the CAN interfaces that are currently known.
+    pub fn interfaces(&self) -> impl Iterator<Item = &InterfaceDetails> {
+        self.links.values()
+    }
+
+    /// Gets the next event.
+    ///
+    /// In blocking mode, this waits until there is an event. In
+    /// non-blocking mode, this returns `None` if there isn't one ready.
+    pub fn next_event(&mut self) -> Result<Option<LinkEvent>, NlInfoError> {
+        loop {
+            if let Some(event) = self.events.pop_front() {
+                return Ok(Some(event));
+            }
+            match self.sock.recv::<Rtm, Ifinfomsg>()? {
+                Some(msg) => {
+                    if let Ok(payload) = msg.get_payload() {
+                        self.update(msg.nl_type, payload)?;
+                    }
+                }
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Reloads the details of the CAN interfaces, queueing events for any
+    /// changes since they were last seen.
+    ///
+    /// This is meant to catch up after messages were dropped by the kernel.
+    pub fn resync(&mut self) -> Result<(), NlInfoError> {
+        let links: HashMap<_, _> = CanInterface::list()?
+            .into_iter()
+            .map(|details| (details.index, details))
+            .collect();
+
+        let mut removed: Vec<_> = self
+            .links
+            .keys()
+            .filter(|index| !links.contains_key(index))
+            .copied()
+            .collect();
+        removed.sort_unstable();
+        for index in removed {
+            if let Some(old) = self.links.remove(&index) {
+                self.events
+                    .push_back(LinkEvent::new(LinkEventKind::Removed, &old));
+            }
+        }
+
+        let mut links: Vec<_> = links.into_values().collect();
+        links.sort_unstable_by_key(|details| details.index);
+        for details in links {
+            self.changed(details);
+        }
+        Ok(())
+    }
+
+    /// Updates the interfaces from a message from the kernel.
+    fn update(&mut self, msg_type: Rtm, payload: &Ifinfomsg) -> Result<(), NlInfoError> {
+        if !is_can_link(payload) {
+            return Ok(());
+        }
+        let details = InterfaceDetails::try_from(payload)?;
+
+        match msg_type {
+            Rtm::Newlink => self.changed(details),
+            Rtm::Dellink => {
+                let details = self.links.remove(&details.index).unwrap_or(details);
+                self.events
+                    .push_back(LinkEvent::new(LinkEventKind::Removed, &details));
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    /// Queues the events for the new details of an interface.
+    fn changed(&mut self, details: InterfaceDetails) {
+        use LinkEventKind::*;
+
+        let Some(old) = self.links.get(&details.index) else {
+            self.events.push_back(LinkEvent::new(Added, &details));
+            self.links.insert(details.index, details);
+            return;
+        };
+
+        let bitrate = |d: &InterfaceDetails| {
+            (
+                d.can.bit_timing.map(|bt| bt.bitrate),
+                d.can.data_bit_timing.map(|bt| bt.bitrate),
+            )
+        };
+
+        let mut kinds = Vec::new();
+        if old.name != details.name {
+            kinds.push(Renamed);
+        }
+        if old.is_up != details.is_up {
+            kinds.push(if details.is_up { Up } else { Down });
+        }
+        if old.can.state != details.can.state {
+            kinds.push(StateChanged);
+        }
+        if bitrate(old) != bitrate(&details) {
+            kinds.push(BitrateChanged);
+        }
+        if old.can.ctrl_mode.0.flags != details.can.ctrl_mode.0.flags {
+            kinds.push(CtrlModeChanged);
+        }
+
+        for kind in kinds {
+            self.events.push_back(LinkEvent::new(kind, &details));
+        }
+        self.links.insert(details.index, details);
+    }
+}
+
+impl fmt::Debug for LinkMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LinkMonitor")
+            .field("fd", &self.sock.as_raw_fd())
+            .field("links", &self.links)
+            .field("events", &self.events)
+            .finish()
+    }
+}
+
+impl Iterator for LinkMonitor {
+    type Item = Result<LinkEvent, NlInfoError>;
+
+    /// Gets the next event, blocking until there is one.
+    ///
+    /// In non-blocking mode, this returns `None` if no event is ready, but
+    /// the monitor can still be used after that.
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event().transpose()
+    }
+}
+
+impl AsRawFd for LinkMonitor {
+    fn as_raw_fd(&self) -> RawFd {
+        self.sock.as_raw_fd()
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nl::{rt::can_ctrlmode, CanBitTiming, CanCtrlModes, CanState};
+    use neli::{
+        consts::rtnl::{Arphrd, Iff, IffFlags, Ifla, RtAddrFamily},
+        rtnl::Rtattr,
+        types::RtBuffer,
+    };
+    use std::os::raw::c_int;
+
+    fn monitor() -> LinkMonitor {
+        LinkMonitor {
+            sock: NlSocketHandle::connect(NlFamily::Route, None, &[]).unwrap(),
+            links: HashMap::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    // A link message from the kernel, with the type of the hardware
+    fn link_msg(ifi_type: Arphrd, index: c_int, name: &str, up: bool) -> Ifinfomsg {
+        let mut attrs = RtBuffer::new();
+        attrs.push(Rtattr::new(None, Ifla::Ifname, name).unwrap());
+        let flags = if up {
+            IffFlags::new(&[Iff::Up])
+        } else {
+            IffFlags::empty()
+        };
+        Ifinfomsg::new(
+            RtAddrFamily::Unspecified,
+            ifi_type,
+            index,
+            flags,
+            IffFlags::empty(),
+            attrs,
+        )
+    }
+
+    fn can_msg(index: c_int, name: &str, up: bool) -> Ifinfomsg {
+        link_msg(Arphrd::from(libc::ARPHRD_CAN), index, name, up)
+    }
+
+    fn kinds(monitor: &mut LinkMonitor) -> Vec<LinkEventKind> {
+        monitor.events.drain(..).map(|ev| ev.kind).collect()
+    }
+
+    #[test]
+    fn test_changes() {
+        use LinkEventKind::*;
+
+        let mut monitor = monitor();
+        let mut details = InterfaceDetails::new(5);
+        details.name = Some("can0".into());
+
+        monitor.changed(details.clone());
+        assert_eq!(vec![Added], kinds(&mut monitor));
+        assert_eq!(1, monitor.interfaces().count());
+
+        monitor.changed(details.clone());
+        assert!(kinds(&mut monitor).is_empty());
+
+        details.is_up = true;
+        details.can.state = Some(CanState::ErrorActive);
+        monitor.changed(details.clone());
+        assert_eq!(vec![Up, StateChanged], kinds(&mut monitor));
+
+        details.can.bit_timing = Some(CanBitTiming {
+            bitrate: 500_000,
+            ..CanBitTiming::default()
+        });
+        details.can.ctrl_mode = CanCtrlModes(can_ctrlmode {
+            mask: 0,
+            flags: 0x20,
+        });
+        monitor.changed(details.clone());
+        assert_eq!(vec![BitrateChanged, CtrlModeChanged], kinds(&mut monitor));
+
+        details.name = Some("can1".into());
+        details.is_up = false;
+        monitor.changed(details.clone());
+        let events: Vec<_> = monitor.events.drain(..).collect();
+        assert_eq!(2, events.len());
+        assert_eq!(Renamed, events[0].kind);
+        assert_eq!(Down, events[1].kind);
+        assert_eq!(Some("can1"), events[1].details.name.as_deref());
+    }
+
+    #[test]
+    fn test_update() {
+        use LinkEventKind::*;
+
+        let mut monitor = monitor();
+
+        monitor
+            .update(Rtm::Newlink, &can_msg(3, "can0", false))
+            .unwrap();
+        let events: Vec<_> = monitor.events.drain(..).collect();
+        assert_eq!(1, events.len());
+        assert_eq!(Added, events[0].kind);
+        assert_eq!(3, events[0].details.index);
+        assert_eq!(Some("can0"), events[0].details.name.as_deref());
+        assert!(!events[0].details.is_up);
+
+        monitor
+            .update(Rtm::Newlink, &can_msg(3, "can0", true))
+            .unwrap();
+        assert_eq!(vec![Up], kinds(&mut monitor));
+        assert!(monitor.interfaces().all(|details| details.is_up));
+
+        // Other kinds of messages are ignored
+        monitor
+            .update(Rtm::Getlink, &can_msg(3, "can0", false))
+            .unwrap();
+        assert!(kinds(&mut monitor).is_empty());
+
+        // A removed link reports the last details that were seen
+        monitor
+            .update(Rtm::Dellink, &can_msg(3, "", false))
+            .unwrap();
+        let events: Vec<_> = monitor.events.drain(..).collect();
+        assert_eq!(1, events.len());
+        assert_eq!(Removed, events[0].kind);
+        assert_eq!(Some("can0"), events[0].details.name.as_deref());
+        assert!(events[0].details.is_up);
+        assert_eq!(0, monitor.interfaces().count());
+    }
+
+    #[test]
+    fn test_non_can_links() {
+        let mut monitor = monitor();
+
+        for msg_type in [Rtm::Newlink, Rtm::Dellink] {
+            monitor
+                .update(msg_type, &link_msg(Arphrd::Ether, 2, "eth0", true))
+                .unwrap();
+            monitor
+                .update(msg_type, &link_msg(Arphrd::Loopback, 1, "lo", true))
+                .unwrap();
+        }
+        assert!(monitor.events.is_empty());
+        assert_eq!(0, monitor.interfaces().count());
+
+        // A non-CAN link with the index of a known CAN interface doesn't
+        // touch it.
+        monitor
+            .update(Rtm::Newlink, &can_msg(2, "can0", true))
+            .unwrap();
+        monitor.events.clear();
+        monitor
+            .update(Rtm::Dellink, &link_msg(Arphrd::Ether, 2, "eth0", false))
+            .unwrap();
+        assert!(monitor.events.is_empty());
+        assert_eq!(1, monitor.interfaces().count());
+    }
+}
@@ -48,6 +48,7 @@ pub const EXT_FILTER_MST: c_uint = 1 << 7;
 ///
 #[repr(C)]
 #[derive(Debug, Default, Clone, Copy, FromBytes, ToBytes, Size)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct can_bittiming {
     pub bitrate: u32,      // Bit-rate in bits/second
     pub sample_point: u32, // Sample point in one-tenth of a percent
@@ -66,6 +67,7 @@ pub struct can_bittiming {
 ///
 #[repr(C)]
 #[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct can_bittiming_const {
     pub name: [c_char; 16], // Name of the CAN controller hardware
     pub tseg1_min: u32,     // Time segment 1 = prop_seg + phase_seg1
@@ -103,6 +105,7 @@ impl Size for can_bittiming_const {
 ///
 #[repr(C)]
 #[derive(Debug, Default, Clone, Copy, FromBytes, ToBytes, Size)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct can_clock {
     pub freq: u32, // CAN system clock frequency in Hz
 }
@@ -111,6 +114,7 @@ pub struct can_clock {
 ///
 #[repr(u32)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CanState {
     /// RX/TX error count < 96
     ErrorActive,
@@ -148,6 +152,7 @@ impl TryFrom<u32> for CanState {
 ///
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone, FromBytes, ToBytes, Size)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct can_berr_counter {
     pub txerr: u16,
     pub rxerr: u16,
@@ -160,6 +165,7 @@ pub struct can_berr_counter {
 ///
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone, FromBytes, ToBytes, Size)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct can_ctrlmode {
     pub mask: u32,
     pub flags: u32,
@@ -183,6 +189,10 @@ pub const CAN_CTRLMODE_PRESUME_ACK: u32 = 0x40;
 pub const CAN_CTRLMODE_FD_NON_ISO: u32 = 0x80;
 /// Classic CAN DLC option
 pub const CAN_CTRLMODE_CC_LEN8_DLC: u32 = 0x100;
+/// The transceiver automatically calculates the TDCV
+pub const CAN_CTRLMODE_TDC_AUTO: u32 = 0x200;
+/// The TDCV is manually set up by the user
+pub const CAN_CTRLMODE_TDC_MANUAL: u32 = 0x400;
 
 /// u16 termination range: 1..65535 Ohms
 pub const CAN_TERMINATION_DISABLED: u32 = 0;
@@ -192,6 +202,7 @@ pub const CAN_TERMINATION_DISABLED: u32 = 0;
 ///
 #[repr(C)]
 #[derive(Debug, Default, Copy, Clone, FromBytes)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct can_device_stats {
     pub bus_error: u32,        // Bus errors
     pub error_warning: u32,    // Changes to error warning state
@@ -201,6 +212,69 @@ pub struct can_device_stats {
     pub restarts: u32,         // CAN controller re-starts
 }
 
+///
+/// Network interface statistics
+/// Missing from libc, from linux/if_link.h:
+///
+/// Newer kernels append fields to the end, so older ones send a shorter
+/// struct. Any fields not sent by the kernel are left as zero.
+///
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct rtnl_link_stats64 {
+    pub rx_packets: u64, // Total packets received
+    pub tx_packets: u64, // Total packets transmitted
+    pub rx_bytes: u64,   // Total bytes received
+    pub tx_bytes: u64,   // Total bytes transmitted
+    pub rx_errors: u64,  // Bad packets received
+    pub tx_errors: u64,  // Packet transmit problems
+    pub rx_dropped: u64, // Received packets dropped, like for no buffer space
+    pub tx_dropped: u64, // Transmit packets dropped, like for no buffer space
+    pub multicast: u64,  // Multicast packets received
+    pub collisions: u64,
+
+    // Detailed rx_errors
+    pub rx_length_errors: u64,
+    pub rx_over_errors: u64, // Receiver ring buffer overflow
+    pub rx_crc_errors: u64,  // Received packets with CRC error
+    pub rx_frame_errors: u64,
+    pub rx_fifo_errors: u64,   // Receiver FIFO overrun
+    pub rx_missed_errors: u64, // Receiver missed packets
+
+    // Detailed tx_errors
+    pub tx_aborted_errors: u64,
+    pub tx_carrier_errors: u64,
+    pub tx_fifo_errors: u64,
+    pub tx_heartbeat_errors: u64,
+    pub tx_window_errors: u64,
+
+    // For cslip etc
+    pub rx_compressed: u64,
+    pub tx_compressed: u64,
+
+    pub rx_nohandler: u64,         // Packets dropped for no protocol handler
+    pub rx_otherhost_dropped: u64, // Packets dropped for another host
+}
+
+impl<'a> FromBytes<'a> for rtnl_link_stats64 {
+    fn from_bytes(buf: &mut Cursor<&'a [u8]>) -> Result<Self, DeError> {
+        let mut stats = rtnl_link_stats64::default();
+        let n = buf.read(as_bytes_mut(&mut stats))?;
+        if n % size_of::<u64>() != 0 {
+            return Err(DeError::UnexpectedEOB);
+        }
+        Ok(stats)
+    }
+}
+
+/// Flag set on the type of a nested attribute
+pub const NLA_F_NESTED: u16 = 1 << 15;
+/// Flag set on the type of an attribute in network byte order
+pub const NLA_F_NET_BYTEORDER: u16 = 1 << 14;
+/// Mask for the type of an attribute, without the flags
+pub const NLA_TYPE_MASK: u16 = !(NLA_F_NESTED | NLA_F_NET_BYTEORDER);
+
 pub const IFLA_CAN_UNSPEC: u16 = 0;
 pub const IFLA_CAN_BITTIMING: u16 = 1;
 pub const IFLA_CAN_BITTIMING_CONST: u16 = 2;
@@ -246,6 +320,147 @@ pub enum IflaCan {
 
 impl RtaType for IflaCan {}
 
+pub const IFLA_CAN_TDC_UNSPEC: u16 = 0;
+pub const IFLA_CAN_TDC_TDCV_MIN: u16 = 1;
+pub const IFLA_CAN_TDC_TDCV_MAX: u16 = 2;
+pub const IFLA_CAN_TDC_TDCO_MIN: u16 = 3;
+pub const IFLA_CAN_TDC_TDCO_MAX: u16 = 4;
+pub const IFLA_CAN_TDC_TDCF_MIN: u16 = 5;
+pub const IFLA_CAN_TDC_TDCF_MAX: u16 = 6;
+pub const IFLA_CAN_TDC_TDCV: u16 = 7;
+pub const IFLA_CAN_TDC_TDCO: u16 = 8;
+pub const IFLA_CAN_TDC_TDCF: u16 = 9;
+
+/// CAN FD Transmitter Delay Compensation (TDC), nested in `IFLA_CAN_TDC`
+///
+#[neli_enum(serialized_type = "libc::c_ushort")]
+pub enum IflaCanTdc {
+    Unspec = IFLA_CAN_TDC_UNSPEC,
+    TdcvMin = IFLA_CAN_TDC_TDCV_MIN,
+    TdcvMax = IFLA_CAN_TDC_TDCV_MAX,
+    TdcoMin = IFLA_CAN_TDC_TDCO_MIN,
+    TdcoMax = IFLA_CAN_TDC_TDCO_MAX,
+    TdcfMin = IFLA_CAN_TDC_TDCF_MIN,
+    TdcfMax = IFLA_CAN_TDC_TDCF_MAX,
+    Tdcv = IFLA_CAN_TDC_TDCV,
+    Tdco = IFLA_CAN_TDC_TDCO,
+    Tdcf = IFLA_CAN_TDC_TDCF,
+}
+
+impl RtaType for IflaCanTdc {}
+
+pub const IFLA_CAN_CTRLMODE_UNSPEC: u16 = 0;
+pub const IFLA_CAN_CTRLMODE_SUPPORTED: u16 = 1;
+
+/// Extended control mode info, nested in `IFLA_CAN_CTRLMODE_EXT`
+///
+#[neli_enum(serialized_type = "libc::c_ushort")]
+pub enum IflaCanCtrlModeExt {
+    Unspec = IFLA_CAN_CTRLMODE_UNSPEC,
+    Supported = IFLA_CAN_CTRLMODE_SUPPORTED,
+}
+
+impl RtaType for IflaCanCtrlModeExt {}
+
+pub const VXCAN_INFO_UNSPEC: u16 = 0;
+pub const VXCAN_INFO_PEER: u16 = 1;
+
+/// vxcan tunnel info, nested in the link info data
+///
+#[neli_enum(serialized_type = "libc::c_ushort")]
+pub enum VxcanInfo {
+    Unspec = VXCAN_INFO_UNSPEC,
+    Peer = VXCAN_INFO_PEER,
+}
+
+impl RtaType for VxcanInfo {}
+
+// CAN gateway definitions
+// Missing from libc, from linux/can/gw.h:
+
+/// CAN gateway types
+pub const CGW_TYPE_UNSPEC: u8 = 0;
+pub const CGW_TYPE_CAN_CAN: u8 = 1;
+
+pub const CGW_UNSPEC: u16 = 0;
+pub const CGW_MOD_AND: u16 = 1;
+pub const CGW_MOD_OR: u16 = 2;
+pub const CGW_MOD_XOR: u16 = 3;
+pub const CGW_MOD_SET: u16 = 4;
+pub const CGW_CS_XOR: u16 = 5;
+pub const CGW_CS_CRC8: u16 = 6;
+pub const CGW_HANDLED: u16 = 7;
+pub const CGW_DROPPED: u16 = 8;
+pub const CGW_SRC_IF: u16 = 9;
+pub const CGW_DST_IF: u16 = 10;
+pub const CGW_FILTER: u16 = 11;
+pub const CGW_DELETED: u16 = 12;
+pub const CGW_LIM_HOPS: u16 = 13;
+pub const CGW_MOD_UID: u16 = 14;
+pub const CGW_FDMOD_AND: u16 = 15;
+pub const CGW_FDMOD_OR: u16 = 16;
+pub const CGW_FDMOD_XOR: u16 = 17;
+pub const CGW_FDMOD_SET: u16 = 18;
+
+/// CAN gateway netlink attributes
+///
+#[neli_enum(serialized_type = "libc::c_ushort")]
+pub enum Cgw {
+    Unspec = CGW_UNSPEC,
+    ModAnd = CGW_MOD_AND,
+    ModOr = CGW_MOD_OR,
+    ModXor = CGW_MOD_XOR,
+    ModSet = CGW_MOD_SET,
+    CsXor = CGW_CS_XOR,
+    CsCrc8 = CGW_CS_CRC8,
+    Handled = CGW_HANDLED,
+    Dropped = CGW_DROPPED,
+    SrcIf = CGW_SRC_IF,
+    DstIf = CGW_DST_IF,
+    Filter = CGW_FILTER,
+    Deleted = CGW_DELETED,
+    LimHops = CGW_LIM_HOPS,
+    ModUid = CGW_MOD_UID,
+    FdModAnd = CGW_FDMOD_AND,
+    FdModOr = CGW_FDMOD_OR,
+    FdModXor = CGW_FDMOD_XOR,
+    FdModSet = CGW_FDMOD_SET,
+}
+
+impl RtaType for Cgw {}
+
+/// Echo the frames back to the sending socket
+pub const CGW_FLAGS_CAN_ECHO: u16 = 0x01;
+/// Keep the timestamp of the source frame
+pub const CGW_FLAGS_CAN_SRC_TSTAMP: u16 = 0x02;
+/// Allow sending back out of the interface that the frame came in on
+pub const CGW_FLAGS_CAN_IIF_TX_OK: u16 = 0x04;
+/// Route CAN FD frames
+pub const CGW_FLAGS_CAN_FD: u16 = 0x08;
+
+/// Modify the CAN ID
+pub const CGW_MOD_ID: u8 = 0x01;
+/// Modify the data length code (DLC), or the length of an FD frame
+pub const CGW_MOD_DLC: u8 = 0x02;
+/// Modify the data
+pub const CGW_MOD_DATA: u8 = 0x04;
+/// Modify the FD flags
+pub const CGW_MOD_FLAGS: u8 = 0x08;
+
+/// The size of a `struct cgw_frame_mod`
+pub const CGW_MODATTR_LEN: usize = 17;
+/// The size of a `struct cgw_fdframe_mod`
+pub const CGW_FDMODATTR_LEN: usize = 73;
+/// The size of a `struct cgw_csum_xor`
+pub const CGW_CS_XOR_LEN: usize = 4;
+/// The size of a `struct cgw_csum_crc8`
+pub const CGW_CS_CRC8_LEN: usize = 282;
+
+pub const CGW_CRC8PRF_UNSPEC: u8 = 0;
+pub const CGW_CRC8PRF_1U8: u8 = 1;
+pub const CGW_CRC8PRF_16U8: u8 = 2;
+pub const CGW_CRC8PRF_SFFID_XOR: u8 = 3;
+
 /////////////////////////////////////////////////////////////////////////////
 
 #[cfg(test)]
@@ -272,4 +487,28 @@ pub mod tests {
             as_bytes(&timing)
         );
     }
+
+    #[test]
+    fn test_link_stats_from_bytes() {
+        // An older kernel might send fewer fields
+        let buf: Vec<u8> = [3u64, 4, 300, 400]
+            .iter()
+            .flat_map(|n| n.to_ne_bytes())
+            .collect();
+        let stats = rtnl_link_stats64::from_bytes(&mut Cursor::new(&buf[..])).unwrap();
+        assert_eq!(3, stats.rx_packets);
+        assert_eq!(4, stats.tx_packets);
+        assert_eq!(300, stats.rx_bytes);
+        assert_eq!(400, stats.tx_bytes);
+        assert_eq!(0, stats.rx_errors);
+
+        let stats = rtnl_link_stats64 {
+            rx_otherhost_dropped: 7,
+            ..rtnl_link_stats64::default()
+        };
+        let stats = rtnl_link_stats64::from_bytes(&mut Cursor::new(as_bytes(&stats))).unwrap();
+        assert_eq!(7, stats.rx_otherhost_dropped);
+
+        assert!(rtnl_link_stats64::from_bytes(&mut Cursor::new(&buf[..5])).is_err());
+    }
 }
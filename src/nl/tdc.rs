@@ -0,0 +1,218 @@
+// socketcan/src/nl/tdc.rs
+//
+// CAN FD transmitter delay compensation parameters.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! CAN FD transmitter delay compensation (TDC) parameters.
+//!
+//! These are sent and received as attributes nested in `IFLA_CAN_TDC`,
+//! which the kernel flags with `NLA_F_NESTED`, and requires that flag on
+//! the ones that it's sent.
+
+use super::{
+    rt::{IflaCan, IflaCanTdc, NLA_F_NESTED},
+    CanCtrlMode, NlInfoError,
+};
+use neli::{attr::Attribute, err::SerError, rtnl::Rtattr, types::Buffer};
+
+/// CAN FD transmitter delay compensation (TDC) parameters.
+///
+/// At higher data bitrates, the delay through the transceiver can be longer
+/// than the bit time, so the controller needs to compensate for it to check
+/// the bits that it sends. The secondary sample point (SSP), where the
+/// transmitted bits are checked, is the transmitter delay compensation
+/// value (TDCV) plus the offset (TDCO), all in minimum time quanta.
+///
+/// In automatic mode, the controller measures the TDCV itself, and only the
+/// TDCO is set. In manual mode, both are set.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CanTdc {
+    /// The transmitter delay compensation value. This is set for manual
+    /// mode, and left out for automatic mode. When read from an interface
+    /// in automatic mode, this is the value measured by the controller, if
+    /// the driver reports it.
+    pub tdcv: Option<u32>,
+    /// The transmitter delay compensation offset
+    pub tdco: u32,
+    /// The transmitter delay compensation filter window, if the controller
+    /// has one
+    pub tdcf: Option<u32>,
+}
+
+impl CanTdc {
+    /// Creates the parameters for automatic mode, where the controller
+    /// measures the TDCV.
+    pub fn auto(tdco: u32) -> Self {
+        Self {
+            tdco,
+            ..Self::default()
+        }
+    }
+
+    /// Creates the parameters for manual mode, with the TDCV and TDCO.
+    pub fn manual(tdcv: u32, tdco: u32) -> Self {
+        Self {
+            tdcv: Some(tdcv),
+            tdco,
+            tdcf: None,
+        }
+    }
+
+    /// Sets the filter window.
+    pub fn tdcf(mut self, tdcf: u32) -> Self {
+        self.tdcf = Some(tdcf);
+        self
+    }
+
+    /// Gets the control mode for the parameters: manual if they have a
+    /// TDCV, otherwise automatic.
+    ///
+    /// Parameters read from an interface in automatic mode can have the
+    /// measured TDCV, so they look like manual ones. To keep automatic mode
+    /// when setting them again, use `CanTdc::auto(tdc.tdco)`.
+    pub fn mode(&self) -> CanCtrlMode {
+        if self.tdcv.is_some() {
+            CanCtrlMode::TdcManual
+        } else {
+            CanCtrlMode::TdcAuto
+        }
+    }
+
+    /// Creates the nested netlink attribute for the parameters.
+    pub(super) fn to_attr(self) -> Result<Rtattr<IflaCan, Buffer>, SerError> {
+        let tdc_type = IflaCan::from(u16::from(IflaCan::Tdc) | NLA_F_NESTED);
+        let mut attr = Rtattr::new(None, tdc_type, Buffer::new())?;
+        if let Some(tdcv) = self.tdcv {
+            attr.add_nested_attribute(&Rtattr::new(None, IflaCanTdc::Tdcv, tdcv)?)?;
+        }
+        attr.add_nested_attribute(&Rtattr::new(None, IflaCanTdc::Tdco, self.tdco)?)?;
+        if let Some(tdcf) = self.tdcf {
+            attr.add_nested_attribute(&Rtattr::new(None, IflaCanTdc::Tdcf, tdcf)?)?;
+        }
+        Ok(attr)
+    }
+}
+
+/// The limits of the CAN FD transmitter delay compensation (TDC) parameters
+/// for an interface.
+///
+/// Any limits that the interface doesn't report are zero, like the TDCV
+/// limits when it doesn't support manual mode, or the TDCF limits when it
+/// doesn't have a filter window.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CanTdcConst {
+    /// The minimum TDCV
+    pub tdcv_min: u32,
+    /// The maximum TDCV
+    pub tdcv_max: u32,
+    /// The minimum TDCO
+    pub tdco_min: u32,
+    /// The maximum TDCO
+    pub tdco_max: u32,
+    /// The minimum TDCF
+    pub tdcf_min: u32,
+    /// The maximum TDCF
+    pub tdcf_max: u32,
+}
+
+/// Parses the TDC parameters and limits out of a nested `IFLA_CAN_TDC`
+/// attribute.
+///
+/// The parameters are only there when TDC is enabled.
+pub(super) fn parse_tdc(
+    attr: &Rtattr<IflaCan, Buffer>,
+) -> Result<(Option<CanTdc>, Option<CanTdcConst>), NlInfoError> {
+    let mut tdc_const = CanTdcConst::default();
+    let (mut tdcv, mut tdco, mut tdcf) = (None, None, None);
+
+    for tdc_attr in attr.get_attr_handle::<IflaCanTdc>()?.get_attrs() {
+        let val = tdc_attr.get_payload_as::<u32>()?;
+        match tdc_attr.rta_type {
+            IflaCanTdc::TdcvMin => tdc_const.tdcv_min = val,
+            IflaCanTdc::TdcvMax => tdc_const.tdcv_max = val,
+            IflaCanTdc::TdcoMin => tdc_const.tdco_min = val,
+            IflaCanTdc::TdcoMax => tdc_const.tdco_max = val,
+            IflaCanTdc::TdcfMin => tdc_const.tdcf_min = val,
+            IflaCanTdc::TdcfMax => tdc_const.tdcf_max = val,
+            IflaCanTdc::Tdcv => tdcv = Some(val),
+            IflaCanTdc::Tdco => tdco = Some(val),
+            IflaCanTdc::Tdcf => tdcf = Some(val),
+            _ => (),
+        }
+    }
+
+    let tdc = tdco.map(|tdco| CanTdc { tdcv, tdco, tdcf });
+    Ok((tdc, Some(tdc_const)))
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tdc_attr() {
+        let tdc = CanTdc::manual(10, 5).tdcf(2);
+        assert_eq!(CanCtrlMode::TdcManual, tdc.mode());
+        assert_eq!(CanCtrlMode::TdcAuto, CanTdc::auto(5).mode());
+
+        let attr = tdc.to_attr().unwrap();
+        assert_eq!(
+            u16::from(IflaCan::Tdc) | NLA_F_NESTED,
+            u16::from(&attr.rta_type)
+        );
+
+        let (parsed, _) = parse_tdc(&attr).unwrap();
+        assert_eq!(Some(tdc), parsed);
+
+        let (parsed, _) = parse_tdc(&CanTdc::auto(7).to_attr().unwrap()).unwrap();
+        assert_eq!(Some(CanTdc::auto(7)), parsed);
+    }
+
+    #[test]
+    fn test_parse_tdc() {
+        let nested = |ty, val: u32| Rtattr::new(None, ty, val).unwrap();
+
+        // Like the kernel sends for an interface with TDC turned off
+        let mut attr = Rtattr::new(None, IflaCan::Tdc, Buffer::new()).unwrap();
+        attr.add_nested_attribute(&nested(IflaCanTdc::TdcoMin, 0))
+            .unwrap();
+        attr.add_nested_attribute(&nested(IflaCanTdc::TdcoMax, 127))
+            .unwrap();
+
+        let (tdc, tdc_const) = parse_tdc(&attr).unwrap();
+        assert_eq!(None, tdc);
+        assert_eq!(
+            Some(CanTdcConst {
+                tdco_max: 127,
+                ..CanTdcConst::default()
+            }),
+            tdc_const
+        );
+
+        // ...and then turned on in automatic mode
+        attr.add_nested_attribute(&nested(IflaCanTdc::Tdcv, 12))
+            .unwrap();
+        attr.add_nested_attribute(&nested(IflaCanTdc::Tdco, 15))
+            .unwrap();
+
+        let (tdc, _) = parse_tdc(&attr).unwrap();
+        assert_eq!(
+            Some(CanTdc {
+                tdcv: Some(12),
+                tdco: 15,
+                tdcf: None,
+            }),
+            tdc
+        );
+    }
+}
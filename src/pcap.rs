@@ -0,0 +1,1064 @@
+// socketcan/src/pcap.rs
+//
+// Implements reading and writing of pcap and pcapng capture files.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Packet capture (pcap and pcapng) file support.
+//!
+//! Reads and writes capture files that use the SocketCAN link type,
+//! `LINKTYPE_CAN_SOCKETCAN` (227). These are the files produced by Wireshark
+//! or tcpdump when capturing on a CAN interface, and the files written
+//! here can be opened directly in Wireshark.
+//!
+//! Each packet holds an 8-byte header followed by the frame data:
+//!
+//! ```text
+//! +---------------------------+-----+-------+-------+-------+-------------+
+//! | CAN ID + flags (BE, 32)   | len | flags | res 0 | res 1 | data ...    |
+//! +---------------------------+-----+-------+-------+-------+-------------+
+//! ```
+//!
+//! The CAN ID word is in network (big-endian) byte order and includes the
+//! SocketCAN EFF/RTR/ERR flags. For a CAN FD frame, the flags byte has the
+//! `CANFD_FDF` bit set, along with the BRS and ESI bits, as needed.
+//!
+//! Both the original pcap format and the newer pcapng format are supported.
+//! The [`Reader`] detects the format of the file from its first bytes. In
+//! a pcapng file, an interface description block is written for each CAN
+//! interface, so a capture from multiple buses keeps the name of the
+//! interface for each frame.
+//!
+//! Frames and timestamps can be taken directly from a socket:
+//!
+//! ```no_run
+//! use socketcan::{pcap, CanAnyFrame, CanSocket, Socket};
+//!
+//! let sock = CanSocket::open("can0").unwrap();
+//! let mut wtr = pcap::Writer::create("can0.pcapng", pcap::Format::PcapNg).unwrap();
+//!
+//! loop {
+//!     let (frame, ts) = sock.read_frame_with_timestamp().unwrap();
+//!     wtr.write_frame(ts, "can0", &CanAnyFrame::from(frame)).unwrap();
+//! }
+//! ```
+//!
+
+use crate::{
+    frame::{
+        can_frame_default, canfd_frame_default, dlc_to_len, len_to_dlc, FdFlags, CANFD_MAX_DLEN,
+        CAN_MAX_DLEN, CAN_MAX_RAW_DLC,
+    },
+    logfile::{self, LogReader, LogRecord, LogWriter},
+    CanAnyFrame, EmbeddedFrame, Frame,
+};
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path,
+    time::{Duration, SystemTime},
+};
+use thiserror::Error;
+
+/// The pcap link type for SocketCAN frames.
+pub const LINKTYPE_CAN_SOCKETCAN: u32 = 227;
+
+/// The CAN FD frame flag in the header of a packet.
+/// This tells that the packet holds an FD frame.
+const CANFD_FDF: u8 = 0x04;
+
+/// The size of the header in front of the frame data in each packet.
+const HDR_LEN: usize = 8;
+
+/// The maximum length of a packet; the header with FD data.
+const SNAP_LEN: u32 = (HDR_LEN + CANFD_MAX_DLEN) as u32;
+
+// pcap file header magic numbers
+const PCAP_MAGIC_US: u32 = 0xA1B2_C3D4;
+const PCAP_MAGIC_NS: u32 = 0xA1B2_3C4D;
+
+// pcapng block types
+const BT_SHB: u32 = 0x0A0D_0D0A;
+const BT_IDB: u32 = 0x0000_0001;
+const BT_SPB: u32 = 0x0000_0003;
+const BT_EPB: u32 = 0x0000_0006;
+
+/// pcapng section header byte-order magic
+const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+// pcapng option codes
+const OPT_ENDOFOPT: u16 = 0;
+const IF_NAME: u16 = 2;
+const IF_TSRESOL: u16 = 9;
+
+/// The largest block that we accept when reading a pcapng file.
+/// Anything bigger is considered a corrupt file.
+const MAX_BLOCK_LEN: usize = 1 << 24;
+
+// ===== Format =====
+
+/// The format of a capture file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// The original, libpcap, file format.
+    ///
+    /// This holds a single interface, so the interface names are not
+    /// kept. Timestamps are written with microsecond resolution.
+    Pcap,
+    /// The pcap "next generation" file format.
+    ///
+    /// This keeps the name of each interface in the capture. Timestamps
+    /// are written with nanosecond resolution.
+    PcapNg,
+}
+
+// ===== PcapRecord =====
+
+/// A single CAN frame from a capture file.
+#[derive(Debug, Clone)]
+pub struct PcapRecord {
+    /// The time the frame was captured.
+    pub timestamp: SystemTime,
+    /// The name of the interface on which the frame was captured.
+    ///
+    /// This is only available from pcapng files, and only if the capture
+    /// recorded it.
+    pub interface: Option<String>,
+    /// The CAN frame
+    pub frame: CanAnyFrame,
+}
+
+impl From<PcapRecord> for LogRecord {
+    fn from(rec: PcapRecord) -> Self {
+        LogRecord::new(rec.timestamp, rec.interface.unwrap_or_default(), rec.frame)
+    }
+}
+
+// ===== ParseError =====
+
+/// Capture file parse error
+#[derive(Error, Debug)]
+pub enum ParseError {
+    /// I/O Error
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// The file is not a pcap or pcapng file
+    #[error("not a pcap or pcapng file")]
+    UnknownFormat,
+    /// The capture is not of SocketCAN frames
+    #[error("unsupported link type: {0}")]
+    UnsupportedLinkType(u32),
+    /// A pcapng block is malformed
+    #[error("invalid pcapng block")]
+    InvalidBlock,
+    /// A packet refers to an interface that was not described
+    #[error("unknown interface ID: {0}")]
+    UnknownInterface(u32),
+    /// A packet does not hold a valid CAN frame
+    #[error("invalid CAN frame")]
+    InvalidCanFrame,
+}
+
+// ===== Frame encoding =====
+
+/// Decodes a CAN frame from the data of a captured packet.
+fn decode_frame(pkt: &[u8]) -> Result<CanAnyFrame, ParseError> {
+    if pkt.len() < HDR_LEN {
+        return Err(ParseError::InvalidCanFrame);
+    }
+
+    let can_id = u32::from_be_bytes([pkt[0], pkt[1], pkt[2], pkt[3]]);
+    let len = pkt[4] as usize;
+    let flags = pkt[5];
+    let data = &pkt[HDR_LEN..];
+
+    if flags & CANFD_FDF != 0 || pkt.len() == SNAP_LEN as usize {
+        // only the lengths that a DLC can encode are valid
+        if len_to_dlc(len).and_then(dlc_to_len) != Some(len) || len > data.len() {
+            return Err(ParseError::InvalidCanFrame);
+        }
+        let mut frame = canfd_frame_default();
+        frame.can_id = can_id;
+        frame.len = len as u8;
+        frame.flags = flags & FdFlags::all().bits();
+        frame.data[..len].copy_from_slice(&data[..len]);
+        Ok(frame.into())
+    } else {
+        if len > CAN_MAX_DLEN {
+            return Err(ParseError::InvalidCanFrame);
+        }
+        let mut frame = can_frame_default();
+        frame.can_id = can_id;
+        frame.can_dlc = len as u8;
+        // the raw DLC only means something for a full classic frame
+        let dlc = pkt[7] as usize;
+        if len == CAN_MAX_DLEN && dlc > CAN_MAX_DLEN && dlc <= CAN_MAX_RAW_DLC {
+            frame.len8_dlc = dlc as u8;
+        }
+
+        // A remote frame doesn't need to carry any data
+        let n = len.min(data.len());
+        if n < len && can_id & libc::CAN_RTR_FLAG == 0 {
+            return Err(ParseError::InvalidCanFrame);
+        }
+        frame.data[..n].copy_from_slice(&data[..n]);
+        Ok(frame.into())
+    }
+}
+
+/// Encodes a CAN frame as the data of a packet, appending it to the buffer.
+///
+/// Classic frames are padded out to 8 data bytes, and FD frames to 64,
+/// the same as the kernel delivers them to a capture.
+fn encode_frame(frame: &CanAnyFrame, buf: &mut Vec<u8>) {
+    use CanAnyFrame::*;
+
+    let (id_word, len, flags, data, pad_to) = match frame {
+        Normal(f) => (f.id_word(), f.len(), 0, f.data(), CAN_MAX_DLEN),
+        Remote(f) => (f.id_word(), f.dlc(), 0, &[][..], CAN_MAX_DLEN),
+        Error(f) => (f.id_word(), f.len(), 0, f.data(), CAN_MAX_DLEN),
+        Fd(f) => (
+            f.id_word(),
+            f.len(),
+            f.flags().bits() | CANFD_FDF,
+            f.data(),
+            CANFD_MAX_DLEN,
+        ),
+    };
+
+    // the last header byte is the raw DLC of a classic frame, if above 8
+    let len8_dlc = match frame {
+        Normal(f) => f.len8_dlc().unwrap_or(0),
+        _ => 0,
+    };
+
+    buf.extend_from_slice(&id_word.to_be_bytes());
+    buf.extend_from_slice(&[len as u8, flags, 0, len8_dlc as u8]);
+    buf.extend_from_slice(data);
+    buf.resize(buf.len() + pad_to - data.len(), 0);
+}
+
+/// Gets the time since the Unix epoch.
+/// Times before the epoch are clamped to it.
+fn since_epoch(ts: SystemTime) -> Duration {
+    ts.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+// ===== Byte order =====
+
+/// Reads integers from a byte slice in the byte order of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    fn u16(&self, b: &[u8]) -> u16 {
+        let b = [b[0], b[1]];
+        match self {
+            Endian::Little => u16::from_le_bytes(b),
+            Endian::Big => u16::from_be_bytes(b),
+        }
+    }
+
+    fn u32(&self, b: &[u8]) -> u32 {
+        let b = [b[0], b[1], b[2], b[3]];
+        match self {
+            Endian::Little => u32::from_le_bytes(b),
+            Endian::Big => u32::from_be_bytes(b),
+        }
+    }
+}
+
+// ===== Reader =====
+
+/// The resolution of timestamps for an interface in a pcapng file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TsResol {
+    /// Units of 10^-n seconds
+    Pow10(u8),
+    /// Units of 2^-n seconds
+    Pow2(u8),
+}
+
+impl TsResol {
+    /// Gets the time of a timestamp in this resolution.
+    fn to_duration(self, ts: u64) -> Duration {
+        let units_per_sec: u128 = match self {
+            TsResol::Pow10(n) => 10u128.saturating_pow(n.into()),
+            TsResol::Pow2(n) => 1u128 << n.min(127),
+        };
+        let secs = ts as u128 / units_per_sec;
+        let nanos = (ts as u128 % units_per_sec) * 1_000_000_000 / units_per_sec;
+        Duration::new(secs as u64, nanos as u32)
+    }
+}
+
+impl From<u8> for TsResol {
+    fn from(v: u8) -> Self {
+        if v & 0x80 == 0 {
+            TsResol::Pow10(v)
+        } else {
+            TsResol::Pow2(v & 0x7F)
+        }
+    }
+}
+
+/// An interface described in a pcapng file.
+#[derive(Debug, Clone)]
+struct Interface {
+    name: Option<String>,
+    link_type: u32,
+    tsresol: TsResol,
+}
+
+/// The file-specific state of the reader.
+#[derive(Debug)]
+enum State {
+    Pcap {
+        endian: Endian,
+        nanos: bool,
+    },
+    PcapNg {
+        endian: Endian,
+        interfaces: Vec<Interface>,
+    },
+}
+
+/// A pcap or pcapng capture file reader.
+#[derive(Debug)]
+pub struct Reader<R> {
+    rdr: R,
+    state: State,
+    buf: Vec<u8>,
+}
+
+impl<R: Read> Reader<io::BufReader<R>> {
+    /// Creates an I/O buffered reader from a capture file reader.
+    pub fn from_reader(rdr: R) -> Result<Self, ParseError> {
+        Reader::new(io::BufReader::new(rdr))
+    }
+}
+
+impl Reader<io::BufReader<fs::File>> {
+    /// Creates an I/O buffered reader from a file.
+    pub fn from_file<P>(path: P) -> Result<Self, ParseError>
+    where
+        P: AsRef<path::Path>,
+    {
+        Reader::from_reader(fs::File::open(path)?)
+    }
+}
+
+impl<R: Read> Reader<R> {
+    /// Creates a reader, detecting the format of the file from its header.
+    pub fn new(mut rdr: R) -> Result<Self, ParseError> {
+        let mut magic = [0u8; 4];
+        rdr.read_exact(&mut magic)?;
+
+        if u32::from_le_bytes(magic) == BT_SHB {
+            let mut reader = Self {
+                rdr,
+                state: State::PcapNg {
+                    endian: Endian::Little,
+                    interfaces: Vec::new(),
+                },
+                buf: Vec::new(),
+            };
+            reader.read_section_header()?;
+            return Ok(reader);
+        }
+
+        let (endian, nanos) = match magic {
+            m if u32::from_le_bytes(m) == PCAP_MAGIC_US => (Endian::Little, false),
+            m if u32::from_be_bytes(m) == PCAP_MAGIC_US => (Endian::Big, false),
+            m if u32::from_le_bytes(m) == PCAP_MAGIC_NS => (Endian::Little, true),
+            m if u32::from_be_bytes(m) == PCAP_MAGIC_NS => (Endian::Big, true),
+            _ => return Err(ParseError::UnknownFormat),
+        };
+
+        // version (4), thiszone (4), sigfigs (4), snaplen (4), network (4)
+        let mut hdr = [0u8; 20];
+        rdr.read_exact(&mut hdr)?;
+
+        let link_type = endian.u32(&hdr[16..]);
+        if link_type != LINKTYPE_CAN_SOCKETCAN {
+            return Err(ParseError::UnsupportedLinkType(link_type));
+        }
+
+        Ok(Self {
+            rdr,
+            state: State::Pcap { endian, nanos },
+            buf: Vec::new(),
+        })
+    }
+
+    /// Gets the format of the file being read.
+    pub fn format(&self) -> Format {
+        match self.state {
+            State::Pcap { .. } => Format::Pcap,
+            State::PcapNg { .. } => Format::PcapNg,
+        }
+    }
+
+    /// Returns an iterator over all records
+    pub fn records(&mut self) -> PcapRecords<'_, R> {
+        PcapRecords { src: self }
+    }
+
+    /// Advance state, returning next record.
+    ///
+    /// In a pcapng file, packets from interfaces that are not SocketCAN,
+    /// and blocks that don't hold packets, are skipped.
+    pub fn next_record(&mut self) -> Result<Option<PcapRecord>, ParseError> {
+        match self.state {
+            State::Pcap { endian, nanos } => self.next_pcap_record(endian, nanos),
+            State::PcapNg { .. } => self.next_pcapng_record(),
+        }
+    }
+
+    /// Reads exactly enough bytes to fill the buffer, returning `false` on
+    /// a clean end of file, before any bytes were read.
+    fn read_or_eof(&mut self, buf: &mut [u8]) -> io::Result<bool> {
+        let mut n = 0;
+        while n < buf.len() {
+            match self.rdr.read(&mut buf[n..]) {
+                Ok(0) if n == 0 => return Ok(false),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(k) => n += k,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Reads the next record from a pcap file.
+    fn next_pcap_record(
+        &mut self,
+        endian: Endian,
+        nanos: bool,
+    ) -> Result<Option<PcapRecord>, ParseError> {
+        // ts_sec (4), ts_usec (4), incl_len (4), orig_len (4)
+        let mut hdr = [0u8; 16];
+        if !self.read_or_eof(&mut hdr)? {
+            return Ok(None);
+        }
+
+        let secs = endian.u32(&hdr[0..]) as u64;
+        let frac = endian.u32(&hdr[4..]);
+        let len = endian.u32(&hdr[8..]) as usize;
+
+        if len > MAX_BLOCK_LEN {
+            return Err(ParseError::InvalidCanFrame);
+        }
+
+        let nanos = if nanos {
+            frac
+        } else {
+            frac.saturating_mul(1000)
+        };
+        let timestamp = SystemTime::UNIX_EPOCH + Duration::new(secs, nanos);
+
+        self.buf.resize(len, 0);
+        self.rdr.read_exact(&mut self.buf)?;
+
+        Ok(Some(PcapRecord {
+            timestamp,
+            interface: None,
+            frame: decode_frame(&self.buf)?,
+        }))
+    }
+
+    /// Reads the body of a pcapng block into the buffer, after its type has
+    /// been read. The length fields are checked, but not kept in the buffer.
+    fn read_block_body(&mut self, endian: Endian) -> Result<(), ParseError> {
+        let mut len = [0u8; 4];
+        self.rdr.read_exact(&mut len)?;
+        let len = endian.u32(&len) as usize;
+
+        // type (4), length (4), body, length (4)
+        if len < 12 || len % 4 != 0 || len > MAX_BLOCK_LEN {
+            return Err(ParseError::InvalidBlock);
+        }
+
+        self.buf.resize(len - 8, 0);
+        self.rdr.read_exact(&mut self.buf)?;
+
+        let trailer = self.buf.split_off(len - 12);
+        if endian.u32(&trailer) != len as u32 {
+            return Err(ParseError::InvalidBlock);
+        }
+        Ok(())
+    }
+
+    /// Reads a pcapng section header block, after its type has been read.
+    ///
+    /// This starts a new section, which sets the byte order for the blocks
+    /// that follow, and clears the interfaces from any previous section.
+    fn read_section_header(&mut self) -> Result<(), ParseError> {
+        // The byte order isn't known until we see the magic, so peek at the
+        // length and the magic before reading the rest of the block.
+        let mut hdr = [0u8; 8];
+        self.rdr.read_exact(&mut hdr)?;
+
+        let endian = match &hdr[4..] {
+            m if Endian::Little.u32(m) == BYTE_ORDER_MAGIC => Endian::Little,
+            m if Endian::Big.u32(m) == BYTE_ORDER_MAGIC => Endian::Big,
+            _ => return Err(ParseError::InvalidBlock),
+        };
+
+        let len = endian.u32(&hdr) as usize;
+        if len < 28 || len % 4 != 0 || len > MAX_BLOCK_LEN {
+            return Err(ParseError::InvalidBlock);
+        }
+
+        // Skip the rest of the block (versions, length, options, trailer)
+        self.buf.resize(len - 12, 0);
+        self.rdr.read_exact(&mut self.buf)?;
+
+        self.state = State::PcapNg {
+            endian,
+            interfaces: Vec::new(),
+        };
+        Ok(())
+    }
+
+    /// Reads the next record from a pcapng file.
+    fn next_pcapng_record(&mut self) -> Result<Option<PcapRecord>, ParseError> {
+        loop {
+            let endian = match self.state {
+                State::PcapNg { endian, .. } => endian,
+                _ => unreachable!(),
+            };
+
+            let mut btype = [0u8; 4];
+            if !self.read_or_eof(&mut btype)? {
+                return Ok(None);
+            }
+            let btype = endian.u32(&btype);
+
+            if btype == BT_SHB {
+                self.read_section_header()?;
+                continue;
+            }
+
+            self.read_block_body(endian)?;
+
+            let interfaces = match &mut self.state {
+                State::PcapNg { interfaces, .. } => interfaces,
+                _ => unreachable!(),
+            };
+            let body = &self.buf[..];
+
+            match btype {
+                BT_IDB => interfaces.push(parse_idb(body, endian)?),
+                BT_EPB => {
+                    // if_id (4), ts_hi (4), ts_lo (4), cap_len (4), orig_len (4)
+                    if body.len() < 20 {
+                        return Err(ParseError::InvalidBlock);
+                    }
+                    let if_id = endian.u32(&body[0..]);
+                    let ts = (endian.u32(&body[4..]) as u64) << 32 | endian.u32(&body[8..]) as u64;
+                    let cap_len = endian.u32(&body[12..]) as usize;
+
+                    let pkt = body.get(20..20 + cap_len).ok_or(ParseError::InvalidBlock)?;
+                    let iface = interfaces
+                        .get(if_id as usize)
+                        .ok_or(ParseError::UnknownInterface(if_id))?;
+
+                    if iface.link_type != LINKTYPE_CAN_SOCKETCAN {
+                        continue;
+                    }
+
+                    return Ok(Some(PcapRecord {
+                        timestamp: SystemTime::UNIX_EPOCH + iface.tsresol.to_duration(ts),
+                        interface: iface.name.clone(),
+                        frame: decode_frame(pkt)?,
+                    }));
+                }
+                BT_SPB => {
+                    // orig_len (4), then the packet, which has no timestamp
+                    // and always comes from the first interface.
+                    if body.len() < 4 {
+                        return Err(ParseError::InvalidBlock);
+                    }
+                    let orig_len = endian.u32(body) as usize;
+                    let pkt = &body[4..];
+                    let pkt = &pkt[..orig_len.min(pkt.len())];
+
+                    let iface = interfaces.first().ok_or(ParseError::UnknownInterface(0))?;
+
+                    if iface.link_type != LINKTYPE_CAN_SOCKETCAN {
+                        continue;
+                    }
+
+                    return Ok(Some(PcapRecord {
+                        timestamp: SystemTime::UNIX_EPOCH,
+                        interface: iface.name.clone(),
+                        frame: decode_frame(pkt)?,
+                    }));
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Parses the body of a pcapng interface description block.
+fn parse_idb(body: &[u8], endian: Endian) -> Result<Interface, ParseError> {
+    // link_type (2), reserved (2), snap_len (4), options...
+    if body.len() < 8 {
+        return Err(ParseError::InvalidBlock);
+    }
+
+    let mut iface = Interface {
+        name: None,
+        link_type: endian.u16(body) as u32,
+        tsresol: TsResol::Pow10(6),
+    };
+
+    let mut opts = &body[8..];
+    while opts.len() >= 4 {
+        let code = endian.u16(opts);
+        let len = endian.u16(&opts[2..]) as usize;
+        let val = opts.get(4..4 + len).ok_or(ParseError::InvalidBlock)?;
+
+        match code {
+            OPT_ENDOFOPT => break,
+            IF_NAME => iface.name = Some(String::from_utf8_lossy(val).into_owned()),
+            IF_TSRESOL if len == 1 => iface.tsresol = TsResol::from(val[0]),
+            _ => (),
+        }
+
+        // Options are padded to 32 bits
+        let n = (4 + len + 3) & !3;
+        opts = opts.get(n..).unwrap_or_default();
+    }
+
+    Ok(iface)
+}
+
+/// Record iterator
+#[derive(Debug)]
+pub struct PcapRecords<'a, R: 'a> {
+    src: &'a mut Reader<R>,
+}
+
+impl<R: Read> Iterator for PcapRecords<'_, R> {
+    type Item = Result<PcapRecord, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.src.next_record().transpose()
+    }
+}
+
+impl<R: Read> LogReader for Reader<R> {
+    fn read_record(&mut self) -> Result<Option<LogRecord>, logfile::Error> {
+        Ok(self.next_record()?.map(LogRecord::from))
+    }
+}
+
+// ===== Writer =====
+
+/// A pcap or pcapng capture file writer.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    wtr: W,
+    format: Format,
+    // The names of the interfaces described so far, in pcapng files
+    interfaces: Vec<String>,
+    buf: Vec<u8>,
+}
+
+impl Writer<io::BufWriter<fs::File>> {
+    /// Creates a buffered writer to a new file.
+    pub fn create<P>(path: P, format: Format) -> io::Result<Self>
+    where
+        P: AsRef<path::Path>,
+    {
+        Self::new(io::BufWriter::new(fs::File::create(path)?), format)
+    }
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a capture file writer, and writes out the file header.
+    ///
+    /// Files are always written in the native byte order of the host.
+    pub fn new(mut wtr: W, format: Format) -> io::Result<Self> {
+        match format {
+            Format::Pcap => {
+                let mut hdr = Vec::with_capacity(24);
+                hdr.extend_from_slice(&PCAP_MAGIC_US.to_ne_bytes());
+                hdr.extend_from_slice(&2u16.to_ne_bytes());
+                hdr.extend_from_slice(&4u16.to_ne_bytes());
+                hdr.extend_from_slice(&0i32.to_ne_bytes());
+                hdr.extend_from_slice(&0u32.to_ne_bytes());
+                hdr.extend_from_slice(&SNAP_LEN.to_ne_bytes());
+                hdr.extend_from_slice(&LINKTYPE_CAN_SOCKETCAN.to_ne_bytes());
+                wtr.write_all(&hdr)?;
+            }
+            Format::PcapNg => {
+                let mut body = Vec::with_capacity(16);
+                body.extend_from_slice(&BYTE_ORDER_MAGIC.to_ne_bytes());
+                body.extend_from_slice(&1u16.to_ne_bytes());
+                body.extend_from_slice(&0u16.to_ne_bytes());
+                // The section length is not specified
+                body.extend_from_slice(&(-1i64).to_ne_bytes());
+                write_block(&mut wtr, BT_SHB, &body)?;
+            }
+        }
+
+        Ok(Self {
+            wtr,
+            format,
+            interfaces: Vec::new(),
+            buf: Vec::new(),
+        })
+    }
+
+    /// Gets the format of the file being written.
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// Writes a frame to the file.
+    ///
+    /// In a pcapng file, an interface description block is written the
+    /// first time that each interface name is seen. In a pcap file, the
+    /// interface name is not kept.
+    pub fn write_frame(
+        &mut self,
+        timestamp: SystemTime,
+        interface: &str,
+        frame: &CanAnyFrame,
+    ) -> io::Result<()> {
+        let ts = since_epoch(timestamp);
+
+        let mut pkt = Vec::with_capacity(SNAP_LEN as usize);
+        encode_frame(frame, &mut pkt);
+        let pkt_len = pkt.len() as u32;
+
+        match self.format {
+            Format::Pcap => {
+                self.buf.clear();
+                self.buf
+                    .extend_from_slice(&(ts.as_secs() as u32).to_ne_bytes());
+                self.buf
+                    .extend_from_slice(&ts.subsec_micros().to_ne_bytes());
+                self.buf.extend_from_slice(&pkt_len.to_ne_bytes());
+                self.buf.extend_from_slice(&pkt_len.to_ne_bytes());
+                self.buf.extend_from_slice(&pkt);
+                self.wtr.write_all(&self.buf)
+            }
+            Format::PcapNg => {
+                let if_id = self.interface_id(interface)?;
+                let ts = ts.as_nanos() as u64;
+
+                self.buf.clear();
+                self.buf.extend_from_slice(&if_id.to_ne_bytes());
+                self.buf
+                    .extend_from_slice(&((ts >> 32) as u32).to_ne_bytes());
+                self.buf.extend_from_slice(&(ts as u32).to_ne_bytes());
+                self.buf.extend_from_slice(&pkt_len.to_ne_bytes());
+                self.buf.extend_from_slice(&pkt_len.to_ne_bytes());
+                self.buf.extend_from_slice(&pkt);
+                write_block(&mut self.wtr, BT_EPB, &self.buf)
+            }
+        }
+    }
+
+    /// Writes a record to the file.
+    ///
+    /// If the record doesn't have an interface name, an empty one is used.
+    pub fn write_record(&mut self, rec: &PcapRecord) -> io::Result<()> {
+        let iface = rec.interface.as_deref().unwrap_or_default();
+        self.write_frame(rec.timestamp, iface, &rec.frame)
+    }
+
+    /// Gets the pcapng interface ID for the named interface, writing
+    /// out a description block for it if this is the first time it's used.
+    fn interface_id(&mut self, name: &str) -> io::Result<u32> {
+        if let Some(id) = self.interfaces.iter().position(|s| s == name) {
+            return Ok(id as u32);
+        }
+
+        let mut body = Vec::new();
+        body.extend_from_slice(&(LINKTYPE_CAN_SOCKETCAN as u16).to_ne_bytes());
+        body.extend_from_slice(&0u16.to_ne_bytes());
+        body.extend_from_slice(&SNAP_LEN.to_ne_bytes());
+        if !name.is_empty() {
+            push_option(&mut body, IF_NAME, name.as_bytes());
+        }
+        push_option(&mut body, IF_TSRESOL, &[9]);
+        push_option(&mut body, OPT_ENDOFOPT, &[]);
+        write_block(&mut self.wtr, BT_IDB, &body)?;
+
+        self.interfaces.push(name.to_string());
+        Ok(self.interfaces.len() as u32 - 1)
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.wtr.flush()
+    }
+
+    /// Consumes the writer, returning the underlying I/O writer.
+    pub fn into_inner(self) -> W {
+        self.wtr
+    }
+}
+
+impl<W: Write> LogWriter for Writer<W> {
+    /// Writes the record to the file.
+    ///
+    /// The capture formats do not record the direction of the frame,
+    /// so it is dropped.
+    fn write_record(&mut self, rec: &LogRecord) -> io::Result<()> {
+        self.write_frame(rec.timestamp, &rec.interface, &rec.frame)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.wtr.flush()
+    }
+}
+
+/// Appends a pcapng option to a block body, padded to 32 bits.
+fn push_option(body: &mut Vec<u8>, code: u16, val: &[u8]) {
+    body.extend_from_slice(&code.to_ne_bytes());
+    body.extend_from_slice(&(val.len() as u16).to_ne_bytes());
+    body.extend_from_slice(val);
+    body.resize((body.len() + 3) & !3, 0);
+}
+
+/// Writes a pcapng block with the body padded out to 32 bits.
+fn write_block<W: Write>(wtr: &mut W, btype: u32, body: &[u8]) -> io::Result<()> {
+    let pad = (4 - body.len() % 4) % 4;
+    let len = (12 + body.len() + pad) as u32;
+
+    wtr.write_all(&btype.to_ne_bytes())?;
+    wtr.write_all(&len.to_ne_bytes())?;
+    wtr.write_all(body)?;
+    wtr.write_all(&[0u8; 3][..pad])?;
+    wtr.write_all(&len.to_ne_bytes())
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CanDataFrame, CanErrorFrame, CanFdFrame, CanRemoteFrame, ExtendedId, StandardId};
+
+    fn test_frames() -> Vec<CanAnyFrame> {
+        vec![
+            CanAnyFrame::Normal(
+                CanDataFrame::new(StandardId::new(0x123).unwrap(), &[1, 2, 3]).unwrap(),
+            ),
+            CanAnyFrame::Normal(
+                CanDataFrame::new(ExtendedId::new(0x18FF_0001).unwrap(), &[0xAA; 8]).unwrap(),
+            ),
+            CanAnyFrame::Normal({
+                let mut frame =
+                    CanDataFrame::new(StandardId::new(0x7FF).unwrap(), &[0xBB; 8]).unwrap();
+                frame.set_len8_dlc(Some(0xF)).unwrap();
+                frame
+            }),
+            CanAnyFrame::Remote(
+                CanRemoteFrame::new_remote(StandardId::new(0x50).unwrap(), 4).unwrap(),
+            ),
+            CanAnyFrame::Error(
+                CanErrorFrame::new_error(0x0008, &[0, 0, 0x04, 0, 0, 0, 0, 0]).unwrap(),
+            ),
+            CanAnyFrame::Fd(
+                CanFdFrame::with_flags(ExtendedId::new(0x1234).unwrap(), &[0x55; 24], FdFlags::BRS)
+                    .unwrap(),
+            ),
+        ]
+    }
+
+    fn assert_frame_eq(a: &CanAnyFrame, b: &CanAnyFrame) {
+        use CanAnyFrame::*;
+        match (a, b) {
+            (Normal(a), Normal(b)) => {
+                assert_eq!(a.id_word(), b.id_word());
+                assert_eq!(a.data(), b.data());
+                assert_eq!(a.len8_dlc(), b.len8_dlc());
+            }
+            (Remote(a), Remote(b)) => {
+                assert_eq!(a.id_word(), b.id_word());
+                assert_eq!(a.dlc(), b.dlc());
+            }
+            (Error(a), Error(b)) => {
+                assert_eq!(a.id_word(), b.id_word());
+                assert_eq!(a.data(), b.data());
+            }
+            (Fd(a), Fd(b)) => {
+                assert_eq!(a.id_word(), b.id_word());
+                assert_eq!(a.flags(), b.flags());
+                assert_eq!(a.data(), b.data());
+            }
+            _ => panic!("Frame type mismatch"),
+        }
+    }
+
+    #[test]
+    fn test_encode() {
+        let frame = CanAnyFrame::Normal(
+            CanDataFrame::new(ExtendedId::new(0x0102_0304).unwrap(), &[0x11, 0x22]).unwrap(),
+        );
+        let mut buf = Vec::new();
+        encode_frame(&frame, &mut buf);
+        assert_eq!(
+            &[0x81, 0x02, 0x03, 0x04, 2, 0, 0, 0, 0x11, 0x22, 0, 0, 0, 0, 0, 0],
+            buf.as_slice()
+        );
+
+        let frame = CanAnyFrame::Fd(
+            CanFdFrame::with_flags(StandardId::new(0x7FF).unwrap(), &[1], FdFlags::ESI).unwrap(),
+        );
+        buf.clear();
+        encode_frame(&frame, &mut buf);
+        assert_eq!(72, buf.len());
+        assert_eq!(&[0, 0, 0x07, 0xFF, 1, 0x06, 0, 0, 1], &buf[..9]);
+
+        assert_frame_eq(&frame, &decode_frame(&buf).unwrap());
+    }
+
+    #[test]
+    fn test_decode_bad_frame() {
+        // a raw DLC is ignored unless the frame has 8 bytes
+        let mut pkt = vec![0, 0, 0x01, 0x23, 2, 0, 0, 0x0F];
+        pkt.resize(16, 0);
+        match decode_frame(&pkt).unwrap() {
+            CanAnyFrame::Normal(frame) => {
+                assert_eq!(None, frame.len8_dlc());
+                assert_eq!(2, frame.dlc());
+            }
+            _ => panic!("Expected data frame"),
+        }
+
+        // as is a raw DLC that's out of range
+        pkt[4] = 8;
+        pkt[7] = 0x80;
+        match decode_frame(&pkt).unwrap() {
+            CanAnyFrame::Normal(frame) => assert_eq!(None, frame.len8_dlc()),
+            _ => panic!("Expected data frame"),
+        }
+
+        // an FD frame must have a length that a DLC can encode
+        let mut pkt = vec![0, 0, 0x01, 0x23, 9, CANFD_FDF, 0, 0];
+        pkt.resize(72, 0);
+        assert!(matches!(
+            decode_frame(&pkt),
+            Err(ParseError::InvalidCanFrame)
+        ));
+        pkt[4] = 12;
+        assert!(decode_frame(&pkt).is_ok());
+    }
+
+    #[test]
+    fn test_pcap_round_trip() {
+        let t0 = SystemTime::UNIX_EPOCH + Duration::new(1_697_630_400, 123_456_000);
+
+        let mut wtr = Writer::new(Vec::new(), Format::Pcap).unwrap();
+        for (i, frame) in test_frames().iter().enumerate() {
+            let ts = t0 + Duration::from_millis(i as u64);
+            wtr.write_frame(ts, "can0", frame).unwrap();
+        }
+        let buf = wtr.into_inner();
+
+        let mut rdr = Reader::from_reader(buf.as_slice()).unwrap();
+        assert_eq!(Format::Pcap, rdr.format());
+
+        let recs = rdr.records().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(test_frames().len(), recs.len());
+
+        for (i, (rec, frame)) in recs.iter().zip(test_frames().iter()).enumerate() {
+            assert_eq!(t0 + Duration::from_millis(i as u64), rec.timestamp);
+            assert_eq!(None, rec.interface);
+            assert_frame_eq(frame, &rec.frame);
+        }
+    }
+
+    #[test]
+    fn test_pcapng_round_trip() {
+        let t0 = SystemTime::UNIX_EPOCH + Duration::new(1_697_630_400, 123_456_789);
+
+        let mut wtr = Writer::new(Vec::new(), Format::PcapNg).unwrap();
+        for (i, frame) in test_frames().iter().enumerate() {
+            let ts = t0 + Duration::from_nanos(i as u64);
+            let iface = if i % 2 == 0 { "can0" } else { "vcan1" };
+            wtr.write_frame(ts, iface, frame).unwrap();
+        }
+        let buf = wtr.into_inner();
+
+        let mut rdr = Reader::from_reader(buf.as_slice()).unwrap();
+        assert_eq!(Format::PcapNg, rdr.format());
+
+        let recs = rdr.records().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(test_frames().len(), recs.len());
+
+        for (i, (rec, frame)) in recs.iter().zip(test_frames().iter()).enumerate() {
+            let iface = if i % 2 == 0 { "can0" } else { "vcan1" };
+            assert_eq!(t0 + Duration::from_nanos(i as u64), rec.timestamp);
+            assert_eq!(Some(iface), rec.interface.as_deref());
+            assert_frame_eq(frame, &rec.frame);
+        }
+    }
+
+    #[test]
+    fn test_pcap_big_endian() {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&PCAP_MAGIC_NS.to_be_bytes());
+        buf.extend_from_slice(&[0, 2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0]);
+        buf.extend_from_slice(&SNAP_LEN.to_be_bytes());
+        buf.extend_from_slice(&LINKTYPE_CAN_SOCKETCAN.to_be_bytes());
+
+        buf.extend_from_slice(&10u32.to_be_bytes());
+        buf.extend_from_slice(&500u32.to_be_bytes());
+        buf.extend_from_slice(&16u32.to_be_bytes());
+        buf.extend_from_slice(&16u32.to_be_bytes());
+        buf.extend_from_slice(&[0, 0, 0x01, 0x23, 1, 0, 0, 0, 0xAB, 0, 0, 0, 0, 0, 0, 0]);
+
+        let mut rdr = Reader::from_reader(buf.as_slice()).unwrap();
+        let rec = rdr.next_record().unwrap().unwrap();
+        assert_eq!(
+            SystemTime::UNIX_EPOCH + Duration::new(10, 500),
+            rec.timestamp
+        );
+        match rec.frame {
+            CanAnyFrame::Normal(frame) => {
+                assert_eq!(0x123, frame.raw_id());
+                assert_eq!(&[0xAB], frame.data());
+            }
+            _ => panic!("Expected data frame"),
+        }
+        assert!(rdr.next_record().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_bad_file() {
+        let buf = [0u8; 24];
+        assert!(matches!(
+            Reader::from_reader(&buf[..]),
+            Err(ParseError::UnknownFormat)
+        ));
+
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&PCAP_MAGIC_US.to_le_bytes());
+        buf.extend_from_slice(&[0u8; 16]);
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        assert!(matches!(
+            Reader::from_reader(buf.as_slice()),
+            Err(ParseError::UnsupportedLinkType(1))
+        ));
+    }
+}
@@ -0,0 +1,654 @@
+// socketcan/src/replay.rs
+//
+// Implements timed replay of CAN logs to sockets.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! Timed replay of CAN logs to sockets.
+//!
+//! This is the equivalent of the `canplayer` utility from
+//! [can-utils](https://github.com/linux-can/can-utils). Records are read
+//! from any [`LogReader`], like a candump [`Reader`](crate::dump::Reader),
+//! and the frames are written out to sockets, keeping the time between
+//! frames the same as when they were recorded.
+//!
+//! The interfaces in the log can be mapped to different ones for the
+//! replay, like sending the frames captured on "can1" out to "vcan0".
+//!
+//! ```no_run
+//! use socketcan::{dump, replay::{Player, ReplayOptions}};
+//!
+//! let mut opts = ReplayOptions::default();
+//! opts.add_mapping("can1=vcan0").unwrap();
+//! opts.speed = 2.0;
+//!
+//! let mut player: Player = Player::new(opts);
+//! let rdr = dump::Reader::from_file("capture.log").unwrap();
+//! player.play(rdr).unwrap();
+//! ```
+//!
+//! Async players, with the same options, are available in the `tokio`
+//! and `async_io` modules.
+
+use crate::{
+    logfile::{Error, LogReader, LogRecord},
+    CanAnyFrame, CanFdSocket, CanFrame, CanSocket, Socket,
+};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    io, thread,
+    time::{Duration, Instant, SystemTime},
+};
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+use std::{
+    future::{poll_fn, Future},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+// ===== ReplayOptions =====
+
+/// Options for the replay of a log.
+#[derive(Debug, Clone)]
+pub struct ReplayOptions {
+    /// Map of interface names in the log to the interfaces used for the
+    /// replay.
+    ///
+    /// If this is empty, frames are sent to the same interfaces that they
+    /// were recorded on. Otherwise, only frames from the interfaces in the
+    /// map are sent, and the rest are skipped.
+    pub interface_map: HashMap<String, String>,
+    /// The speed of the replay, as a multiple of the original.
+    ///
+    /// A speed of 2.0 replays the log twice as fast as it was recorded.
+    /// A speed that is infinite, zero, or negative sends the frames as fast
+    /// as possible, without any delays.
+    pub speed: f64,
+    /// The maximum delay between two frames.
+    ///
+    /// This limits long gaps in the log, after the speed is applied.
+    pub max_gap: Option<Duration>,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            interface_map: HashMap::new(),
+            speed: 1.0,
+            max_gap: None,
+        }
+    }
+}
+
+impl ReplayOptions {
+    /// Maps an interface from the log to the one used for the replay.
+    pub fn map_interface<S, T>(&mut self, log_iface: S, iface: T)
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        self.interface_map.insert(log_iface.into(), iface.into());
+    }
+
+    /// Adds an interface mapping from a string like "can1=vcan0", the same
+    /// as the assignments given to `canplayer`.
+    ///
+    /// This sends the frames recorded on "can1" to "vcan0".
+    pub fn add_mapping(&mut self, mapping: &str) -> io::Result<()> {
+        match mapping.split_once('=') {
+            Some((log_iface, iface)) if !log_iface.is_empty() && !iface.is_empty() => {
+                self.map_interface(log_iface, iface);
+                Ok(())
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid interface mapping: '{}'", mapping),
+            )),
+        }
+    }
+
+    /// Gets the interface to use for a frame recorded on the named
+    /// interface, or `None` if the frame should not be sent.
+    pub fn output_interface<'a>(&'a self, log_iface: &'a str) -> Option<&'a str> {
+        if self.interface_map.is_empty() {
+            Some(log_iface)
+        } else {
+            self.interface_map.get(log_iface).map(String::as_str)
+        }
+    }
+}
+
+// ===== Scheduler =====
+
+/// A frame that is scheduled to be sent.
+#[derive(Debug)]
+pub(crate) struct ScheduledFrame {
+    /// The interface to send the frame on
+    pub iface: String,
+    /// When the frame should be sent
+    pub deadline: Instant,
+    /// The frame
+    pub frame: CanAnyFrame,
+}
+
+/// Reads the records to replay, and determines when each should be sent.
+///
+/// This is shared by the blocking and async players. The time of each frame
+/// is kept relative to the previous one, so that delays in sending don't
+/// accumulate over the replay.
+#[derive(Debug)]
+pub(crate) struct Scheduler<'a> {
+    opts: &'a ReplayOptions,
+    // The log time and deadline of the last frame
+    prev: Option<(SystemTime, Instant)>,
+}
+
+impl<'a> Scheduler<'a> {
+    /// Creates a scheduler for a replay with the specified options.
+    pub fn new(opts: &'a ReplayOptions) -> Self {
+        Self { opts, prev: None }
+    }
+
+    /// Restarts the timing, so that the next frame is sent immediately.
+    ///
+    /// This is used at the start of each pass through a looped replay.
+    pub fn restart(&mut self) {
+        self.prev = None;
+    }
+
+    /// Gets the time that a frame recorded at the specified time should
+    /// be sent.
+    pub fn deadline(&mut self, ts: SystemTime) -> Instant {
+        let deadline = match self.prev {
+            None => Instant::now(),
+            Some((prev_ts, prev_deadline)) => {
+                let gap = ts.duration_since(prev_ts).unwrap_or_default();
+                let speed = self.opts.speed;
+
+                let mut gap = if speed.is_finite() && speed > 0.0 {
+                    Duration::try_from_secs_f64(gap.as_secs_f64() / speed).unwrap_or(Duration::MAX)
+                } else {
+                    Duration::ZERO
+                };
+                if let Some(max_gap) = self.opts.max_gap {
+                    gap = gap.min(max_gap);
+                }
+                prev_deadline.checked_add(gap).unwrap_or(prev_deadline)
+            }
+        };
+        self.prev = Some((ts, deadline));
+        deadline
+    }
+
+    /// Reads the next record that should be sent.
+    ///
+    /// Records from interfaces that are not mapped are skipped.
+    pub fn next<R>(&mut self, rdr: &mut R) -> Result<Option<ScheduledFrame>, Error>
+    where
+        R: LogReader + ?Sized,
+    {
+        while let Some(rec) = rdr.read_record()? {
+            if let Some(sf) = self.schedule(rec) {
+                return Ok(Some(sf));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Determines when a record should be sent, or `None` if it's from an
+    /// interface that is not mapped.
+    ///
+    /// The async players use this with records that they read themselves.
+    pub fn schedule(&mut self, rec: LogRecord) -> Option<ScheduledFrame> {
+        let iface = self.opts.output_interface(&rec.interface)?.to_string();
+        Some(ScheduledFrame {
+            iface,
+            deadline: self.deadline(rec.timestamp),
+            frame: rec.frame,
+        })
+    }
+}
+
+// ===== FrameSink =====
+
+/// A destination for the frames in a replay.
+///
+/// This is implemented for the blocking sockets.
+pub trait FrameSink {
+    /// Opens a sink for the named interface.
+    fn open_sink(iface: &str) -> io::Result<Self>
+    where
+        Self: Sized;
+
+    /// Sends a frame.
+    fn send_frame(&mut self, frame: &CanAnyFrame) -> io::Result<()>;
+}
+
+impl FrameSink for CanSocket {
+    fn open_sink(iface: &str) -> io::Result<Self> {
+        CanSocket::open(iface)
+    }
+
+    /// Sends a frame.
+    ///
+    /// This fails with an `InvalidInput` error for an FD frame.
+    fn send_frame(&mut self, frame: &CanAnyFrame) -> io::Result<()> {
+        let frame = match *frame {
+            CanAnyFrame::Normal(frame) => CanFrame::Data(frame),
+            CanAnyFrame::Remote(frame) => CanFrame::Remote(frame),
+            CanAnyFrame::Error(frame) => CanFrame::Error(frame),
+            CanAnyFrame::Fd(_) => return Err(io::ErrorKind::InvalidInput.into()),
+        };
+        self.write_frame(&frame)
+    }
+}
+
+impl FrameSink for CanFdSocket {
+    fn open_sink(iface: &str) -> io::Result<Self> {
+        CanFdSocket::open(iface)
+    }
+
+    fn send_frame(&mut self, frame: &CanAnyFrame) -> io::Result<()> {
+        self.write_frame(frame)
+    }
+}
+
+// ===== Player =====
+
+/// A blocking player to replay CAN logs to sockets.
+///
+/// The sink for each interface is opened the first time that it is needed,
+/// unless one was already added to the player.
+#[derive(Debug)]
+pub struct Player<S = CanFdSocket> {
+    opts: ReplayOptions,
+    sinks: HashMap<String, S>,
+}
+
+impl<S: FrameSink> Player<S> {
+    /// Creates a player with the specified options.
+    pub fn new(opts: ReplayOptions) -> Self {
+        Self {
+            opts,
+            sinks: HashMap::new(),
+        }
+    }
+
+    /// Gets the options for the player.
+    pub fn options(&self) -> &ReplayOptions {
+        &self.opts
+    }
+
+    /// Adds the sink to use for the named output interface.
+    pub fn add_sink<T: Into<String>>(&mut self, iface: T, sink: S) {
+        self.sinks.insert(iface.into(), sink);
+    }
+
+    /// Plays the records from a log, blocking until they're all sent.
+    ///
+    /// On success, returns the number of frames that were sent.
+    pub fn play<R: LogReader>(&mut self, mut rdr: R) -> Result<u64, Error> {
+        let opts = self.opts.clone();
+        let mut sched = Scheduler::new(&opts);
+        self.play_pass(&mut sched, &mut rdr)
+    }
+
+    /// Plays a log repeatedly.
+    ///
+    /// The `open` function is called at the start of each pass to get a
+    /// new reader of the log. If `loops` is `None`, the log is played
+    /// forever, or until an error occurs. The replay also stops if a pass
+    /// through the log doesn't send any frames.
+    ///
+    /// On success, returns the total number of frames that were sent.
+    pub fn play_loop<R, F>(&mut self, loops: Option<u32>, mut open: F) -> Result<u64, Error>
+    where
+        R: LogReader,
+        F: FnMut() -> Result<R, Error>,
+    {
+        let opts = self.opts.clone();
+        let mut sched = Scheduler::new(&opts);
+        let mut n = 0;
+
+        for _ in 0..loops.unwrap_or(u32::MAX) {
+            sched.restart();
+            let mut rdr = open()?;
+            match self.play_pass(&mut sched, &mut rdr)? {
+                0 => break,
+                k => n += k,
+            }
+        }
+        Ok(n)
+    }
+
+    /// Plays a single pass through a log
+    fn play_pass<R>(&mut self, sched: &mut Scheduler, rdr: &mut R) -> Result<u64, Error>
+    where
+        R: LogReader + ?Sized,
+    {
+        let mut n = 0;
+        while let Some(sf) = sched.next(rdr)? {
+            let now = Instant::now();
+            if sf.deadline > now {
+                thread::sleep(sf.deadline - now);
+            }
+
+            let sink = match self.sinks.entry(sf.iface) {
+                Entry::Occupied(ent) => ent.into_mut(),
+                Entry::Vacant(ent) => {
+                    let sink = S::open_sink(ent.key())?;
+                    ent.insert(sink)
+                }
+            };
+            sink.send_frame(&sf.frame)?;
+            n += 1;
+        }
+        Ok(n)
+    }
+}
+
+// ===== AsyncFrameSink =====
+
+/// An async destination for the frames in a replay.
+///
+/// This is implemented for the async CAN FD sockets, and is the async
+/// equivalent of [`FrameSink`].
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+pub trait AsyncFrameSink {
+    /// Opens a sink for the named interface.
+    fn open_sink(iface: &str) -> io::Result<Self>
+    where
+        Self: Sized;
+
+    /// Attempts to send a frame, registering the task to be woken if the
+    /// sink isn't ready for it.
+    fn poll_send_frame(
+        &mut self,
+        cx: &mut Context<'_>,
+        frame: &CanAnyFrame,
+    ) -> Poll<io::Result<()>>;
+}
+
+// ===== AsyncPlayer =====
+
+/// The `poll_next()` function of an async stream of log records.
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+pub(crate) type PollNextRecord<R, E> =
+    fn(Pin<&mut R>, &mut Context<'_>) -> Poll<Option<Result<LogRecord, E>>>;
+
+/// The replay shared by the async players.
+///
+/// Each runtime gives the functions to get the next record from its type
+/// of stream, and to create a timer that waits until a deadline.
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+#[derive(Debug)]
+pub(crate) struct AsyncPlayer<S> {
+    opts: ReplayOptions,
+    sinks: HashMap<String, S>,
+}
+
+#[cfg(any(
+    feature = "tokio",
+    feature = "async-io",
+    feature = "async-std",
+    feature = "smol"
+))]
+impl<S: AsyncFrameSink> AsyncPlayer<S> {
+    /// Creates a player with the specified options.
+    pub fn new(opts: ReplayOptions) -> Self {
+        Self {
+            opts,
+            sinks: HashMap::new(),
+        }
+    }
+
+    /// Gets the options for the player.
+    pub fn options(&self) -> &ReplayOptions {
+        &self.opts
+    }
+
+    /// Adds the sink to use for the named output interface.
+    pub fn add_sink(&mut self, iface: String, sink: S) {
+        self.sinks.insert(iface, sink);
+    }
+
+    /// Plays the records from a log.
+    pub async fn play<R, E, W>(
+        &mut self,
+        mut records: R,
+        poll_next: PollNextRecord<R, E>,
+        timer: fn(Instant) -> W,
+    ) -> Result<u64, Error>
+    where
+        R: Unpin,
+        Error: From<E>,
+        W: Future,
+    {
+        let opts = self.opts.clone();
+        let mut sched = Scheduler::new(&opts);
+        self.play_pass(&mut sched, &mut records, poll_next, timer)
+            .await
+    }
+
+    /// Plays a log repeatedly.
+    ///
+    /// See [`Player::play_loop`].
+    pub async fn play_loop<R, E, F, W>(
+        &mut self,
+        loops: Option<u32>,
+        mut open: F,
+        poll_next: PollNextRecord<R, E>,
+        timer: fn(Instant) -> W,
+    ) -> Result<u64, Error>
+    where
+        R: Unpin,
+        Error: From<E>,
+        F: FnMut() -> Result<R, Error>,
+        W: Future,
+    {
+        let opts = self.opts.clone();
+        let mut sched = Scheduler::new(&opts);
+        let mut n = 0;
+
+        for _ in 0..loops.unwrap_or(u32::MAX) {
+            sched.restart();
+            let mut records = open()?;
+            match self
+                .play_pass(&mut sched, &mut records, poll_next, timer)
+                .await?
+            {
+                0 => break,
+                k => n += k,
+            }
+        }
+        Ok(n)
+    }
+
+    /// Plays a single pass through a log
+    async fn play_pass<R, E, W>(
+        &mut self,
+        sched: &mut Scheduler<'_>,
+        records: &mut R,
+        poll_next: PollNextRecord<R, E>,
+        timer: fn(Instant) -> W,
+    ) -> Result<u64, Error>
+    where
+        R: Unpin,
+        Error: From<E>,
+        W: Future,
+    {
+        let mut n = 0;
+        while let Some(rec) = poll_fn(|cx| poll_next(Pin::new(records), cx)).await {
+            let Some(sf) = sched.schedule(rec?) else {
+                continue;
+            };
+            timer(sf.deadline).await;
+
+            let sink = match self.sinks.entry(sf.iface) {
+                Entry::Occupied(ent) => ent.into_mut(),
+                Entry::Vacant(ent) => {
+                    let sink = S::open_sink(ent.key())?;
+                    ent.insert(sink)
+                }
+            };
+            poll_fn(|cx| sink.poll_send_frame(cx, &sf.frame)).await?;
+            n += 1;
+        }
+        Ok(n)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{logfile::LogRecord, CanDataFrame, EmbeddedFrame, StandardId};
+    use std::{cell::RefCell, rc::Rc};
+
+    /// A log reader over a list of records
+    struct VecReader(std::vec::IntoIter<LogRecord>);
+
+    impl LogReader for VecReader {
+        fn read_record(&mut self) -> Result<Option<LogRecord>, Error> {
+            Ok(self.0.next())
+        }
+    }
+
+    /// A sink that keeps the time that each frame was sent
+    #[derive(Default)]
+    struct TestSink(Rc<RefCell<Vec<(Instant, CanAnyFrame)>>>);
+
+    impl FrameSink for TestSink {
+        fn open_sink(_iface: &str) -> io::Result<Self> {
+            Err(io::ErrorKind::NotFound.into())
+        }
+
+        fn send_frame(&mut self, frame: &CanAnyFrame) -> io::Result<()> {
+            self.0.borrow_mut().push((Instant::now(), *frame));
+            Ok(())
+        }
+    }
+
+    // Records on "can1" at 0, 1, and 2 sec, and on "can2" at 1.5 sec
+    fn test_log() -> VecReader {
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_697_630_400);
+        let frame = CanAnyFrame::Normal(
+            CanDataFrame::new(StandardId::new(0x123).unwrap(), &[1, 2]).unwrap(),
+        );
+        VecReader(
+            vec![
+                LogRecord::new(t0, "can1", frame),
+                LogRecord::new(t0 + Duration::from_millis(1000), "can1", frame),
+                LogRecord::new(t0 + Duration::from_millis(1500), "can2", frame),
+                LogRecord::new(t0 + Duration::from_millis(2000), "can1", frame),
+            ]
+            .into_iter(),
+        )
+    }
+
+    #[test]
+    fn test_mapping() {
+        let mut opts = ReplayOptions::default();
+        assert_eq!(Some("can1"), opts.output_interface("can1"));
+
+        opts.add_mapping("can1=vcan0").unwrap();
+        assert_eq!(Some("vcan0"), opts.output_interface("can1"));
+        assert_eq!(None, opts.output_interface("can2"));
+
+        assert!(opts.add_mapping("can1").is_err());
+        assert!(opts.add_mapping("=vcan0").is_err());
+    }
+
+    #[test]
+    fn test_scheduler() {
+        let mut opts = ReplayOptions {
+            speed: 2.0,
+            ..ReplayOptions::default()
+        };
+        opts.max_gap = Some(Duration::from_millis(400));
+
+        let mut sched = Scheduler::new(&opts);
+        let mut rdr = test_log();
+
+        let t0 = sched.next(&mut rdr).unwrap().unwrap().deadline;
+        let t1 = sched.next(&mut rdr).unwrap().unwrap().deadline;
+        let t2 = sched.next(&mut rdr).unwrap().unwrap().deadline;
+        let t3 = sched.next(&mut rdr).unwrap().unwrap().deadline;
+        assert!(sched.next(&mut rdr).unwrap().is_none());
+
+        // 1 sec at 2x is 500ms, capped to 400
+        assert_eq!(Duration::from_millis(400), t1 - t0);
+        assert_eq!(Duration::from_millis(250), t2 - t1);
+        assert_eq!(Duration::from_millis(250), t3 - t2);
+    }
+
+    #[test]
+    fn test_play() {
+        let mut opts = ReplayOptions {
+            speed: 20.0,
+            ..ReplayOptions::default()
+        };
+        opts.add_mapping("can1=vcan0").unwrap();
+
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let mut player = Player::new(opts);
+        player.add_sink("vcan0", TestSink(sent.clone()));
+
+        let start = Instant::now();
+        assert_eq!(3, player.play(test_log()).unwrap());
+
+        // 2 sec at 20x is 100ms
+        let sent = sent.borrow();
+        assert_eq!(3, sent.len());
+        assert!(sent[2].0 - start >= Duration::from_millis(100));
+        assert!(sent[1].0 - sent[0].0 >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_play_loop() {
+        let opts = ReplayOptions {
+            speed: f64::INFINITY,
+            ..ReplayOptions::default()
+        };
+
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let mut player = Player::new(opts);
+        player.add_sink("can1", TestSink(sent.clone()));
+        player.add_sink("can2", TestSink(sent.clone()));
+
+        let start = Instant::now();
+        assert_eq!(12, player.play_loop(Some(3), || Ok(test_log())).unwrap());
+        assert_eq!(12, sent.borrow().len());
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // An interface without a sink can't be opened
+        let mut player: Player<TestSink> = Player::new(ReplayOptions::default());
+        assert!(matches!(player.play(test_log()), Err(Error::Io(_))));
+    }
+}
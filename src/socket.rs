@@ -16,7 +16,7 @@ use crate::{
     frame::{can_frame_default, canfd_frame_default, AsPtr, CAN_ERR_MASK},
     CanAddr, CanAnyFrame, CanFdFrame, CanFrame, CanRawFrame, IoError, IoErrorKind, IoResult,
 };
-use libc::{canid_t, socklen_t, AF_CAN, EINPROGRESS};
+use libc::{canid_t, socklen_t, AF_CAN, EINPROGRESS, ENOENT};
 use socket2::SockAddr;
 use std::{
     fmt,
@@ -27,7 +27,7 @@ use std::{
         unix::io::{AsFd, AsRawFd, BorrowedFd, IntoRawFd, OwnedFd, RawFd},
     },
     ptr,
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 
 pub use libc::{
@@ -35,6 +35,9 @@ pub use libc::{
     CAN_RAW_JOIN_FILTERS, CAN_RAW_LOOPBACK, CAN_RAW_RECV_OWN_MSGS, SOL_CAN_BASE, SOL_CAN_RAW,
 };
 
+/// The ioctl to get the receive timestamp of the last frame, as a `timespec`
+const SIOCGSTAMPNS: u32 = 0x8907;
+
 /// Check an error return value for timeouts.
 ///
 /// Due to the fact that timeouts are reported as errors, calling `read_frame`
@@ -81,10 +84,37 @@ fn raw_open_socket(addr: &CanAddr) -> IoResult<socket2::Socket> {
     let can_raw = socket2::Protocol::from(CAN_RAW);
 
     let sock = socket2::Socket::new_raw(af_can, socket2::Type::RAW, Some(can_raw))?;
+
+    // The kernel only starts to timestamp the frames for a socket the first
+    // time that a timestamp is requested, so this is done before any frames
+    // can arrive. With no frames yet, there's no timestamp to get.
+    match rx_timestamp(sock.as_raw_fd()) {
+        Err(err) if err.raw_os_error() != Some(ENOENT) => return Err(err),
+        _ => (),
+    }
+
     sock.bind(&SockAddr::from(*addr))?;
     Ok(sock)
 }
 
+/// Gets the receive timestamp of the last frame read from the socket.
+///
+/// This fails with `ENOENT` if the socket hasn't received a frame yet.
+fn rx_timestamp(fd: RawFd) -> IoResult<SystemTime> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    let ret = unsafe { libc::ioctl(fd, SIOCGSTAMPNS as _, &mut ts as *mut libc::timespec) };
+
+    if ret == -1 {
+        return Err(IoError::last_os_error());
+    }
+
+    let ts = Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32);
+    Ok(SystemTime::UNIX_EPOCH + ts)
+}
+
 /// `setsockopt` wrapper
 ///
 /// The libc `setsockopt` function is set to set various options on a socket.
@@ -257,14 +287,29 @@ pub trait Socket: AsRawFd {
         }
     }
 
+    /// Blocking read a single can frame with the time it was received.
+    ///
+    /// The timestamp is the one that the kernel recorded when the frame
+    /// arrived, which is what is wanted for logging and captures.
+    ///
+    /// Sockets that are opened by this crate have timestamps enabled from
+    /// the start. For a socket that was opened elsewhere, like from a raw
+    /// file descriptor, the kernel only starts timestamping frames with
+    /// the first call, so the first frame may get the current time instead.
+    ///
+    /// Note that reading a frame and retrieving the timestamp requires two
+    /// consecutive syscalls. If the socket is shared with another thread
+    /// that is also reading, the timestamp may belong to the other frame.
+    fn read_frame_with_timestamp(&self) -> IoResult<(Self::FrameType, SystemTime)> {
+        let frame = self.read_frame()?;
+        Ok((frame, rx_timestamp(self.as_raw_fd())?))
+    }
+
     /// Write a single can frame.
     ///
     /// Note that this function can fail with an `EAGAIN` error or similar.
     /// Use `write_frame_insist` if you need to be sure that the message got
     /// sent or failed.
-    //fn write_frame(&self, frame: &Self::FrameType) -> IoResult<()>;
-
-    /// Writes a normal CAN 2.0 frame to the socket.
     fn write_frame<F>(&self, frame: &F) -> IoResult<()>
     where
         F: Into<Self::FrameType> + AsPtr;
@@ -440,34 +485,6 @@ pub trait SocketOptions: AsRawFd {
     }
 }
 
-// TODO: We need to restore this, but preferably with TIMESTAMPING
-
-/*
-impl CanSocket {
-
-    /// Blocking read a single can frame with timestamp
-    ///
-    /// Note that reading a frame and retrieving the timestamp requires two
-    /// consecutive syscalls. To avoid race conditions, exclusive access
-    /// to the socket is enforce through requiring a `mut &self`.
-    pub fn read_frame_with_timestamp(&mut self) -> IoResult<(CanFrame, time::SystemTime)> {
-        let frame = self.read_frame()?;
-
-        let mut ts = timespec { tv_sec: 0, tv_nsec: 0 };
-        let ret = unsafe {
-            libc::ioctl(self.fd, SIOCGSTAMPNS as c_ulong, &mut ts as *mut timespec)
-        };
-
-        if ret == -1 {
-            return Err(IoError::last_os_error());
-        }
-
-        Ok((frame, system_time_from_timespec(ts)))
-    }
-
-}
-*/
-
 // ===== CanSocket =====
 
 /// A socket for classic CAN 2.0 devices.
@@ -759,3 +776,40 @@ impl AsRef<libc::can_filter> for CanFilter {
         &self.0
     }
 }
+
+/// Serialization of a filter as `{ "id", "mask", "inverted" }`, with the
+/// inverted flag separate from the ID.
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use super::*;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct FilterRepr {
+        id: canid_t,
+        mask: canid_t,
+        inverted: bool,
+    }
+
+    impl Serialize for CanFilter {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            FilterRepr {
+                id: self.0.can_id & !libc::CAN_INV_FILTER,
+                mask: self.0.can_mask,
+                inverted: self.0.can_id & libc::CAN_INV_FILTER != 0,
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for CanFilter {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let repr = FilterRepr::deserialize(deserializer)?;
+            Ok(if repr.inverted {
+                CanFilter::new_inverted(repr.id, repr.mask)
+            } else {
+                CanFilter::new(repr.id, repr.mask)
+            })
+        }
+    }
+}
@@ -43,6 +43,38 @@ use tokio::io::unix::AsyncFd;
 use tokio::io::Interest;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
+#[cfg(feature = "dump")]
+use crate::{
+    dump::{AsyncLines, ParseError},
+    logfile::{Error as LogError, LogRecord},
+    replay::{AsyncFrameSink, AsyncPlayer, ReplayOptions},
+};
+#[cfg(feature = "dump")]
+use tokio::io::{AsyncBufRead, BufReader};
+
+#[cfg(feature = "netlink")]
+use crate::nl::{LinkEvent, NlInfoError};
+#[cfg(feature = "netlink")]
+use neli::{
+    consts::{nl::NlType, rtnl::Rtm},
+    err::NlError,
+    nl::Nlmsghdr,
+    rtnl::Ifinfomsg,
+    FromBytesWithInput,
+};
+#[cfg(feature = "netlink")]
+use std::fmt::Debug;
+
+/// Registers an I/O object with the tokio reactor.
+///
+/// `AsyncFd::new()` is deprecated because it can't make sure that the file
+/// descriptor stays open while it's registered. The objects registered here
+/// all own their file descriptor, so it stays open as long as the `AsyncFd`.
+#[allow(deprecated)]
+fn async_fd<T: AsRawFd>(inner: T) -> IoResult<AsyncFd<T>> {
+    AsyncFd::new(inner)
+}
+
 /// An asynchronous I/O wrapped CanSocket
 #[derive(Debug)]
 pub struct AsyncCanSocket<T: Socket>(AsyncFd<T>);
@@ -52,21 +84,21 @@ impl<T: Socket + From<OwnedFd>> AsyncCanSocket<T> {
     pub fn open(ifname: &str) -> IoResult<Self> {
         let sock = T::open(ifname)?;
         sock.set_nonblocking(true)?;
-        Ok(Self(AsyncFd::new(sock)?))
+        Ok(Self(async_fd(sock)?))
     }
 
     /// Open CAN device by kernel interface number
     pub fn open_if(ifindex: u32) -> IoResult<Self> {
         let sock = T::open_iface(ifindex)?;
         sock.set_nonblocking(true)?;
-        Ok(Self(AsyncFd::new(sock)?))
+        Ok(Self(async_fd(sock)?))
     }
 
     /// Open a CAN socket by address
     pub fn open_addr(addr: &CanAddr) -> IoResult<Self> {
         let sock = T::open_addr(addr)?;
         sock.set_nonblocking(true)?;
-        Ok(Self(AsyncFd::new(sock)?))
+        Ok(Self(async_fd(sock)?))
     }
 }
 
@@ -288,6 +320,262 @@ impl AsyncWrite for CanFdSocket {
 
 /////////////////////////////////////////////////////////////////////////////
 
+/// An asynchronous candump log reader.
+///
+/// This is the async equivalent of [`dump::Reader`](crate::dump::Reader),
+/// and is a `Stream` of the records in the log. The stream ends at the
+/// end of the input, unless the reader is set to
+/// [follow](Self::set_follow) a log that is still being written.
+#[cfg(feature = "dump")]
+#[derive(Debug)]
+pub struct DumpReader<R>(AsyncLines<R, Pin<Box<tokio::time::Sleep>>>);
+
+#[cfg(feature = "dump")]
+impl<R: AsyncRead + Unpin> DumpReader<BufReader<R>> {
+    /// Creates an I/O buffered reader from an asynchronous reader.
+    pub fn from_reader(rdr: R) -> Self {
+        DumpReader::new(BufReader::new(rdr))
+    }
+}
+
+#[cfg(feature = "dump")]
+impl<R: AsyncBufRead + Unpin> DumpReader<R> {
+    /// Creates a reader from a buffered asynchronous reader.
+    pub fn new(rdr: R) -> Self {
+        Self(AsyncLines::new(rdr))
+    }
+
+    /// Sets whether the reader is lenient.
+    ///
+    /// See [`dump::Reader::set_lenient`](crate::dump::Reader::set_lenient).
+    pub fn set_lenient(&mut self, lenient: bool) {
+        self.0.lines.parser.lenient = lenient;
+    }
+
+    /// Determines if the reader is lenient.
+    pub fn is_lenient(&self) -> bool {
+        self.0.lines.parser.lenient
+    }
+
+    /// Sets whether the reader follows a log that is still being written,
+    /// like `tail -f`.
+    ///
+    /// When following, the reader waits for more data at the end of the
+    /// input, checking for it periodically, so the stream never ends. A
+    /// partial line at the end of the input is kept until its newline
+    /// arrives. Otherwise the stream ends at the end of the input, and a
+    /// last line without a newline is taken as complete.
+    pub fn set_follow(&mut self, follow: bool) {
+        self.0.lines.follow = follow;
+    }
+
+    /// Determines if the reader follows the log.
+    pub fn is_following(&self) -> bool {
+        self.0.lines.follow
+    }
+
+    /// Gets the number of malformed lines skipped by a lenient reader.
+    pub fn skipped(&self) -> u64 {
+        self.0.lines.parser.skipped
+    }
+
+    /// Gets the number of lines read so far.
+    pub fn line_number(&self) -> u64 {
+        self.0.lines.parser.line
+    }
+
+    /// Reads the next record from the log.
+    ///
+    /// Returns `None` at the end of the input.
+    pub async fn next_record(&mut self) -> std::result::Result<Option<LogRecord>, ParseError> {
+        self.next().await.transpose()
+    }
+}
+
+#[cfg(feature = "dump")]
+impl<R: AsyncBufRead + Unpin> Stream for DumpReader<R> {
+    type Item = std::result::Result<LogRecord, ParseError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .0
+            .poll_next(cx, R::poll_fill_buf, R::consume, |d| {
+                Box::pin(tokio::time::sleep(d))
+            })
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+/// An asynchronous player to replay CAN logs to sockets.
+///
+/// This is the async equivalent of [`replay::Player`](crate::replay::Player).
+/// The sink for each interface is opened the first time that it is needed,
+/// unless one was already added to the player.
+#[cfg(feature = "dump")]
+#[derive(Debug)]
+pub struct Player<S = CanFdSocket>(AsyncPlayer<S>);
+
+#[cfg(feature = "dump")]
+impl<S: AsyncFrameSink> Player<S> {
+    /// Creates a player with the specified options.
+    pub fn new(opts: ReplayOptions) -> Self {
+        Self(AsyncPlayer::new(opts))
+    }
+
+    /// Gets the options for the player.
+    pub fn options(&self) -> &ReplayOptions {
+        self.0.options()
+    }
+
+    /// Adds the sink to use for the named output interface.
+    pub fn add_sink<T: Into<String>>(&mut self, iface: T, sink: S) {
+        self.0.add_sink(iface.into(), sink);
+    }
+
+    /// Plays the records from a log.
+    ///
+    /// The records come from an async stream, like a [`DumpReader`], so
+    /// that reading the log doesn't block the executor.
+    ///
+    /// On success, returns the number of frames that were sent.
+    pub async fn play<R, E>(&mut self, records: R) -> std::result::Result<u64, LogError>
+    where
+        R: Stream<Item = std::result::Result<LogRecord, E>> + Unpin,
+        LogError: From<E>,
+    {
+        self.0.play(records, R::poll_next, sleep_until).await
+    }
+
+    /// Plays a log repeatedly.
+    ///
+    /// See [`replay::Player::play_loop`](crate::replay::Player::play_loop).
+    pub async fn play_loop<R, E, F>(
+        &mut self,
+        loops: Option<u32>,
+        open: F,
+    ) -> std::result::Result<u64, LogError>
+    where
+        R: Stream<Item = std::result::Result<LogRecord, E>> + Unpin,
+        LogError: From<E>,
+        F: FnMut() -> std::result::Result<R, LogError>,
+    {
+        self.0
+            .play_loop(loops, open, R::poll_next, sleep_until)
+            .await
+    }
+}
+
+/// Creates a timer for the player that waits until the deadline.
+#[cfg(feature = "dump")]
+fn sleep_until(deadline: std::time::Instant) -> tokio::time::Sleep {
+    tokio::time::sleep_until(deadline.into())
+}
+
+#[cfg(feature = "dump")]
+impl AsyncFrameSink for CanFdSocket {
+    fn open_sink(iface: &str) -> IoResult<Self> {
+        CanFdSocket::open(iface)
+    }
+
+    fn poll_send_frame(&mut self, cx: &mut Context<'_>, frame: &CanAnyFrame) -> Poll<IoResult<()>> {
+        loop {
+            let mut ready_guard = ready!(self.0.poll_write_ready(cx))?;
+            match ready_guard.try_io(|inner| inner.get_ref().write_frame(frame)) {
+                Ok(result) => return Poll::Ready(result),
+                Err(_would_block) => continue,
+            }
+        }
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+/// An asynchronous monitor for changes to the CAN interfaces.
+///
+/// This is the async equivalent of [`nl::LinkMonitor`](crate::nl::LinkMonitor),
+/// and is a `Stream` of the events.
+#[cfg(feature = "netlink")]
+#[derive(Debug)]
+pub struct LinkMonitor(AsyncFd<crate::nl::LinkMonitor>);
+
+#[cfg(feature = "netlink")]
+impl LinkMonitor {
+    /// Creates a monitor for the CAN interfaces.
+    pub fn new() -> std::result::Result<Self, NlInfoError> {
+        let monitor = crate::nl::LinkMonitor::new()?;
+        monitor.set_nonblocking(true)?;
+        Ok(Self(async_fd(monitor)?))
+    }
+
+    /// Gets a reference to the underlying blocking monitor.
+    pub fn get_ref(&self) -> &crate::nl::LinkMonitor {
+        self.0.get_ref()
+    }
+
+    /// Waits for the next event.
+    pub async fn next_event(&mut self) -> std::result::Result<LinkEvent, NlInfoError> {
+        std::future::poll_fn(|cx| self.poll_event(cx)).await
+    }
+
+    /// Polls for the next event.
+    fn poll_event(
+        &mut self,
+        cx: &mut Context,
+    ) -> Poll<std::result::Result<LinkEvent, NlInfoError>> {
+        loop {
+            let mut ready_guard = ready!(self.0.poll_read_ready_mut(cx))?;
+            match ready_guard.get_inner_mut().next_event() {
+                Ok(Some(event)) => return Poll::Ready(Ok(event)),
+                Ok(None) => ready_guard.clear_ready(),
+                Err(err) => return Poll::Ready(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "netlink")]
+impl Stream for LinkMonitor {
+    type Item = std::result::Result<LinkEvent, NlInfoError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.get_mut().poll_event(cx).map(Some)
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+/// Sends a request to the kernel on a new, non-blocking, netlink socket,
+/// and waits for the response.
+///
+/// This is all that the async [`CanInterface`] needs from the runtime.
+#[cfg(feature = "netlink")]
+async fn nl_request<T, P>(
+    hdr: Nlmsghdr<Rtm, Ifinfomsg>,
+) -> std::result::Result<Option<Nlmsghdr<T, P>>, NlError<T, P>>
+where
+    T: NlType + Debug,
+    P: for<'a> FromBytesWithInput<'a, Input = usize> + Debug,
+{
+    let mut sock = crate::nl::CanInterface::open_route_socket()?;
+    sock.nonblock()?;
+    sock.send(hdr)?;
+
+    let mut sock = async_fd(sock)?;
+    loop {
+        let mut ready_guard = sock.readable_mut().await?;
+        match ready_guard.get_inner_mut().recv::<T, P>() {
+            Ok(None) => ready_guard.clear_ready(),
+            res => return res,
+        }
+    }
+}
+
+#[cfg(feature = "netlink")]
+crate::nl::async_can_interface!();
+
+/////////////////////////////////////////////////////////////////////////////
+
 #[cfg(feature = "vcan_tests")]
 #[cfg(test)]
 mod tests {
@@ -570,3 +858,193 @@ mod tests {
         Ok(())
     }
 }
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(feature = "dump")]
+#[cfg(test)]
+mod dump_tests {
+    use super::*;
+    use crate::{CanDataFrame, EmbeddedFrame, Frame, StandardId};
+    use futures::executor::block_on;
+    use std::{
+        cell::RefCell,
+        io,
+        rc::Rc,
+        time::{Duration, Instant, SystemTime},
+    };
+    use tokio::io::AsyncWriteExt;
+
+    const LOG: &[u8] = b"(1469439874.299591) can1 080#\n\
+                         (1469439874.299654) can1 701#7F\n\
+                         (1469439874.29x654) can1 123#\r\n\
+                         (1469439874.299700) vcan0 00000050#0102";
+
+    // Frames on "can1" at 0, 100, and 150 ms, and on "can2" at 50 ms
+    fn play_log() -> impl Stream<Item = io::Result<LogRecord>> + Unpin {
+        let t0 = SystemTime::UNIX_EPOCH + Duration::from_secs(1_469_439_874);
+        let rec = |ms, iface, id, data: &[u8]| {
+            let frame = CanDataFrame::new(StandardId::new(id).unwrap(), data).unwrap();
+            Ok(LogRecord::new(
+                t0 + Duration::from_millis(ms),
+                iface,
+                CanAnyFrame::Normal(frame),
+            ))
+        };
+        stream::iter(vec![
+            rec(0, "can1", 0x080, &[]),
+            rec(50, "can2", 0x701, &[0x7F]),
+            rec(100, "can1", 0x123, &[0x01]),
+            rec(150, "can1", 0x124, &[0x02, 0x03]),
+        ])
+    }
+
+    /// A sink that keeps the time that each frame was sent
+    struct TestSink(Rc<RefCell<Vec<(Instant, CanAnyFrame)>>>);
+
+    impl AsyncFrameSink for TestSink {
+        fn open_sink(_iface: &str) -> io::Result<Self> {
+            Err(io::ErrorKind::NotFound.into())
+        }
+
+        fn poll_send_frame(
+            &mut self,
+            _cx: &mut Context<'_>,
+            frame: &CanAnyFrame,
+        ) -> Poll<io::Result<()>> {
+            self.0.borrow_mut().push((Instant::now(), *frame));
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_player() {
+        let mut opts = ReplayOptions::default();
+        opts.add_mapping("can1=vcan0").unwrap();
+
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let mut player = Player::new(opts);
+        player.add_sink("vcan0", TestSink(sent.clone()));
+
+        let start = Instant::now();
+        assert_eq!(3, player.play(play_log()).await.unwrap());
+
+        let sent = sent.borrow();
+        let ids: Vec<_> = sent
+            .iter()
+            .map(|(_, frame)| match frame {
+                CanAnyFrame::Normal(frame) => frame.raw_id(),
+                _ => panic!("Expected a data frame"),
+            })
+            .collect();
+        assert_eq!(vec![0x080, 0x123, 0x124], ids);
+
+        // The frames keep their times from the start of the replay
+        assert!(sent[0].0 - start < Duration::from_millis(100));
+        assert!(sent[1].0 - start >= Duration::from_millis(100));
+        assert!(sent[2].0 - start >= Duration::from_millis(150));
+    }
+
+    #[tokio::test]
+    async fn test_player_errors() {
+        let mut opts = ReplayOptions::default();
+        opts.map_interface("can9", "vcan9");
+        let mut player = Player::<TestSink>::new(opts);
+
+        // None of the records are for the mapped interface, so nothing is sent
+        assert_eq!(0, player.play(play_log()).await.unwrap());
+
+        // An error from the log stops the replay
+        let err = io::Error::from(io::ErrorKind::InvalidData);
+        let err = player.play(stream::iter(vec![Err(err)])).await.unwrap_err();
+        assert!(matches!(err, LogError::Io(err) if err.kind() == io::ErrorKind::InvalidData));
+
+        // As does a sink that can't be opened
+        let mut player = Player::<TestSink>::new(ReplayOptions::default());
+        let err = player.play(play_log()).await.unwrap_err();
+        assert!(matches!(err, LogError::Io(err) if err.kind() == io::ErrorKind::NotFound));
+    }
+
+    #[tokio::test]
+    async fn test_follow() {
+        let (mut tx, rx) = tokio::io::duplex(64);
+        let mut rdr = DumpReader::from_reader(rx);
+        rdr.set_follow(true);
+
+        tx.write_all(b"(1469439874.299591) can1 ").await.unwrap();
+        let wait = tokio::time::timeout(Duration::from_millis(200), rdr.next_record()).await;
+        assert!(wait.is_err());
+
+        tx.write_all(b"080#\n").await.unwrap();
+        drop(tx);
+        let rec = rdr.next_record().await.unwrap().unwrap();
+        assert_eq!("can1", rec.interface);
+        assert_eq!(1, rdr.line_number());
+    }
+
+    #[test]
+    fn test_dump_reader() {
+        let recs = block_on(DumpReader::from_reader(LOG).take(2).collect::<Vec<_>>());
+        let rec = recs[1].as_ref().unwrap();
+        assert_eq!("can1", rec.interface);
+        let CanAnyFrame::Normal(frame) = rec.frame else {
+            panic!("Expected a data frame");
+        };
+        assert_eq!(0x701, frame.raw_id());
+        assert_eq!(&[0x7F], frame.data());
+
+        let mut rdr = DumpReader::from_reader(LOG);
+        rdr.set_lenient(true);
+        let recs = block_on((&mut rdr).collect::<Vec<_>>());
+        assert_eq!(3, recs.len());
+        assert_eq!(1, rdr.skipped());
+        assert_eq!(4, rdr.line_number());
+
+        let rec = recs[2].as_ref().unwrap();
+        assert_eq!("vcan0", rec.interface);
+        let CanAnyFrame::Normal(frame) = rec.frame else {
+            panic!("Expected a data frame");
+        };
+        assert!(frame.is_extended());
+        assert_eq!(&[1, 2], frame.data());
+
+        let mut rdr = DumpReader::from_reader(LOG);
+        let err = block_on((&mut rdr).collect::<Vec<_>>())
+            .into_iter()
+            .find_map(|res| res.err())
+            .unwrap();
+        assert_eq!(3, err.position().unwrap().line);
+    }
+}
+
+#[cfg(feature = "netlink_tests")]
+#[cfg(test)]
+mod netlink_tests {
+    use super::*;
+    use crate::nl::SetCanParams;
+
+    #[tokio::test]
+    async fn interface() {
+        let iface = CanInterface::open("lo").unwrap();
+        let expected = iface.get_ref().details().unwrap();
+
+        // The futures can be run on other threads
+        let details = tokio::spawn(async move { iface.details().await })
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(expected.index, details.index);
+        assert_eq!(expected.name, details.name);
+        assert!(details.is_up);
+
+        let iface = CanInterface::open_iface(details.index);
+        iface.bring_up().await.unwrap();
+
+        // The loopback isn't a CAN interface, so the kernel refuses this
+        let params = SetCanParams {
+            restart_ms: Some(100),
+            ..SetCanParams::default()
+        };
+        assert!(iface.set_can_params(&params).await.is_err());
+    }
+}
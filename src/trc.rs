@@ -0,0 +1,1239 @@
+// socketcan/src/trc.rs
+//
+// Implements PEAK TRC trace file reading and writing.
+//
+// This file is part of the Rust 'socketcan-rs' library.
+//
+// Licensed under the MIT license:
+//   <LICENSE or http://opensource.org/licenses/MIT>
+// This file may not be copied, modified, or distributed except according
+// to those terms.
+
+//! PEAK TRC trace file support.
+//!
+//! Reads and writes the text trace files that are produced by the tools from
+//! PEAK-System, like PCAN-View and PCAN-Basic. File versions 1.1 through 2.1
+//! are supported. A file without a `$FILEVERSION` header is treated as the
+//! original, version 1.0, layout.
+//!
+//! Example (version 2.1):
+//!
+//! ```text
+//! ;$FILEVERSION=2.1
+//! ;$STARTTIME=45217.500000000000
+//! ;$COLUMNS=N,O,T,B,I,d,R,L,D
+//! ;
+//!       1         0.000 DT 1     0123 Rx -  2    11 22
+//!       2         1.250 FB 1 18FF0001 Tx -  9    00 01 02 03 04 05 06 07 08 09 0A 0B
+//! ```
+//!
+//! The PEAK format stores the time of each message as an offset, in
+//! milliseconds, from the start time in the file header. The records used
+//! here hold the absolute time, in microseconds since the Unix epoch, the
+//! same as the records from the [`dump`](crate::dump) module. For a file
+//! without a start time, this is just the offset from the start of the trace.
+//!
+//! The format spec is available from PEAK-System:
+//! <https://www.peak-system.com/produktcd/Pdf/English/PEAK_CAN_TRC_File_Format.pdf>
+//!
+
+pub use crate::logfile::Direction;
+
+use crate::{
+    frame::{dlc_to_len, FdFlags, CAN_EFF_FLAG, CAN_ERR_FLAG, CAN_MAX_DLEN, CAN_MAX_RAW_DLC},
+    logfile::{self, LogReader, LogRecord, LogWriter},
+    CanAnyFrame, CanDataFrame, CanErrorFrame, CanFdFrame, CanRemoteFrame, ConstructionError,
+    EmbeddedFrame, ExtendedId, Frame, Id, StandardId,
+};
+use std::{
+    fmt, fs,
+    io::{self, BufRead, Write},
+    path,
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
+use thiserror::Error;
+
+/// The number of days between the OLE Automation epoch (1899-12-30),
+/// used for the TRC start time, and the Unix epoch.
+const OLE_UNIX_EPOCH_DAYS: u64 = 25569;
+
+/// The number of microseconds in a day.
+const US_PER_DAY: u64 = 86_400_000_000;
+
+/// The number of decimal places written for the start time, which is a
+/// resolution of less than 0.1 µs.
+const START_TIME_DECIMALS: usize = 12;
+
+/// Parses a TRC start time, in fractional days since the OLE Automation
+/// epoch, into microseconds since the Unix epoch.
+///
+/// This is done with integers, rather than floating point, so that the
+/// time is exact to the microsecond. Times before the Unix epoch are
+/// clamped to it.
+fn parse_start_time(s: &str) -> Option<u64> {
+    let (days, frac) = s.split_once('.').unwrap_or((s, ""));
+    let all_digits = |s: &str| s.bytes().all(|c| c.is_ascii_digit());
+    if days.is_empty() || !all_digits(days) || !all_digits(frac) {
+        return None;
+    }
+
+    let days: u64 = days.parse().ok()?;
+    // digits past the resolution of a u128 don't matter
+    let frac = &frac[..frac.len().min(20)];
+    let scale = 10u128.pow(frac.len() as u32);
+    let frac = frac.parse::<u128>().unwrap_or(0);
+    let frac_us = ((frac * US_PER_DAY as u128 + scale / 2) / scale) as u64;
+
+    let us = days.checked_mul(US_PER_DAY)?.checked_add(frac_us)?;
+    Some(us.saturating_sub(OLE_UNIX_EPOCH_DAYS * US_PER_DAY))
+}
+
+/// Formats a time, in microseconds since the Unix epoch, as a TRC start
+/// time, in fractional days since the OLE Automation epoch.
+fn fmt_start_time(us: u64) -> String {
+    let days = OLE_UNIX_EPOCH_DAYS + us / US_PER_DAY;
+    let scale = 10u128.pow(START_TIME_DECIMALS as u32);
+    let frac = (us % US_PER_DAY) as u128 * scale / US_PER_DAY as u128;
+    format!("{}.{:0width$}", days, frac, width = START_TIME_DECIMALS)
+}
+
+// Error frame class bits, from linux/can/error.h
+const CAN_ERR_PROT: u32 = 0x0008;
+const CAN_ERR_CNT: u32 = 0x0200;
+
+// Protocol violation types, from linux/can/error.h
+const CAN_ERR_PROT_BIT: u8 = 0x01;
+const CAN_ERR_PROT_FORM: u8 = 0x02;
+const CAN_ERR_PROT_STUFF: u8 = 0x04;
+const CAN_ERR_PROT_TX: u8 = 0x80;
+
+// PEAK error frame types (data byte 0 of an error frame record)
+const PEAK_ERR_BIT: u8 = 0x01;
+const PEAK_ERR_FORM: u8 = 0x02;
+const PEAK_ERR_STUFF: u8 = 0x04;
+const PEAK_ERR_OTHER: u8 = 0x08;
+
+/// The number of data bytes in a PEAK error frame record.
+const PEAK_ERR_LEN: usize = 5;
+
+// ===== TrcVersion =====
+
+/// The version of the TRC file format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TrcVersion {
+    /// The original format, without a version header
+    V1_0,
+    /// Adds the message type (direction) column
+    V1_1,
+    /// Adds the bus (channel) column
+    V1_2,
+    /// Adds a reserved column
+    V1_3,
+    /// Column-based format with CAN FD support
+    V2_0,
+    /// Adds the bus and reserved columns to 2.0
+    V2_1,
+}
+
+impl TrcVersion {
+    /// Whether this version of the file can hold CAN FD frames.
+    pub fn supports_fd(&self) -> bool {
+        *self >= TrcVersion::V2_0
+    }
+
+    /// The default columns for a version of the file.
+    ///
+    /// For 2.x files these can be overridden by a `$COLUMNS` header.
+    fn columns(&self) -> Vec<Column> {
+        use Column::*;
+        match *self {
+            TrcVersion::V1_0 => vec![Number, Offset, Id, Dlc, Data],
+            TrcVersion::V1_1 => vec![Number, Offset, Type, Id, Dlc, Data],
+            TrcVersion::V1_2 => vec![Number, Offset, Bus, Type, Id, Dlc, Data],
+            TrcVersion::V1_3 => vec![Number, Offset, Bus, Type, Id, Reserved, Dlc, Data],
+            TrcVersion::V2_0 => vec![Number, Offset, Type, Id, Direction, Length, Data],
+            TrcVersion::V2_1 => {
+                vec![
+                    Number, Offset, Type, Bus, Id, Direction, Reserved, Dlc, Data,
+                ]
+            }
+        }
+    }
+}
+
+impl fmt::Display for TrcVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use TrcVersion::*;
+        let s = match *self {
+            V1_0 => "1.0",
+            V1_1 => "1.1",
+            V1_2 => "1.2",
+            V1_3 => "1.3",
+            V2_0 => "2.0",
+            V2_1 => "2.1",
+        };
+        write!(f, "{}", s)
+    }
+}
+
+impl FromStr for TrcVersion {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use TrcVersion::*;
+        match s.trim() {
+            "1.0" => Ok(V1_0),
+            "1.1" => Ok(V1_1),
+            "1.2" => Ok(V1_2),
+            "1.3" => Ok(V1_3),
+            "2.0" => Ok(V2_0),
+            "2.1" => Ok(V2_1),
+            s => Err(ParseError::UnsupportedVersion(s.into())),
+        }
+    }
+}
+
+/// A column in a TRC file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    /// Message number
+    Number,
+    /// Time offset from the start, in ms
+    Offset,
+    /// Message type
+    Type,
+    /// Bus (channel) number
+    Bus,
+    /// CAN ID, in hex
+    Id,
+    /// Direction (Rx/Tx)
+    Direction,
+    /// Reserved
+    Reserved,
+    /// Data length code
+    Dlc,
+    /// Data length, in bytes
+    Length,
+    /// Data bytes, in hex
+    Data,
+}
+
+impl TryFrom<&str> for Column {
+    type Error = ParseError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        use Column::*;
+        match s.trim() {
+            "N" => Ok(Number),
+            "O" => Ok(Offset),
+            "T" => Ok(Type),
+            "B" => Ok(Bus),
+            "I" => Ok(Id),
+            "d" => Ok(Direction),
+            "R" => Ok(Reserved),
+            "L" => Ok(Dlc),
+            "l" => Ok(Length),
+            "D" => Ok(Data),
+            _ => Err(ParseError::InvalidColumns),
+        }
+    }
+}
+
+// ===== TrcRecord =====
+
+/// A single CAN message recorded in a TRC file.
+#[derive(Debug, Clone, Copy)]
+pub struct TrcRecord {
+    /// The timestamp, in microseconds since the Unix epoch
+    pub t_us: u64,
+    /// The bus (channel) number, if recorded by the file version
+    pub bus: Option<u8>,
+    /// Whether the message was received or transmitted
+    pub direction: Direction,
+    /// The CAN frame
+    pub frame: CanAnyFrame,
+}
+
+impl From<TrcRecord> for LogRecord {
+    /// Converts to a generic log record.
+    ///
+    /// The interface name is the bus number, if any.
+    fn from(rec: TrcRecord) -> Self {
+        LogRecord {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_micros(rec.t_us),
+            interface: rec.bus.map(|bus| bus.to_string()).unwrap_or_default(),
+            direction: rec.direction,
+            frame: rec.frame,
+        }
+    }
+}
+
+impl From<&LogRecord> for TrcRecord {
+    /// Converts from a generic log record.
+    ///
+    /// The bus number is taken from the interface name, if it is a number.
+    fn from(rec: &LogRecord) -> Self {
+        let t_us = rec
+            .timestamp
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        TrcRecord {
+            t_us,
+            bus: rec.interface.parse().ok(),
+            direction: rec.direction,
+            frame: rec.frame,
+        }
+    }
+}
+
+// ===== ParseError =====
+
+/// TRC file parse error
+#[derive(Error, Debug)]
+pub enum ParseError {
+    /// I/O Error
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// The file version is not supported
+    #[error("unsupported TRC file version: {0}")]
+    UnsupportedVersion(String),
+    /// The start time in the header could not be parsed
+    #[error("invalid start time")]
+    InvalidStartTime,
+    /// The column definition in the header could not be parsed
+    #[error("invalid column definition")]
+    InvalidColumns,
+    /// Unexpected end of line
+    #[error("unexpected end of line")]
+    UnexpectedEndOfLine,
+    /// Invalid time offset
+    #[error("invalid time offset")]
+    InvalidTimestamp,
+    /// Invalid message type
+    #[error("invalid message type")]
+    InvalidMessageType,
+    /// Invalid CAN frame
+    #[error("invalid CAN frame")]
+    InvalidCanFrame,
+    /// Error creating the frame
+    #[error(transparent)]
+    ConstructionError(#[from] ConstructionError),
+}
+
+// ===== Reader =====
+
+/// The kind of message in a line of the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MsgKind {
+    Data,
+    Remote,
+    Error,
+    Fd(FdFlags),
+}
+
+/// A TRC trace file reader.
+#[derive(Debug)]
+pub struct Reader<R> {
+    rdr: R,
+    line_buf: String,
+    // The first data line, read while parsing the header
+    pending: bool,
+    version: TrcVersion,
+    columns: Vec<Column>,
+    start_us: u64,
+}
+
+impl<R: io::Read> Reader<R> {
+    /// Creates an I/O buffered reader from a TRC file reader.
+    ///
+    /// This reads and parses the header of the file.
+    pub fn from_reader(rdr: R) -> Result<Reader<io::BufReader<R>>, ParseError> {
+        Reader::new(io::BufReader::new(rdr))
+    }
+}
+
+impl Reader<fs::File> {
+    /// Creates an I/O buffered reader from a file.
+    pub fn from_file<P>(path: P) -> Result<Reader<io::BufReader<fs::File>>, ParseError>
+    where
+        P: AsRef<path::Path>,
+    {
+        Reader::from_reader(fs::File::open(path)?)
+    }
+}
+
+impl<R: BufRead> Reader<R> {
+    /// Creates a reader from a buffered reader, parsing the file header.
+    pub fn new(rdr: R) -> Result<Self, ParseError> {
+        let mut reader = Self {
+            rdr,
+            line_buf: String::new(),
+            pending: false,
+            version: TrcVersion::V1_0,
+            columns: Vec::new(),
+            start_us: 0,
+        };
+        reader.read_header()?;
+        Ok(reader)
+    }
+
+    /// Gets the version of the file, as read from the header.
+    pub fn version(&self) -> TrcVersion {
+        self.version
+    }
+
+    /// Gets the start time of the trace, in microseconds since the Unix
+    /// epoch.
+    ///
+    /// This is zero if the file did not contain a start time.
+    pub fn start_time_us(&self) -> u64 {
+        self.start_us
+    }
+
+    /// Reads the comment lines at the top of the file, looking for the
+    /// version, start time, and column definitions.
+    fn read_header(&mut self) -> Result<(), ParseError> {
+        loop {
+            self.line_buf.clear();
+            if self.rdr.read_line(&mut self.line_buf)? == 0 {
+                break;
+            }
+            let line = self.line_buf.trim();
+
+            if let Some(comment) = line.strip_prefix(';') {
+                if let Some((key, val)) = comment.trim().split_once('=') {
+                    match key {
+                        "$FILEVERSION" => self.version = val.parse()?,
+                        "$STARTTIME" => {
+                            self.start_us =
+                                parse_start_time(val.trim()).ok_or(ParseError::InvalidStartTime)?;
+                        }
+                        "$COLUMNS" => {
+                            self.columns = val
+                                .split(',')
+                                .map(Column::try_from)
+                                .collect::<Result<_, _>>()?;
+                        }
+                        _ => (),
+                    }
+                }
+            } else if !line.is_empty() {
+                self.pending = true;
+                break;
+            }
+        }
+
+        if self.columns.is_empty() {
+            self.columns = self.version.columns();
+        }
+        Ok(())
+    }
+
+    /// Returns an iterator over all records
+    pub fn records(&mut self) -> TrcRecords<'_, R> {
+        TrcRecords { src: self }
+    }
+
+    /// Advance state, returning next record.
+    ///
+    /// Status, event, and warning lines that don't hold a CAN frame are
+    /// skipped.
+    pub fn next_record(&mut self) -> Result<Option<TrcRecord>, ParseError> {
+        loop {
+            if self.pending {
+                self.pending = false;
+            } else {
+                self.line_buf.clear();
+                if self.rdr.read_line(&mut self.line_buf)? == 0 {
+                    return Ok(None);
+                }
+            }
+
+            let line = self.line_buf.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            if let Some(rec) = self.parse_line(line)? {
+                return Ok(Some(rec));
+            }
+        }
+    }
+
+    /// Parses a single message line from the file.
+    fn parse_line(&self, line: &str) -> Result<Option<TrcRecord>, ParseError> {
+        let v2 = self.version >= TrcVersion::V2_0;
+        let mut toks = line.split_whitespace();
+
+        let mut t_us = self.start_us;
+        let mut bus = None;
+        let mut direction = Direction::Rx;
+        let mut kind = MsgKind::Data;
+        let mut id = None;
+        let mut dlc = None;
+        let mut len = None;
+        let mut data = Vec::new();
+
+        for col in &self.columns {
+            if *col == Column::Data {
+                for tok in toks.by_ref() {
+                    match tok {
+                        "RTR" if !v2 => kind = MsgKind::Remote,
+                        tok => data.push(
+                            u8::from_str_radix(tok, 16).map_err(|_| ParseError::InvalidCanFrame)?,
+                        ),
+                    }
+                }
+                break;
+            }
+
+            let tok = toks.next().ok_or(ParseError::UnexpectedEndOfLine)?;
+
+            match col {
+                Column::Offset => {
+                    let offset = parse_offset_us(tok).ok_or(ParseError::InvalidTimestamp)?;
+                    t_us = t_us.saturating_add(offset);
+                }
+                Column::Type if v2 => {
+                    kind = match tok {
+                        "DT" => MsgKind::Data,
+                        "RR" => MsgKind::Remote,
+                        "ER" => MsgKind::Error,
+                        "FD" => MsgKind::Fd(FdFlags::empty()),
+                        "FB" => MsgKind::Fd(FdFlags::BRS),
+                        "FE" => MsgKind::Fd(FdFlags::ESI),
+                        "BI" => MsgKind::Fd(FdFlags::BRS | FdFlags::ESI),
+                        // Status, error counter, and event lines have no frame
+                        "ST" | "EC" | "EV" => return Ok(None),
+                        _ => return Err(ParseError::InvalidMessageType),
+                    };
+                }
+                Column::Type => match tok {
+                    "Rx" => direction = Direction::Rx,
+                    "Tx" => direction = Direction::Tx,
+                    "Error" => kind = MsgKind::Error,
+                    "Warng" => return Ok(None),
+                    _ => return Err(ParseError::InvalidMessageType),
+                },
+                Column::Direction => {
+                    direction = match tok {
+                        "Rx" => Direction::Rx,
+                        "Tx" => Direction::Tx,
+                        _ => return Err(ParseError::InvalidMessageType),
+                    };
+                }
+                Column::Bus => {
+                    bus = Some(tok.parse().map_err(|_| ParseError::InvalidCanFrame)?);
+                }
+                // Error frames have no ID in 2.x files
+                Column::Id if tok != "-" => {
+                    let raw =
+                        u32::from_str_radix(tok, 16).map_err(|_| ParseError::InvalidCanFrame)?;
+                    // PEAK writes 4 hex digits for standard ID's and 8
+                    // for extended ones.
+                    id = Some(make_id(raw, tok.len() > 4)?);
+                }
+                Column::Dlc => {
+                    dlc = Some(
+                        tok.parse::<usize>()
+                            .map_err(|_| ParseError::InvalidCanFrame)?,
+                    );
+                }
+                Column::Length => {
+                    len = Some(
+                        tok.parse::<usize>()
+                            .map_err(|_| ParseError::InvalidCanFrame)?,
+                    );
+                }
+                _ => (),
+            }
+        }
+
+        let frame: CanAnyFrame = match kind {
+            MsgKind::Error => CanAnyFrame::Error(error_frame_from_peak(&data)?),
+            MsgKind::Remote => {
+                let id = id.ok_or(ParseError::InvalidCanFrame)?;
+                let dlc = dlc.or(len).unwrap_or(0);
+                CanAnyFrame::Remote(
+                    CanRemoteFrame::new_remote(id, dlc).ok_or(ConstructionError::TooMuchData)?,
+                )
+            }
+            MsgKind::Data => {
+                let id = id.ok_or(ParseError::InvalidCanFrame)?;
+                let n = len.or(dlc.map(|dlc| dlc.min(CAN_MAX_DLEN))).unwrap_or(0);
+                if n != data.len() {
+                    return Err(ParseError::InvalidCanFrame);
+                }
+                let mut frame =
+                    CanDataFrame::new(id, &data).ok_or(ConstructionError::TooMuchData)?;
+                // keep a raw DLC above 8 for a frame with 8 bytes
+                if let Some(dlc @ 9..=CAN_MAX_RAW_DLC) = dlc {
+                    frame.set_len8_dlc(Some(dlc))?;
+                }
+                CanAnyFrame::Normal(frame)
+            }
+            MsgKind::Fd(flags) => {
+                let id = id.ok_or(ParseError::InvalidCanFrame)?;
+                let n = len.or(dlc.and_then(dlc_to_len)).unwrap_or(0);
+                if n != data.len() {
+                    return Err(ParseError::InvalidCanFrame);
+                }
+                CanFdFrame::with_flags(id, &data, flags)
+                    .ok_or(ConstructionError::TooMuchData)?
+                    .into()
+            }
+        };
+
+        Ok(Some(TrcRecord {
+            t_us,
+            bus,
+            direction,
+            frame,
+        }))
+    }
+}
+
+/// Record iterator
+#[derive(Debug)]
+pub struct TrcRecords<'a, R: 'a> {
+    src: &'a mut Reader<R>,
+}
+
+impl<R: BufRead> Iterator for TrcRecords<'_, R> {
+    type Item = Result<TrcRecord, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.src.next_record().transpose()
+    }
+}
+
+impl<R: BufRead> LogReader for Reader<R> {
+    fn read_record(&mut self) -> Result<Option<LogRecord>, logfile::Error> {
+        Ok(self.next_record()?.map(LogRecord::from))
+    }
+}
+
+/// Parses a time offset in milliseconds, like "1841.3" into microseconds.
+fn parse_offset_us(s: &str) -> Option<u64> {
+    let (ms, frac) = s.split_once('.').unwrap_or((s, ""));
+    let ms = ms.parse::<u64>().ok()?;
+
+    let mut us = 0;
+    for (i, c) in frac.chars().enumerate() {
+        let digit = c.to_digit(10)? as u64;
+        if i < 3 {
+            us += digit * 10u64.pow(2 - i as u32);
+        }
+    }
+    Some(ms * 1000 + us)
+}
+
+/// Creates an ID of the specified type from a raw integer value.
+fn make_id(raw: u32, extended: bool) -> Result<Id, ParseError> {
+    let id: Option<Id> = if extended || raw > StandardId::MAX.as_raw() as u32 {
+        ExtendedId::new(raw).map(Id::from)
+    } else {
+        StandardId::new(raw as u16).map(Id::from)
+    };
+    id.ok_or(ParseError::InvalidCanFrame)
+}
+
+/// Creates a SocketCAN error frame from the data bytes of a PEAK error
+/// frame record.
+///
+/// The PEAK record has the error type, the direction, the error code
+/// capture, and the RX and TX error counters.
+fn error_frame_from_peak(data: &[u8]) -> Result<CanErrorFrame, ParseError> {
+    if data.len() < PEAK_ERR_LEN {
+        return Err(ParseError::InvalidCanFrame);
+    }
+
+    let mut vtype = match data[0] {
+        PEAK_ERR_BIT => CAN_ERR_PROT_BIT,
+        PEAK_ERR_FORM => CAN_ERR_PROT_FORM,
+        PEAK_ERR_STUFF => CAN_ERR_PROT_STUFF,
+        _ => 0,
+    };
+    if data[1] == 0 {
+        vtype |= CAN_ERR_PROT_TX;
+    }
+
+    let mut err_data = [0u8; CAN_MAX_DLEN];
+    err_data[2] = vtype;
+    err_data[6] = data[4];
+    err_data[7] = data[3];
+
+    Ok(CanErrorFrame::new_error(
+        CAN_ERR_PROT | CAN_ERR_CNT,
+        &err_data,
+    )?)
+}
+
+/// Creates the data bytes of a PEAK error frame record from a SocketCAN
+/// error frame.
+fn error_frame_to_peak(frame: &CanErrorFrame) -> [u8; PEAK_ERR_LEN] {
+    let data = frame.data();
+    let vtype = if frame.error_bits() & CAN_ERR_PROT != 0 {
+        data[2]
+    } else {
+        0
+    };
+
+    let err_type = match vtype & !CAN_ERR_PROT_TX {
+        CAN_ERR_PROT_BIT => PEAK_ERR_BIT,
+        CAN_ERR_PROT_FORM => PEAK_ERR_FORM,
+        CAN_ERR_PROT_STUFF => PEAK_ERR_STUFF,
+        _ => PEAK_ERR_OTHER,
+    };
+    let dir = if vtype & CAN_ERR_PROT_TX != 0 { 0 } else { 1 };
+
+    let (txerr, rxerr) = if frame.error_bits() & CAN_ERR_CNT != 0 {
+        (data[6], data[7])
+    } else {
+        (0, 0)
+    };
+
+    [err_type, dir, 0, rxerr, txerr]
+}
+
+// ===== Writer =====
+
+/// A TRC trace file writer.
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    wtr: W,
+    version: TrcVersion,
+    start_us: u64,
+    count: u64,
+}
+
+impl Writer<io::BufWriter<fs::File>> {
+    /// Creates a buffered writer to a new file.
+    ///
+    /// See [`Writer::new`]
+    pub fn create<P>(path: P, version: TrcVersion, start_us: u64) -> io::Result<Self>
+    where
+        P: AsRef<path::Path>,
+    {
+        Self::new(
+            io::BufWriter::new(fs::File::create(path)?),
+            version,
+            start_us,
+        )
+    }
+}
+
+impl<W: Write> Writer<W> {
+    /// Creates a TRC writer, and writes out the file header.
+    ///
+    /// The `start_us` is the start time of the trace, in microseconds since
+    /// the Unix epoch. The timestamp of each record is written as an offset
+    /// from it.
+    pub fn new(mut wtr: W, version: TrcVersion, start_us: u64) -> io::Result<Self> {
+        if version >= TrcVersion::V1_1 {
+            writeln!(wtr, ";$FILEVERSION={}", version)?;
+            writeln!(wtr, ";$STARTTIME={}", fmt_start_time(start_us))?;
+        }
+        if version >= TrcVersion::V2_0 {
+            let cols = if version == TrcVersion::V2_0 {
+                "N,O,T,I,d,l,D"
+            } else {
+                "N,O,T,B,I,d,R,L,D"
+            };
+            writeln!(wtr, ";$COLUMNS={}", cols)?;
+        }
+        writeln!(wtr, ";")?;
+        writeln!(wtr, ";   Generated by socketcan-rs")?;
+        writeln!(wtr, ";")?;
+
+        Ok(Self {
+            wtr,
+            version,
+            start_us,
+            count: 0,
+        })
+    }
+
+    /// Gets the version of the file being written.
+    pub fn version(&self) -> TrcVersion {
+        self.version
+    }
+
+    /// Writes a record to the file.
+    ///
+    /// This fails with an `InvalidInput` error if the frame can not be
+    /// represented in the file version, like an FD frame in a 1.x file,
+    /// or an error frame in a 1.0 file.
+    pub fn write_record(&mut self, rec: &TrcRecord) -> io::Result<()> {
+        use CanAnyFrame::*;
+
+        let invalid = || io::Error::from(io::ErrorKind::InvalidInput);
+        let v2 = self.version >= TrcVersion::V2_0;
+
+        if let Fd(_) = rec.frame {
+            if !v2 {
+                return Err(invalid());
+            }
+        }
+        if let Error(_) = rec.frame {
+            if self.version == TrcVersion::V1_0 {
+                return Err(invalid());
+            }
+        }
+
+        let offset = rec.t_us.saturating_sub(self.start_us);
+        let (ms, us) = (offset / 1000, offset % 1000);
+        let bus = rec.bus.unwrap_or(1);
+
+        let id = match rec.frame {
+            Error(_) if v2 => "-".to_string(),
+            Error(_) => "0000".to_string(),
+            Normal(f) => fmt_id(&f),
+            Remote(f) => fmt_id(&f),
+            Fd(f) => fmt_id(&f),
+        };
+
+        // The data length code and data bytes
+        let (dlc, len, data): (usize, usize, Vec<u8>) = match rec.frame {
+            Normal(f) => (f.raw_dlc(), f.len(), f.data().to_vec()),
+            Remote(f) => (f.dlc(), f.dlc(), Vec::new()),
+            Error(f) => (PEAK_ERR_LEN, PEAK_ERR_LEN, error_frame_to_peak(&f).to_vec()),
+            Fd(f) => (f.raw_dlc(), f.len(), f.data().to_vec()),
+        };
+        let mut data = data
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>();
+        if let Remote(_) = rec.frame {
+            if !v2 {
+                data.push("RTR".into());
+            }
+        }
+        let data = data.join(" ");
+
+        self.count += 1;
+        let n = self.count;
+
+        match self.version {
+            TrcVersion::V1_0 => {
+                writeln!(
+                    self.wtr,
+                    "{:>6}) {:>11}  {:>8}  {}  {}",
+                    n, ms, id, dlc, data
+                )
+            }
+            TrcVersion::V1_1 | TrcVersion::V1_2 | TrcVersion::V1_3 => {
+                let typ = match rec.frame {
+                    Error(_) => "Error".to_string(),
+                    _ => rec.direction.to_string(),
+                };
+                let offset = format!("{}.{}", ms, us / 100);
+                match self.version {
+                    TrcVersion::V1_1 => writeln!(
+                        self.wtr,
+                        "{:>6}) {:>11}  {:<5} {:>8}  {}  {}",
+                        n, offset, typ, id, dlc, data
+                    ),
+                    TrcVersion::V1_2 => writeln!(
+                        self.wtr,
+                        "{:>6}) {:>11} {} {:<5} {:>8}  {}  {}",
+                        n, offset, bus, typ, id, dlc, data
+                    ),
+                    _ => writeln!(
+                        self.wtr,
+                        "{:>6}) {:>11} {} {:<5} {:>8} -  {}  {}",
+                        n, offset, bus, typ, id, dlc, data
+                    ),
+                }
+            }
+            TrcVersion::V2_0 | TrcVersion::V2_1 => {
+                let typ = match rec.frame {
+                    Normal(_) => "DT",
+                    Remote(_) => "RR",
+                    Error(_) => "ER",
+                    Fd(f) => match (f.is_brs(), f.is_esi()) {
+                        (false, false) => "FD",
+                        (true, false) => "FB",
+                        (false, true) => "FE",
+                        (true, true) => "BI",
+                    },
+                };
+                let offset = format!("{}.{:03}", ms, us);
+                let dir = rec.direction;
+                if self.version == TrcVersion::V2_0 {
+                    writeln!(
+                        self.wtr,
+                        "{:>7} {:>13} {} {:>8} {} {:<2} {}",
+                        n, offset, typ, id, dir, len, data
+                    )
+                } else {
+                    writeln!(
+                        self.wtr,
+                        "{:>7} {:>13} {} {} {:>8} {} -  {:<4} {}",
+                        n, offset, typ, bus, id, dir, dlc, data
+                    )
+                }
+            }
+        }
+    }
+
+    /// Flushes the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.wtr.flush()
+    }
+
+    /// Consumes the writer, returning the underlying I/O writer.
+    pub fn into_inner(self) -> W {
+        self.wtr
+    }
+}
+
+impl<W: Write> LogWriter for Writer<W> {
+    fn write_record(&mut self, rec: &LogRecord) -> io::Result<()> {
+        Writer::write_record(self, &TrcRecord::from(rec))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.wtr.flush()
+    }
+}
+
+/// Formats the ID of a frame the way PEAK does, with 4 hex digits for
+/// a standard ID and 8 for an extended one.
+fn fmt_id<F: Frame>(frame: &F) -> String {
+    if frame.id_word() & CAN_EFF_FLAG != 0 {
+        format!("{:08X}", frame.raw_id())
+    } else {
+        debug_assert!(frame.id_word() & CAN_ERR_FLAG == 0);
+        format!("{:04X}", frame.raw_id())
+    }
+}
+
+/////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CanError;
+
+    // 2023-10-18 12:00:00 UTC
+    const START_US: u64 = 1_697_630_400_000_000;
+
+    #[test]
+    fn test_v1_1() {
+        let input: &[u8] = b";$FILEVERSION=1.1\n\
+            ;$STARTTIME=45217.5\n\
+            ;\n\
+            ;   Message Number\n\
+            ;---+--   ----+----  --+--  ----+---  +  -+ -- -- --\n\
+                 1)      1841.0  Rx         0001  8  00 01 02 03 04 05 06 07\n\
+                 2)      1842.3  Tx     0100FFFF  2  AA BB\n\
+                 3)      1843.0  Rx         0010  4  RTR\n\
+                 4)      1844.0  Warng  FFFFFFFF  4  00 00 00 08  BUSHEAVY\n";
+
+        let mut reader = Reader::from_reader(input).unwrap();
+        assert_eq!(TrcVersion::V1_1, reader.version());
+        assert_eq!(START_US, reader.start_time_us());
+
+        let rec = reader.next_record().unwrap().unwrap();
+        assert_eq!(START_US + 1_841_000, rec.t_us);
+        assert_eq!(None, rec.bus);
+        assert_eq!(Direction::Rx, rec.direction);
+        match rec.frame {
+            CanAnyFrame::Normal(frame) => {
+                assert_eq!(0x0001, frame.raw_id());
+                assert!(!frame.is_extended());
+                assert_eq!(&[0, 1, 2, 3, 4, 5, 6, 7], frame.data());
+            }
+            _ => panic!("Expected data frame"),
+        }
+
+        let rec = reader.next_record().unwrap().unwrap();
+        assert_eq!(START_US + 1_842_300, rec.t_us);
+        assert_eq!(Direction::Tx, rec.direction);
+        match rec.frame {
+            CanAnyFrame::Normal(frame) => {
+                assert_eq!(0x0100FFFF, frame.raw_id());
+                assert!(frame.is_extended());
+                assert_eq!(&[0xAA, 0xBB], frame.data());
+            }
+            _ => panic!("Expected data frame"),
+        }
+
+        let rec = reader.next_record().unwrap().unwrap();
+        match rec.frame {
+            CanAnyFrame::Remote(frame) => {
+                assert_eq!(0x0010, frame.raw_id());
+                assert_eq!(4, frame.dlc());
+            }
+            _ => panic!("Expected remote frame"),
+        }
+
+        // The warning line is skipped
+        assert!(reader.next_record().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_v1_3() {
+        let input: &[u8] = b";$FILEVERSION=1.3\n\
+            ;$STARTTIME=45217.5\n\
+                 1)      1841.0 2  Rx         0123 -  3  01 02 03\n";
+
+        let mut reader = Reader::from_reader(input).unwrap();
+        let rec = reader.next_record().unwrap().unwrap();
+        assert_eq!(Some(2), rec.bus);
+        assert_eq!(0x123, rec.frame_raw_id());
+    }
+
+    #[test]
+    fn test_v2_0() {
+        let input: &[u8] = b";$FILEVERSION=2.0\n\
+            ;$STARTTIME=45217.5\n\
+            ;$COLUMNS=N,O,T,I,d,l,D\n\
+            ;\n\
+                  1      1059.900 DT     0300 Rx 7  00 00 00 00 04 00 00\n\
+                  2      1060.012 FB 18FF0001 Tx 12 00 01 02 03 04 05 06 07 08 09 0A 0B\n\
+                  3      1061.000 ST          Rx    00 00 00 08\n\
+                  4      1062.000 RR     0123 Rx 3\n\
+                  5      1063.000 ER        - Rx 5  04 01 00 10 20\n";
+
+        let mut reader = Reader::from_reader(input).unwrap();
+        assert_eq!(TrcVersion::V2_0, reader.version());
+
+        let rec = reader.next_record().unwrap().unwrap();
+        assert_eq!(START_US + 1_059_900, rec.t_us);
+        match rec.frame {
+            CanAnyFrame::Normal(frame) => {
+                assert_eq!(0x0300, frame.raw_id());
+                assert_eq!(7, frame.len());
+            }
+            _ => panic!("Expected data frame"),
+        }
+
+        let rec = reader.next_record().unwrap().unwrap();
+        assert_eq!(START_US + 1_060_012, rec.t_us);
+        assert_eq!(Direction::Tx, rec.direction);
+        match rec.frame {
+            CanAnyFrame::Fd(frame) => {
+                assert_eq!(0x18FF0001, frame.raw_id());
+                assert!(frame.is_extended());
+                assert!(frame.is_brs());
+                assert!(!frame.is_esi());
+                assert_eq!(12, frame.len());
+            }
+            _ => panic!("Expected FD frame"),
+        }
+
+        // The status line is skipped
+        let rec = reader.next_record().unwrap().unwrap();
+        match rec.frame {
+            CanAnyFrame::Remote(frame) => assert_eq!(3, frame.dlc()),
+            _ => panic!("Expected remote frame"),
+        }
+
+        let rec = reader.next_record().unwrap().unwrap();
+        match rec.frame {
+            CanAnyFrame::Error(frame) => {
+                let data = frame.data();
+                assert_eq!(CAN_ERR_PROT_STUFF, data[2]);
+                assert_eq!(0x20, data[6]);
+                assert_eq!(0x10, data[7]);
+                assert!(matches!(frame.into_error(), CanError::Unknown(_)));
+            }
+            _ => panic!("Expected error frame"),
+        }
+
+        assert!(reader.next_record().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_v2_1() {
+        let input: &[u8] = b";$FILEVERSION=2.1\n\
+            ;$STARTTIME=45217.5\n\
+            ;$COLUMNS=N,O,T,B,I,d,R,L,D\n\
+                  1         0.000 DT 1     0123 Rx -  2    11 22\n\
+                  2         1.250 BI 2 18FF0001 Tx -  9    00 01 02 03 04 05 06 07 08 09 0A 0B\n";
+
+        let mut reader = Reader::from_reader(input).unwrap();
+
+        let rec = reader.next_record().unwrap().unwrap();
+        assert_eq!(START_US, rec.t_us);
+        assert_eq!(Some(1), rec.bus);
+
+        let rec = reader.next_record().unwrap().unwrap();
+        assert_eq!(START_US + 1_250, rec.t_us);
+        assert_eq!(Some(2), rec.bus);
+        match rec.frame {
+            CanAnyFrame::Fd(frame) => {
+                assert!(frame.is_brs());
+                assert!(frame.is_esi());
+                assert_eq!(12, frame.len());
+            }
+            _ => panic!("Expected FD frame"),
+        }
+    }
+
+    #[test]
+    fn test_bad_line() {
+        let input: &[u8] = b";$FILEVERSION=2.0\n\
+                  1      1059.900 DT     0300 Rx 7  00 00\n";
+
+        let mut reader = Reader::from_reader(input).unwrap();
+        assert!(matches!(
+            reader.next_record(),
+            Err(ParseError::InvalidCanFrame)
+        ));
+
+        let input: &[u8] = b";$FILEVERSION=3.0\n";
+        assert!(matches!(
+            Reader::from_reader(input),
+            Err(ParseError::UnsupportedVersion(_))
+        ));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data_frame = CanDataFrame::new(StandardId::new(0x123).unwrap(), &[1, 2, 3]).unwrap();
+        let mut len8_frame =
+            CanDataFrame::new(ExtendedId::new(0x1234).unwrap(), &[0x11; 8]).unwrap();
+        len8_frame.set_len8_dlc(Some(0xC)).unwrap();
+        let remote_frame = CanRemoteFrame::new_remote(ExtendedId::new(0x50).unwrap(), 2).unwrap();
+        let err_frame = error_frame_from_peak(&[PEAK_ERR_FORM, 0, 0, 3, 4]).unwrap();
+        let fd_frame =
+            CanFdFrame::with_flags(StandardId::new(0x7FF).unwrap(), &[0xAA; 21], FdFlags::BRS)
+                .unwrap();
+
+        let recs = [
+            TrcRecord {
+                t_us: START_US + 1_000,
+                bus: Some(1),
+                direction: Direction::Rx,
+                frame: CanAnyFrame::Normal(data_frame),
+            },
+            TrcRecord {
+                t_us: START_US + 1_500,
+                bus: Some(1),
+                direction: Direction::Tx,
+                frame: CanAnyFrame::Normal(len8_frame),
+            },
+            TrcRecord {
+                t_us: START_US + 2_500,
+                bus: Some(1),
+                direction: Direction::Tx,
+                frame: CanAnyFrame::Remote(remote_frame),
+            },
+            TrcRecord {
+                t_us: START_US + 3_000,
+                bus: Some(1),
+                direction: Direction::Rx,
+                frame: CanAnyFrame::Error(err_frame),
+            },
+            TrcRecord {
+                t_us: START_US + 4_123,
+                bus: Some(1),
+                direction: Direction::Rx,
+                frame: fd_frame.into(),
+            },
+        ];
+
+        use TrcVersion::*;
+        for version in [V1_0, V1_1, V1_2, V1_3, V2_0, V2_1] {
+            // 1.0 files can't hold error frames
+            let skip = |frame: &CanAnyFrame| match frame {
+                CanAnyFrame::Fd(_) => !version.supports_fd(),
+                CanAnyFrame::Error(_) => version == V1_0,
+                _ => false,
+            };
+
+            let mut wtr = Writer::new(Vec::new(), version, START_US).unwrap();
+            for rec in &recs {
+                match wtr.write_record(rec) {
+                    Err(_) if skip(&rec.frame) => (),
+                    res => res.unwrap(),
+                }
+            }
+            let buf = wtr.into_inner();
+
+            let mut reader = Reader::from_reader(buf.as_slice()).unwrap();
+            assert_eq!(version, reader.version());
+
+            for rec in &recs {
+                if skip(&rec.frame) {
+                    continue;
+                }
+                let rd = reader.next_record().unwrap().unwrap();
+
+                // 1.0 files have no start time and only keep 1 ms of
+                // resolution, and the other 1.x files keep 0.1 ms
+                match version {
+                    V1_0 => assert_eq!((rec.t_us - START_US) / 1000, rd.t_us / 1000),
+                    V2_0 | V2_1 => assert_eq!(rec.t_us, rd.t_us),
+                    _ => assert_eq!(rec.t_us / 100, rd.t_us / 100),
+                }
+                if version != V1_0 && version != V1_1 && version != V2_0 {
+                    assert_eq!(rec.bus, rd.bus);
+                }
+                if version != V1_0 && !matches!(rec.frame, CanAnyFrame::Error(_)) {
+                    assert_eq!(rec.direction, rd.direction);
+                }
+
+                match (rec.frame, rd.frame) {
+                    (CanAnyFrame::Normal(a), CanAnyFrame::Normal(b)) => {
+                        assert_eq!(a.id(), b.id());
+                        assert_eq!(a.data(), b.data());
+                        // 2.0 files have a length column, but no DLC
+                        if version != V2_0 {
+                            assert_eq!(a.raw_dlc(), b.raw_dlc());
+                        }
+                    }
+                    (CanAnyFrame::Remote(a), CanAnyFrame::Remote(b)) => {
+                        assert_eq!(a.id(), b.id());
+                        assert_eq!(a.dlc(), b.dlc());
+                    }
+                    (CanAnyFrame::Error(a), CanAnyFrame::Error(b)) => {
+                        assert_eq!(a.data(), b.data());
+                    }
+                    (CanAnyFrame::Fd(a), CanAnyFrame::Fd(b)) => {
+                        assert_eq!(a.id(), b.id());
+                        assert_eq!(a.flags(), b.flags());
+                        assert_eq!(&a.data()[..21], &b.data()[..21]);
+                        assert_eq!(24, b.len());
+                    }
+                    _ => panic!("Frame type mismatch"),
+                }
+            }
+            assert!(reader.next_record().unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn test_start_time() {
+        assert_eq!("45217.500000000000", fmt_start_time(START_US));
+        assert_eq!(Some(START_US), parse_start_time("45217.5"));
+        assert_eq!(Some(0), parse_start_time("25569"));
+        assert_eq!(Some(0), parse_start_time("100.25"));
+        assert_eq!(None, parse_start_time("-45217.5"));
+        assert_eq!(None, parse_start_time("45217.5x"));
+
+        // the start time keeps the full resolution of the timestamps
+        for us in [START_US + 1, START_US + 7, START_US + 86_399_999_999] {
+            let wtr = Writer::new(Vec::new(), TrcVersion::V2_1, us).unwrap();
+            let buf = wtr.into_inner();
+            let reader = Reader::from_reader(buf.as_slice()).unwrap();
+            assert_eq!(us, reader.start_time_us());
+        }
+    }
+
+    impl TrcRecord {
+        fn frame_raw_id(&self) -> u32 {
+            match self.frame {
+                CanAnyFrame::Normal(f) => f.raw_id(),
+                CanAnyFrame::Remote(f) => f.raw_id(),
+                CanAnyFrame::Error(f) => f.raw_id(),
+                CanAnyFrame::Fd(f) => f.raw_id(),
+            }
+        }
+    }
+}
@@ -63,3 +63,26 @@ async fn async_canfd_simple() {
         _ => panic!("Did not get FD frame back!"),
     }
 }
+
+#[cfg(all(feature = "netlink_tests", feature = "async-io"))]
+#[async_std::test]
+async fn async_interface() {
+    use socketcan::{async_io::CanInterface, nl::SetCanParams};
+
+    let iface = CanInterface::open("lo").unwrap();
+    let expected = iface.get_ref().details().unwrap();
+
+    let details = iface.details().await.unwrap();
+    assert_eq!(expected.index, details.index);
+    assert_eq!(expected.name, details.name);
+    assert!(details.is_up);
+
+    iface.bring_up().await.unwrap();
+
+    // The loopback isn't a CAN interface, so the kernel refuses this
+    let params = SetCanParams {
+        restart_ms: Some(100),
+        ..SetCanParams::default()
+    };
+    assert!(iface.set_can_params(&params).await.is_err());
+}